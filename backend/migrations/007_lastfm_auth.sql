@@ -0,0 +1,32 @@
+-- Last.fm Web Authentication
+-- Run after 006_push_subscriptions.sql
+
+-- Pending auth.getToken tokens, bound to the user that requested them
+CREATE TABLE IF NOT EXISTS lastfm_auth_tokens (
+    token VARCHAR(64) PRIMARY KEY,
+    user_id CHAR(36) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    INDEX idx_lastfm_tokens_user (user_id)
+);
+
+-- Authorized Last.fm sessions (session keys are encrypted at rest)
+CREATE TABLE IF NOT EXISTS lastfm_sessions (
+    user_id CHAR(36) PRIMARY KEY,
+    lastfm_username VARCHAR(100) NOT NULL,
+    session_key_encrypted VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    UNIQUE KEY unique_lastfm_username (lastfm_username)
+);
+
+-- Usernames were previously typed in by clients and can't be trusted: clear those without a
+-- session, along with the listening data synced from them
+DELETE sc FROM scrobbles_cache sc
+JOIN users u ON u.id = sc.user_id
+WHERE u.lastfm_username IS NOT NULL
+AND NOT EXISTS (SELECT 1 FROM lastfm_sessions ls WHERE ls.user_id = u.id);
+UPDATE users u SET lastfm_username = NULL, lastfm_connected_at = NULL
+WHERE NOT EXISTS (SELECT 1 FROM lastfm_sessions ls WHERE ls.user_id = u.id);
@@ -0,0 +1,6 @@
+-- Track-level taste
+-- Run after 007_lastfm_auth.sql
+
+-- scrobbles_cache now holds both artist rows (track_name IS NULL) and track rows
+ALTER TABLE scrobbles_cache
+ADD INDEX idx_user_period_track (user_id, period, track_name);
@@ -0,0 +1,35 @@
+-- Artist Tags / Genres
+-- Run after 008_track_taste.sql
+
+-- Stable artist identity shared by scrobbles and artist metadata:
+-- the MusicBrainz ID when known, otherwise 'name:' + the lower-cased artist name
+ALTER TABLE scrobbles_cache
+ADD COLUMN artist_key VARCHAR(300) NOT NULL DEFAULT '' AFTER artist_mbid;
+
+UPDATE scrobbles_cache
+SET artist_key = IF(artist_mbid IS NULL OR artist_mbid = '', CONCAT('name:', LOWER(TRIM(artist_name))), LOWER(artist_mbid));
+
+ALTER TABLE scrobbles_cache
+ADD INDEX idx_scrobbles_artist_key (artist_key);
+
+-- Artists we've looked up on Last.fm (shared across all users)
+CREATE TABLE IF NOT EXISTS artists (
+    artist_key VARCHAR(300) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    mbid VARCHAR(36),
+    tags_fetched_at TIMESTAMP NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    
+    INDEX idx_artists_tags_fetched (tags_fetched_at)
+);
+
+-- artist.gettoptags results; weight is Last.fm's relative tag count (0-100)
+CREATE TABLE IF NOT EXISTS artist_tags (
+    artist_key VARCHAR(300) NOT NULL,
+    tag VARCHAR(100) NOT NULL,
+    weight INT NOT NULL,
+    
+    PRIMARY KEY (artist_key, tag),
+    FOREIGN KEY (artist_key) REFERENCES artists(artist_key) ON DELETE CASCADE,
+    INDEX idx_artist_tags_tag (tag, weight)
+);
@@ -0,0 +1,15 @@
+-- Last.fm Sync Status
+-- Run after 009_artist_tags.sql
+
+-- Per-user bookkeeping for the background re-sync worker and the "last synced X ago" indicator
+
+CREATE TABLE IF NOT EXISTS lastfm_sync_status (
+    user_id CHAR(36) PRIMARY KEY,
+    status ENUM('syncing', 'ok', 'error') NOT NULL,
+    last_synced_at TIMESTAMP NULL,
+    last_attempt_at TIMESTAMP NULL,
+    last_error TEXT NULL,
+    consecutive_failures INT NOT NULL DEFAULT 0,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    INDEX idx_sync_status_attempt (last_attempt_at)
+);
@@ -0,0 +1,19 @@
+-- Gender Preferences
+-- Run after 010_lastfm_sync_status.sql
+
+-- gender is now one of 'man', 'woman', 'non-binary' and looking_for a comma-separated set of
+-- them (e.g. 'woman,non-binary'), so discover can match preferences with FIND_IN_SET.
+-- Map the common free-form values stored before validation existed.
+UPDATE users SET gender = 'man' WHERE LOWER(TRIM(gender)) IN ('male', 'm', 'man');
+UPDATE users SET gender = 'woman' WHERE LOWER(TRIM(gender)) IN ('female', 'f', 'w', 'woman');
+UPDATE users SET gender = 'non-binary'
+WHERE LOWER(TRIM(gender)) IN ('non-binary', 'nonbinary', 'non binary', 'nb', 'enby');
+UPDATE users SET gender = NULL WHERE gender NOT IN ('man', 'woman', 'non-binary');
+
+UPDATE users SET looking_for = 'man' WHERE LOWER(TRIM(looking_for)) IN ('male', 'men', 'm', 'man');
+UPDATE users SET looking_for = 'woman' WHERE LOWER(TRIM(looking_for)) IN ('female', 'women', 'f', 'w', 'woman');
+UPDATE users SET looking_for = 'man,woman,non-binary' WHERE LOWER(TRIM(looking_for)) IN ('both', 'any', 'everyone', 'all');
+-- Anything else unrecognised means no stated preference
+UPDATE users SET looking_for = NULL
+WHERE looking_for IS NOT NULL
+AND looking_for NOT REGEXP '^(man|woman|non-binary)(,(man|woman|non-binary))*$';
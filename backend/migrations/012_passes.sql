@@ -0,0 +1,15 @@
+-- Passes
+-- Run after 011_gender_preferences.sql
+
+-- "No thanks" swipes, the counterpart of likes. Passed profiles are hidden from discover
+-- for PASS_COOLDOWN_DAYS; passing again restarts the cooldown.
+CREATE TABLE IF NOT EXISTS passes (
+    id CHAR(36) PRIMARY KEY,
+    from_user_id CHAR(36) NOT NULL,
+    to_user_id CHAR(36) NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (from_user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (to_user_id) REFERENCES users(id) ON DELETE CASCADE,
+    UNIQUE KEY unique_pass (from_user_id, to_user_id),
+    INDEX idx_passes_from_created (from_user_id, created_at)
+);
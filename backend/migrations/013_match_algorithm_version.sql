@@ -0,0 +1,6 @@
+-- Match algorithm version
+-- Run after 012_passes.sql
+
+-- Which compatibility algorithm scored each match, so algorithms can be compared on real
+-- outcomes. Matches created before this migration keep NULL.
+ALTER TABLE matches ADD COLUMN algorithm_version VARCHAR(32) NULL AFTER compatibility_score;
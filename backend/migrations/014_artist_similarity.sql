@@ -0,0 +1,19 @@
+-- Artist Similarity
+-- Run after 013_match_algorithm_version.sql
+
+ALTER TABLE artists
+ADD COLUMN similar_fetched_at TIMESTAMP NULL AFTER tags_fetched_at,
+ADD INDEX idx_artists_similar_fetched (similar_fetched_at);
+
+-- artist.getsimilar results: an edge from each artist to the artists Last.fm considers related.
+-- similarity is Last.fm's match score (0-1); similar artists don't need to be in `artists`.
+CREATE TABLE IF NOT EXISTS artist_similarities (
+    artist_key VARCHAR(300) NOT NULL,
+    similar_key VARCHAR(300) NOT NULL,
+    similar_name VARCHAR(255) NOT NULL,
+    similarity DECIMAL(5, 4) NOT NULL,
+    
+    PRIMARY KEY (artist_key, similar_key),
+    FOREIGN KEY (artist_key) REFERENCES artists(artist_key) ON DELETE CASCADE,
+    INDEX idx_artist_similarities_similar (similar_key)
+);
@@ -0,0 +1,15 @@
+-- Compatibility Calibration
+-- Run after 014_artist_similarity.sql
+
+-- Percentile calibrations mapping raw taste similarity to 0-100 scores, written by the
+-- calibrate_compatibility tool. The server uses the latest row for its algorithm version.
+-- quantiles is a JSON array of raw similarities at the 0th..100th percentile.
+CREATE TABLE IF NOT EXISTS compatibility_calibrations (
+    id CHAR(36) PRIMARY KEY,
+    algorithm_version VARCHAR(32) NOT NULL,
+    quantiles TEXT NOT NULL,
+    sample_size INT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+
+    INDEX idx_compatibility_calibrations_version (algorithm_version, created_at)
+);
@@ -0,0 +1,26 @@
+-- Artist Identity
+-- Run after 015_compatibility_calibration.sql
+
+-- Name-based artist keys are now built from a normalized name ("name:beatles" for "The Beatles",
+-- "name:sigur ros" for "Sigur Rós"), which SQL can't reproduce. Data keyed the old way is
+-- dropped or marked stale so it is fetched again under the new keys.
+
+-- Tags and similar artists of name-keyed artists go with them (ON DELETE CASCADE)
+DELETE FROM artists WHERE artist_key LIKE 'name:%';
+
+-- Similar-artist lists that point at name-keyed artists are refetched
+UPDATE artists a
+SET a.similar_fetched_at = NULL
+WHERE EXISTS (
+    SELECT 1 FROM artist_similarities s
+    WHERE s.artist_key = a.artist_key AND s.similar_key LIKE 'name:%'
+);
+
+-- Users with name-keyed listening data are resynced by the background job
+UPDATE scrobbles_cache
+SET last_synced_at = '2000-01-01 00:00:00'
+WHERE user_id IN (
+    SELECT user_id FROM (
+        SELECT DISTINCT user_id FROM scrobbles_cache WHERE artist_key LIKE 'name:%'
+    ) AS name_keyed
+);
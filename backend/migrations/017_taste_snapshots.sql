@@ -0,0 +1,24 @@
+-- Taste Snapshots
+-- Run after 016_artist_identity.sql
+
+-- A user's top artists (one-month chart) as of each sync, so taste changes can be followed
+-- over time. A later sync on the same day replaces that day's snapshot.
+CREATE TABLE IF NOT EXISTS taste_snapshots (
+    id CHAR(36) PRIMARY KEY,
+    user_id CHAR(36) NOT NULL,
+    taken_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    INDEX idx_taste_snapshots_user (user_id, taken_at)
+);
+
+CREATE TABLE IF NOT EXISTS taste_snapshot_artists (
+    snapshot_id CHAR(36) NOT NULL,
+    artist_key VARCHAR(300) NOT NULL,
+    artist_name VARCHAR(255) NOT NULL,
+    artist_rank INT NOT NULL,
+    play_count INT NOT NULL,
+
+    PRIMARY KEY (snapshot_id, artist_key),
+    FOREIGN KEY (snapshot_id) REFERENCES taste_snapshots(id) ON DELETE CASCADE
+);
@@ -0,0 +1,17 @@
+-- Now Playing
+-- Run after 017_taste_snapshots.sql
+
+-- Lets people keep what they're listening to from their matches
+ALTER TABLE users ADD COLUMN hide_now_playing BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Latest user.getrecenttracks poll per user; the track columns are NULL when nothing is playing
+CREATE TABLE IF NOT EXISTS now_playing (
+    user_id CHAR(36) PRIMARY KEY,
+    artist_name VARCHAR(255) NULL,
+    artist_mbid VARCHAR(36) NULL,
+    track_name VARCHAR(255) NULL,
+    album_name VARCHAR(255) NULL,
+    polled_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    INDEX idx_now_playing_polled (polled_at)
+);
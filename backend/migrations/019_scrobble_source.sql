@@ -0,0 +1,6 @@
+-- Scrobble Source
+-- Run after 018_now_playing.sql
+
+-- Listening data can now be imported from export files as well as synced from Last.fm:
+-- 'lastfm' (sync), 'lastfm_export' or 'listenbrainz_export'
+ALTER TABLE scrobbles_cache ADD COLUMN source VARCHAR(20) NOT NULL DEFAULT 'lastfm';
@@ -0,0 +1,8 @@
+-- Listening Provider
+-- Run after 019_scrobble_source.sql
+
+-- Users sync listening data from Last.fm or ListenBrainz; their ListenBrainz username is public,
+-- so connecting it needs no authorization handshake
+ALTER TABLE users
+ADD COLUMN listening_provider VARCHAR(20) NOT NULL DEFAULT 'lastfm',
+ADD COLUMN listenbrainz_username VARCHAR(255) NULL;
@@ -0,0 +1,9 @@
+-- Artist Listeners
+-- Run after 020_listening_provider.sql
+
+-- Listener counts come from artist.getinfo (user.gettopartists doesn't return them) and are
+-- shared across users like tags; NULL until looked up
+ALTER TABLE artists
+ADD COLUMN listeners INT NULL AFTER mbid,
+ADD COLUMN info_fetched_at TIMESTAMP NULL AFTER similar_fetched_at,
+ADD INDEX idx_artists_info_fetched (info_fetched_at);
@@ -0,0 +1,11 @@
+-- Discover Feed Built
+-- Run after 021_artist_listeners.sql
+
+-- When the user's discover feed was last built. A feed can be built and still be empty when
+-- nobody scores high enough, so an empty `discover_cache` doesn't mean it needs building.
+ALTER TABLE users
+ADD COLUMN discover_feed_built_at TIMESTAMP NULL;
+
+UPDATE users u
+SET u.discover_feed_built_at = NOW()
+WHERE EXISTS (SELECT 1 FROM discover_cache dc WHERE dc.user_id = u.id);
@@ -0,0 +1,10 @@
+-- Swipe Timestamps
+-- Run after 022_discover_feed_built.sql
+
+-- Undo reverts the most recent swipe; with second precision, two swipes in the same second
+-- couldn't be told apart
+ALTER TABLE likes
+MODIFY created_at TIMESTAMP(6) DEFAULT CURRENT_TIMESTAMP(6);
+
+ALTER TABLE passes
+MODIFY created_at TIMESTAMP(6) DEFAULT CURRENT_TIMESTAMP(6);
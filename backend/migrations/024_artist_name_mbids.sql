@@ -0,0 +1,11 @@
+-- Artist Name MBIDs
+-- Run after 023_swipe_timestamps.sql
+
+-- Which MusicBrainz ID each normalized artist name belongs to, learned from every synced artist
+-- that came with one. Artists synced without an MBID are stored under it, so SQL joins on
+-- `artist_key` match them up like `ArtistIdentities` does. mbid is NULL for names shared by
+-- artists with different MBIDs, which stay keyed by name. Filled in as users sync.
+CREATE TABLE IF NOT EXISTS artist_name_mbids (
+    name_key VARCHAR(300) PRIMARY KEY,
+    mbid VARCHAR(36) NULL
+);
@@ -0,0 +1,85 @@
+//! Calibrate compatibility scores against the current user population
+//!
+//! Usage: cargo run --bin calibrate_compatibility -- [--users N] [--dry-run]
+//!
+//! Samples N users (default 100), computes the raw similarity of every pair with the configured
+//! COMPATIBILITY_ALGORITHM and stores the resulting percentiles. Restart the server to apply them.
+
+use lastfm_dating_backend::{
+    db,
+    services::{compatibility_algorithm, compatibility_calibration, CompatibilityService, LastFmService},
+    Config,
+};
+use std::sync::Arc;
+
+struct Args {
+    users: u32,
+    dry_run: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        users: 100,
+        dry_run: false,
+    };
+
+    let mut argv = std::env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--users" => {
+                let value = argv.next().ok_or_else(|| format!("{} needs a value", arg))?;
+                args.users = value.parse().map_err(|_| "--users must be a number".to_string())?;
+            }
+            "--dry-run" => args.dry_run = true,
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+
+    Ok(args)
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    dotenvy::dotenv().ok();
+
+    let args = parse_args().map_err(anyhow::Error::msg)?;
+    let config = Config::from_env()?;
+
+    let algorithm = compatibility_algorithm::by_version(&config.compatibility_algorithm, config.compatibility_weights)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown algorithm \"{}\", expected one of: {}",
+                config.compatibility_algorithm,
+                compatibility_algorithm::ALGORITHM_VERSIONS.join(", ")
+            )
+        })?;
+    let algorithm_version = algorithm.version();
+
+    let pool = db::create_pool(&config.database_url).await?;
+    let lastfm_service = Arc::new(LastFmService::new(config.clone()));
+    let service = CompatibilityService::new(lastfm_service).with_algorithm(algorithm);
+
+    let user_ids = compatibility_calibration::load_sample_users(&pool, args.users).await?;
+    println!("Calibrating {} against {} users", algorithm_version, user_ids.len());
+
+    let (calibration, pairs) = compatibility_calibration::calibrate(&pool, &service, &user_ids).await?;
+    if !calibration.is_calibrated() {
+        anyhow::bail!("None of the {} sampled pairs share any taste; nothing to calibrate", pairs);
+    }
+
+    println!();
+    println!("{:>10} {:>10}", "similarity", "score");
+    for similarity in [0.01, 0.05, 0.1, 0.2, 0.3, 0.5, 0.7] {
+        println!("{:>10.2} {:>10.1}", similarity, calibration.score(similarity));
+    }
+    println!();
+
+    if args.dry_run {
+        println!("Dry run: calibration from {} pairs not stored", pairs);
+    } else {
+        calibration.save(&pool, algorithm_version, pairs).await?;
+        println!("Stored calibration from {} pairs; restart the server to apply it", pairs);
+    }
+
+    Ok(())
+}
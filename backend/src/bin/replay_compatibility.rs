@@ -0,0 +1,91 @@
+//! Replay historical likes through compatibility algorithms to see which predicts mutual likes best
+//!
+//! Usage: cargo run --bin replay_compatibility -- [--algorithms vector-v1,overlap-v0] [--limit N]
+//!
+//! Uses DATABASE_URL and the COMPATIBILITY_*_WEIGHT settings from the environment.
+
+use lastfm_dating_backend::{
+    db,
+    services::{compatibility_algorithm, compatibility_replay, LastFmService},
+    Config,
+};
+use std::sync::Arc;
+
+struct Args {
+    algorithms: Vec<String>,
+    limit: Option<u32>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        algorithms: compatibility_algorithm::ALGORITHM_VERSIONS
+            .iter()
+            .map(|v| v.to_string())
+            .collect(),
+        limit: None,
+    };
+
+    let mut argv = std::env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        let mut value = || argv.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--algorithms" => {
+                args.algorithms = value()?.split(',').map(|v| v.trim().to_string()).collect();
+            }
+            "--limit" => {
+                args.limit = Some(value()?.parse().map_err(|_| "--limit must be a number".to_string())?);
+            }
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+
+    Ok(args)
+}
+
+fn format_score(value: Option<f64>) -> String {
+    value.map(|v| format!("{:.3}", v)).unwrap_or_else(|| "-".to_string())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    dotenvy::dotenv().ok();
+
+    let args = parse_args().map_err(anyhow::Error::msg)?;
+    let config = Config::from_env()?;
+
+    let mut algorithms = Vec::new();
+    for version in &args.algorithms {
+        let algorithm = compatibility_algorithm::by_version(version, config.compatibility_weights)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown algorithm \"{}\", expected one of: {}",
+                    version,
+                    compatibility_algorithm::ALGORITHM_VERSIONS.join(", ")
+                )
+            })?;
+        algorithms.push(algorithm);
+    }
+
+    let pool = db::create_pool(&config.database_url).await?;
+    let lastfm_service = Arc::new(LastFmService::new(config.clone()));
+
+    let pairs = compatibility_replay::load_liked_pairs(&pool, args.limit).await?;
+    println!("Replaying {} liked pairs with {:?}", pairs.len(), config.compatibility_weights);
+    println!();
+    println!("{:<12} {:>7} {:>7} {:>12} {:>12} {:>7}", "algorithm", "pairs", "mutual", "mean mutual", "mean 1-sided", "auc");
+
+    for algorithm in algorithms {
+        let report = compatibility_replay::replay(&pool, lastfm_service.clone(), algorithm, &pairs).await?;
+        println!(
+            "{:<12} {:>7} {:>7} {:>12} {:>12} {:>7}",
+            report.algorithm_version,
+            report.pairs,
+            report.mutual_pairs,
+            format_score(report.mean_mutual_score),
+            format_score(report.mean_one_sided_score),
+            format_score(report.auc),
+        );
+    }
+
+    Ok(())
+}
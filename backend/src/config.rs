@@ -1,4 +1,27 @@
+use crate::services::compatibility_algorithm::{AlgorithmWeights, DEFAULT_ALGORITHM};
 use std::env;
+use std::str::FromStr;
+
+/// Where `CacheService` keeps its data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheBackend {
+    /// Shared Redis instance (production, multi-node)
+    Redis,
+    /// In-process LRU cache (local development, tests, single node)
+    Memory,
+}
+
+impl FromStr for CacheBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "redis" => Ok(CacheBackend::Redis),
+            "memory" => Ok(CacheBackend::Memory),
+            other => Err(format!("Unknown cache backend: {}", other)),
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct Config {
@@ -6,12 +29,39 @@ pub struct Config {
     pub jwt_secret: String,
     pub lastfm_api_key: String,
     pub lastfm_api_secret: String,
+    pub lastfm_api_url: String,
+    pub lastfm_auth_url: String,
+    pub lastfm_session_encryption_key: String,
+    /// ListenBrainz API root, the alternative listening-data provider
+    pub listenbrainz_api_url: String,
+    /// Cap on ListenBrainz API requests per second
+    pub listenbrainz_rate_limit_per_sec: f64,
+    /// Global cap on Last.fm API requests per second (shared by requests and background jobs)
+    pub lastfm_rate_limit_per_sec: f64,
+    pub lastfm_timeout_secs: u64,
+    /// Retries for transient Last.fm failures (5xx, 429), with exponential backoff from `lastfm_retry_backoff_ms`
+    pub lastfm_max_retries: u32,
+    pub lastfm_retry_backoff_ms: u64,
+    /// How often the background worker looks for stale users; 0 disables it
+    pub lastfm_resync_interval_secs: u64,
+    /// Users whose scrobbles are older than this are re-synced by the worker
+    pub lastfm_resync_stale_after_secs: u64,
+    /// How often connected users' now-playing tracks are polled; 0 disables it
+    pub now_playing_poll_interval_secs: u64,
+    /// Days a passed profile stays out of discover before it can be shown again
+    pub pass_cooldown_days: u32,
+    /// Version name of the algorithm scoring compatibility (see `compatibility_algorithm::ALGORITHM_VERSIONS`)
+    pub compatibility_algorithm: String,
+    pub compatibility_weights: AlgorithmWeights,
     pub s3_endpoint: String,
     pub s3_bucket: String,
     pub s3_region: String,
     pub s3_access_key: String,
     pub s3_secret_key: String,
+    pub cache_backend: CacheBackend,
     pub redis_url: String,
+    /// Capacity of the in-memory cache (also used when falling back from Redis)
+    pub cache_max_entries: usize,
     pub vapid_private_key: Option<String>,
     pub vapid_public_key: Option<String>,
     pub vapid_subject: Option<String>,
@@ -33,12 +83,75 @@ impl Config {
             jwt_secret: env::var("JWT_SECRET")?,
             lastfm_api_key: env::var("LASTFM_API_KEY")?,
             lastfm_api_secret: env::var("LASTFM_API_SECRET")?,
+            lastfm_api_url: env::var("LASTFM_API_URL").unwrap_or_else(|_| "https://ws.audioscrobbler.com/2.0/".to_string()),
+            lastfm_auth_url: env::var("LASTFM_AUTH_URL").unwrap_or_else(|_| "https://www.last.fm/api/auth/".to_string()),
+            lastfm_session_encryption_key: env::var("LASTFM_SESSION_ENCRYPTION_KEY")?,
+            listenbrainz_api_url: env::var("LISTENBRAINZ_API_URL").unwrap_or_else(|_| "https://api.listenbrainz.org/".to_string()),
+            listenbrainz_rate_limit_per_sec: env::var("LISTENBRAINZ_RATE_LIMIT_PER_SEC")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()
+                .expect("LISTENBRAINZ_RATE_LIMIT_PER_SEC must be a valid number"),
+            lastfm_rate_limit_per_sec: env::var("LASTFM_RATE_LIMIT_PER_SEC")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .expect("LASTFM_RATE_LIMIT_PER_SEC must be a valid number"),
+            lastfm_timeout_secs: env::var("LASTFM_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .expect("LASTFM_TIMEOUT_SECS must be a valid number"),
+            lastfm_max_retries: env::var("LASTFM_MAX_RETRIES")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .expect("LASTFM_MAX_RETRIES must be a valid number"),
+            lastfm_retry_backoff_ms: env::var("LASTFM_RETRY_BACKOFF_MS")
+                .unwrap_or_else(|_| "500".to_string())
+                .parse()
+                .expect("LASTFM_RETRY_BACKOFF_MS must be a valid number"),
+            lastfm_resync_interval_secs: env::var("LASTFM_RESYNC_INTERVAL_SECS")
+                .unwrap_or_else(|_| "600".to_string())
+                .parse()
+                .expect("LASTFM_RESYNC_INTERVAL_SECS must be a valid number"),
+            lastfm_resync_stale_after_secs: env::var("LASTFM_RESYNC_STALE_AFTER_SECS")
+                .unwrap_or_else(|_| "21600".to_string())
+                .parse()
+                .expect("LASTFM_RESYNC_STALE_AFTER_SECS must be a valid number"),
+            now_playing_poll_interval_secs: env::var("NOW_PLAYING_POLL_INTERVAL_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .expect("NOW_PLAYING_POLL_INTERVAL_SECS must be a valid number"),
+            pass_cooldown_days: env::var("PASS_COOLDOWN_DAYS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .expect("PASS_COOLDOWN_DAYS must be a valid number"),
+            compatibility_algorithm: env::var("COMPATIBILITY_ALGORITHM").unwrap_or_else(|_| DEFAULT_ALGORITHM.to_string()),
+            compatibility_weights: AlgorithmWeights {
+                position: env::var("COMPATIBILITY_POSITION_WEIGHT")
+                    .unwrap_or_else(|_| "1.0".to_string())
+                    .parse()
+                    .expect("COMPATIBILITY_POSITION_WEIGHT must be a valid number"),
+                play_count: env::var("COMPATIBILITY_PLAY_COUNT_WEIGHT")
+                    .unwrap_or_else(|_| "1.0".to_string())
+                    .parse()
+                    .expect("COMPATIBILITY_PLAY_COUNT_WEIGHT must be a valid number"),
+                popularity: env::var("COMPATIBILITY_POPULARITY_WEIGHT")
+                    .unwrap_or_else(|_| "1.0".to_string())
+                    .parse()
+                    .expect("COMPATIBILITY_POPULARITY_WEIGHT must be a valid number"),
+            },
             s3_endpoint: env::var("S3_ENDPOINT").unwrap_or_else(|_| "http://localhost:9000".to_string()),
             s3_bucket: env::var("S3_BUCKET").unwrap_or_else(|_| "lastfm-photos".to_string()),
             s3_region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
             s3_access_key: env::var("S3_ACCESS_KEY").unwrap_or_default(),
             s3_secret_key: env::var("S3_SECRET_KEY").unwrap_or_default(),
+            cache_backend: env::var("CACHE_BACKEND")
+                .unwrap_or_else(|_| "redis".to_string())
+                .parse()
+                .expect("CACHE_BACKEND must be \"redis\" or \"memory\""),
             redis_url: env::var("REDIS_URL").unwrap_or_else(|_| "redis://localhost:6379".to_string()),
+            cache_max_entries: env::var("CACHE_MAX_ENTRIES")
+                .unwrap_or_else(|_| "10000".to_string())
+                .parse()
+                .expect("CACHE_MAX_ENTRIES must be a valid number"),
             vapid_private_key: env::var("VAPID_PRIVATE_KEY").ok().filter(|s| !s.is_empty()),
             vapid_public_key: env::var("VAPID_PUBLIC_KEY").ok().filter(|s| !s.is_empty()),
             vapid_subject: env::var("VAPID_SUBJECT").ok().filter(|s| !s.is_empty()),
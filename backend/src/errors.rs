@@ -6,6 +6,8 @@ use axum::{
 use serde_json::json;
 use thiserror::Error;
 
+use crate::services::lastfm_client::LastFmError;
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
@@ -23,6 +25,9 @@ pub enum AppError {
     #[error("External API error: {0}")]
     ExternalApi(String),
 
+    #[error("{0}")]
+    LastFm(#[from] LastFmError),
+
     #[error("Internal server error: {0}")]
     Internal(String),
 
@@ -44,6 +49,13 @@ impl IntoResponse for AppError {
             AppError::Validation(ref msg) => (StatusCode::BAD_REQUEST, msg.as_str()),
             AppError::NotFound(ref msg) => (StatusCode::NOT_FOUND, msg.as_str()),
             AppError::ExternalApi(ref msg) => (StatusCode::BAD_GATEWAY, msg.as_str()),
+            AppError::LastFm(ref e) => {
+                tracing::warn!("Last.fm error: {}", e);
+                let body = Json(json!({
+                    "error": e.user_message(),
+                }));
+                return (e.status_code(), body).into_response();
+            }
             AppError::Internal(ref msg) => {
                 tracing::error!("Internal error: {}", msg);
                 (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
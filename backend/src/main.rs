@@ -1,16 +1,18 @@
 use axum::{
+    extract::DefaultBodyLimit,
     middleware,
     routing::{delete, get, post, put},
     Router,
 };
 use lastfm_dating_backend::{
-    config::Config,
+    config::{CacheBackend, Config},
     db,
     middleware::auth_middleware,
     routes,
     services::{
-        AuthService, CacheService, CaptchaService, CompatibilityService, LastFmService,
-        MatchService, NotificationService, PhotoService, WebSocketService,
+        compatibility_algorithm, ArtistSimilarityService, ArtistTagService, AuthService, CacheService, Calibration, CaptchaService, CompatibilityService,
+        DiscoverService, LastFmService, ListenBrainzService, MatchService, MusicDnaService, NotificationService, NowPlayingService, PhotoService, SyncService,
+        WebSocketService,
     },
     AppState,
 };
@@ -56,30 +58,82 @@ async fn main() {
 
     tracing::info!("Database connection established");
 
-    // Initialize services
-    let auth_service = Arc::new(AuthService::new(config.clone()));
-    let lastfm_service = Arc::new(LastFmService::new(config.clone()));
-    let compatibility_service = Arc::new(CompatibilityService::new(lastfm_service.clone()));
-    let match_service = Arc::new(MatchService::new(compatibility_service.clone()));
-    
     // Initialize cache service
-    let cache_service = match CacheService::new(&config.redis_url).await {
-        Ok(service) => {
-            print_success("Redis connection established");
-            Arc::new(service)
+    let cache_service = Arc::new(match config.cache_backend {
+        CacheBackend::Redis => match CacheService::redis(&config.redis_url).await {
+            Ok(service) => {
+                print_success("Redis connection established");
+                service
+            }
+            Err(e) => {
+                print_error(&format!("Failed to connect to Redis: {}. Falling back to in-memory cache.", e));
+                tracing::warn!("Redis connection failed, using in-memory cache: {}", e);
+                CacheService::in_memory(config.cache_max_entries)
+            }
+        },
+        CacheBackend::Memory => {
+            print_success("Using in-memory cache");
+            CacheService::in_memory(config.cache_max_entries)
         }
-        Err(e) => {
-            print_error(&format!("Failed to connect to Redis: {}. Application requires Redis.", e));
-            tracing::error!("Redis connection failed: {}", e);
-            panic!("Redis is required for this application to function properly");
+    });
+
+    // Initialize services
+    let auth_service = Arc::new(AuthService::new(config.clone()));
+    let lastfm_service = Arc::new(LastFmService::new(config.clone()).with_cache(cache_service.clone()));
+    let listenbrainz_service = Arc::new(ListenBrainzService::new(&config));
+    let artist_tag_service = Arc::new(ArtistTagService::new(lastfm_service.clone()));
+    let artist_similarity_service = Arc::new(ArtistSimilarityService::new(lastfm_service.clone()));
+    let algorithm = compatibility_algorithm::by_version(&config.compatibility_algorithm, config.compatibility_weights)
+        .unwrap_or_else(|| {
+            panic!(
+                "Unknown COMPATIBILITY_ALGORITHM \"{}\", expected one of: {}",
+                config.compatibility_algorithm,
+                compatibility_algorithm::ALGORITHM_VERSIONS.join(", ")
+            )
+        });
+    tracing::info!("Scoring compatibility with {}", algorithm.version());
+    let calibration = match Calibration::load_latest(&pool, algorithm.version())
+        .await
+        .expect("Failed to load compatibility calibration")
+    {
+        Some(calibration) => calibration,
+        None => {
+            tracing::warn!(
+                "No calibration stored for {}; run calibrate_compatibility to spread scores across 0-100",
+                algorithm.version()
+            );
+            Calibration::default()
         }
     };
+    let compatibility_service = Arc::new(
+        CompatibilityService::new(lastfm_service.clone())
+            .with_cache(cache_service.clone())
+            .with_algorithm(algorithm)
+            .with_calibration(calibration),
+    );
+    let discover_service = Arc::new(DiscoverService::new(compatibility_service.clone()));
+    let music_dna_service = Arc::new(MusicDnaService::new().with_cache(cache_service.clone()));
+    let sync_service = Arc::new(SyncService::new(
+        &config,
+        lastfm_service.clone(),
+        listenbrainz_service.clone(),
+        artist_tag_service.clone(),
+        artist_similarity_service,
+        discover_service.clone(),
+        music_dna_service.clone(),
+    ));
+    let match_service = Arc::new(MatchService::new(compatibility_service.clone()));
     
     // Initialize photo service with S3
     let photo_service = Arc::new(PhotoService::new(config.clone()).with_s3().await);
     
     // Initialize WebSocket service
     let websocket_service = Arc::new(WebSocketService::new());
+    let now_playing_service = Arc::new(NowPlayingService::new(
+        &config,
+        lastfm_service.clone(),
+        websocket_service.clone(),
+    ));
     
     // Initialize notification service
     let notification_service = Arc::new(NotificationService::new(
@@ -90,6 +144,10 @@ async fn main() {
     
     let captcha_service = Arc::new(CaptchaService::new());
 
+    // Keep everyone's scrobbles fresh without waiting for a manual sync
+    sync_service.clone().spawn_worker(pool.clone());
+    now_playing_service.clone().spawn_worker(pool.clone());
+
     let config_arc = Arc::new(config);
 
     // Create shared app state
@@ -98,12 +156,18 @@ async fn main() {
         config: config_arc.clone(),
         auth_service,
         lastfm_service,
+        listenbrainz_service,
+        artist_tag_service,
+        sync_service,
         compatibility_service,
+        discover_service,
+        music_dna_service,
         match_service,
         photo_service,
         captcha_service,
         cache_service,
         websocket_service,
+        now_playing_service,
         notification_service,
     };
 
@@ -121,15 +185,30 @@ async fn main() {
         .route("/users/me", get(routes::users::get_me))
         .route("/users/me", put(routes::users::update_me))
         .route("/users/:id", get(routes::users::get_user))
+        .route("/users/:id/block", post(routes::users::block_user))
         .route("/likes", post(routes::matches::create_like))
+        .route("/passes", post(routes::matches::create_pass))
+        .route("/swipes/undo", post(routes::matches::undo_last_swipe))
         .route("/matches", get(routes::matches::get_matches))
+        .route("/matches/now-playing", get(routes::matches::get_matches_now_playing))
         .route("/matches/:id", delete(routes::matches::delete_match))
         .route("/photos", post(routes::photos::create_photo))
         .route("/photos/user/:user_id", get(routes::photos::get_user_photos))
         .route("/photos/:id", delete(routes::photos::delete_photo))
-        .route("/lastfm/connect", post(routes::lastfm::connect_lastfm))
+        .route("/lastfm/auth/start", post(routes::lastfm::start_lastfm_auth))
+        .route("/lastfm/auth/complete", post(routes::lastfm::complete_lastfm_auth))
         .route("/lastfm/sync", post(routes::lastfm::sync_scrobbles))
+        .route("/lastfm/sync/status", get(routes::lastfm::get_sync_status))
+        .route("/lastfm/top-artists", get(routes::lastfm::get_top_artists))
+        .route("/lastfm/genres", get(routes::lastfm::get_genres))
+        .route("/listenbrainz/connect", post(routes::listenbrainz::connect_listenbrainz))
+        .route(
+            "/listening-history/import",
+            post(routes::history_import::import_listening_history)
+                .layer(DefaultBodyLimit::max(routes::history_import::MAX_IMPORT_BYTES)),
+        )
         .route("/discover", get(routes::discover::get_discover_profiles))
+        .route("/compatibility/:user_id", get(routes::compatibility::get_compatibility_breakdown))
         // WebSocket route
         .route("/ws", get(routes::websocket::websocket_handler))
         // Notification routes
@@ -145,6 +224,8 @@ async fn main() {
         // Achievement routes
         .route("/achievements", get(routes::achievements::get_achievements))
         .route("/users/me/stats", get(routes::achievements::get_user_stats))
+        .route("/users/me/music-dna", get(routes::music_dna::get_my_music_dna))
+        .route("/users/me/taste-timeline", get(routes::taste_history::get_taste_timeline))
         .layer(middleware::from_fn_with_state(
             config_arc.clone(),
             auth_middleware,
@@ -154,6 +235,7 @@ async fn main() {
     let public_achievement_routes = Router::new()
         .route("/users/:id/achievements", get(routes::achievements::get_user_achievements_public))
         .route("/users/:id/stats", get(routes::achievements::get_user_stats_public))
+        .route("/users/:id/music-dna", get(routes::music_dna::get_music_dna_public))
         .route("/events/popular", get(routes::events::get_popular_events));
 
     // Combine routes and add CORS with security restrictions
@@ -8,11 +8,18 @@ pub struct Match {
     pub user1_id: String,
     pub user2_id: String,
     pub compatibility_score: Option<f64>,
+    /// Algorithm that produced `compatibility_score`
+    pub algorithm_version: Option<String>,
     pub created_at: NaiveDateTime,
 }
 
 impl Match {
-    pub fn new(user1_id: String, user2_id: String, compatibility_score: Option<f64>) -> Self {
+    pub fn new(
+        user1_id: String,
+        user2_id: String,
+        compatibility_score: Option<f64>,
+        algorithm_version: Option<String>,
+    ) -> Self {
         // Ensure user1_id is always lexicographically smaller for consistency
         let (user1, user2) = if user1_id < user2_id {
             (user1_id, user2_id)
@@ -25,6 +32,7 @@ impl Match {
             user1_id: user1,
             user2_id: user2,
             compatibility_score,
+            algorithm_version,
             created_at: chrono::Utc::now().naive_utc(),
         }
     }
@@ -1,13 +1,19 @@
 pub mod user;
 pub mod photo;
 pub mod like;
+pub mod pass;
 pub mod match_model;
 pub mod message;
 pub mod scrobble;
+pub mod sync_status;
+pub mod now_playing;
 
 pub use user::{User, CreateUser, UpdateUser, UserProfile};
 pub use photo::{Photo, CreatePhoto};
 pub use like::{Like, CreateLike};
+pub use pass::{CreatePass, Swipe};
 pub use match_model::Match;
 pub use message::{Message, CreateMessage};
-pub use scrobble::{Scrobble, Artist};
+pub use scrobble::{artist_key, Scrobble, Artist, Period, Track};
+pub use sync_status::SyncStatus;
+pub use now_playing::{MatchNowPlaying, NowPlayingTrack};
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// Track a user is scrobbling right now, from Last.fm's recent tracks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct NowPlayingTrack {
+    pub artist_name: String,
+    pub artist_mbid: Option<String>,
+    pub track_name: String,
+    pub album_name: Option<String>,
+}
+
+/// What a match is listening to, for the matches list before any WebSocket updates arrive
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct MatchNowPlaying {
+    pub user_id: String,
+    #[sqlx(flatten)]
+    pub track: NowPlayingTrack,
+}
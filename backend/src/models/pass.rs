@@ -0,0 +1,18 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePass {
+    pub to_user_id: String,
+}
+
+/// A like or a pass, as returned when undoing the last swipe
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Swipe {
+    /// "like" or "pass"
+    pub kind: String,
+    #[serde(skip_serializing)]
+    pub id: String,
+    pub to_user_id: String,
+    pub created_at: NaiveDateTime,
+}
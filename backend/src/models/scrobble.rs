@@ -1,5 +1,8 @@
+use crate::services::{artist_normalization::normalize_artist_name, listening_provider::LASTFM_PROVIDER};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -8,10 +11,14 @@ pub struct Scrobble {
     pub user_id: String,
     pub artist_name: String,
     pub artist_mbid: Option<String>,
+    pub artist_key: String,
     pub track_name: Option<String>,
     pub play_count: i32,
     pub listeners: i32,
     pub period: String,
+    /// Where the row came from: the provider it was synced from (`lastfm`, `listenbrainz`), or
+    /// `lastfm_export` / `listenbrainz_export` when imported from a file
+    pub source: String,
     pub last_synced_at: NaiveDateTime,
 }
 
@@ -23,6 +30,40 @@ pub struct Artist {
     pub listeners: i32,
 }
 
+impl Artist {
+    pub fn key(&self) -> String {
+        artist_key(&self.name, self.mbid.as_deref())
+    }
+}
+
+/// Stable identity for an artist across users and tables
+/// Prefers the MusicBrainz ID and falls back to the normalized name
+pub fn artist_key(name: &str, mbid: Option<&str>) -> String {
+    match mbid.filter(|m| !m.is_empty()) {
+        Some(mbid) => mbid.to_lowercase(),
+        None => format!("name:{}", normalize_artist_name(name)),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Track {
+    pub name: String,
+    pub artist_name: String,
+    pub artist_mbid: Option<String>,
+    pub play_count: i32,
+}
+
+impl From<Scrobble> for Artist {
+    fn from(scrobble: Scrobble) -> Self {
+        Self {
+            name: scrobble.artist_name,
+            mbid: scrobble.artist_mbid,
+            play_count: scrobble.play_count,
+            listeners: scrobble.listeners,
+        }
+    }
+}
+
 impl Scrobble {
     pub fn new(
         user_id: String,
@@ -35,13 +76,98 @@ impl Scrobble {
         Self {
             id: Uuid::new_v4().to_string(),
             user_id,
+            artist_key: artist_key(&artist_name, artist_mbid.as_deref()),
             artist_name,
             artist_mbid,
             track_name: None,
             play_count,
             listeners,
             period,
+            source: LASTFM_PROVIDER.to_string(),
             last_synced_at: chrono::Utc::now().naive_utc(),
         }
     }
+
+    /// Cache row for one of the user's top tracks
+    pub fn new_track(user_id: String, track: &Track, period: String) -> Self {
+        Self {
+            track_name: Some(track.name.clone()),
+            ..Self::new(
+                user_id,
+                track.artist_name.clone(),
+                track.artist_mbid.clone(),
+                track.play_count,
+                0,
+                period,
+            )
+        }
+    }
+
+    /// Convert a track row back into a `Track`; artist rows have no track name
+    pub fn into_track(self) -> Option<Track> {
+        Some(Track {
+            name: self.track_name?,
+            artist_name: self.artist_name,
+            artist_mbid: self.artist_mbid,
+            play_count: self.play_count,
+        })
+    }
+}
+
+/// Last.fm chart periods, as accepted by the `period` parameter of user.get* methods
+/// Defaults to six months: recent enough to be current, long enough to be representative.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Period {
+    #[serde(rename = "7day")]
+    SevenDay,
+    #[serde(rename = "1month")]
+    OneMonth,
+    #[serde(rename = "3month")]
+    ThreeMonth,
+    #[serde(rename = "6month")]
+    #[default]
+    SixMonth,
+    #[serde(rename = "12month")]
+    TwelveMonth,
+    #[serde(rename = "overall")]
+    Overall,
+}
+
+impl Period {
+    pub const ALL: [Period; 6] = [
+        Period::SevenDay,
+        Period::OneMonth,
+        Period::ThreeMonth,
+        Period::SixMonth,
+        Period::TwelveMonth,
+        Period::Overall,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Period::SevenDay => "7day",
+            Period::OneMonth => "1month",
+            Period::ThreeMonth => "3month",
+            Period::SixMonth => "6month",
+            Period::TwelveMonth => "12month",
+            Period::Overall => "overall",
+        }
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Period::ALL
+            .into_iter()
+            .find(|p| p.as_str() == s)
+            .ok_or_else(|| format!("Unknown period: {}", s))
+    }
 }
@@ -0,0 +1,14 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+/// Outcome of the most recent Last.fm sync for a user
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SyncStatus {
+    pub user_id: String,
+    /// `syncing`, `ok` or `error`
+    pub status: String,
+    pub last_synced_at: Option<NaiveDateTime>,
+    pub last_attempt_at: Option<NaiveDateTime>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: i32,
+}
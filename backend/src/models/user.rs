@@ -1,3 +1,4 @@
+use crate::services::listening_provider::LASTFM_PROVIDER;
 use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -15,8 +16,13 @@ pub struct User {
     pub looking_for: Option<String>,
     pub lastfm_username: Option<String>,
     pub lastfm_connected_at: Option<NaiveDateTime>,
+    /// Where listening data is synced from: `lastfm` or `listenbrainz`
+    pub listening_provider: String,
+    pub listenbrainz_username: Option<String>,
     pub latitude: Option<f64>,
     pub longitude: Option<f64>,
+    /// Keep the track they're scrobbling from their matches
+    pub hide_now_playing: bool,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
@@ -39,6 +45,8 @@ pub struct UpdateUser {
     pub looking_for: Option<String>,
     pub latitude: Option<f64>,
     pub longitude: Option<f64>,
+    pub hide_now_playing: Option<bool>,
+    pub listening_provider: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -68,8 +76,11 @@ impl User {
             looking_for: None,
             lastfm_username: None,
             lastfm_connected_at: None,
+            listening_provider: LASTFM_PROVIDER.to_string(),
+            listenbrainz_username: None,
             latitude: None,
             longitude: None,
+            hide_now_playing: false,
             created_at: chrono::Utc::now().naive_utc(),
             updated_at: chrono::Utc::now().naive_utc(),
         }
@@ -0,0 +1,105 @@
+use crate::{
+    errors::AppError,
+    middleware::AuthUser,
+    models::Period,
+    services::{
+        artist_tag_service::{shared_genres, SharedGenre},
+        compatibility_service::{RelatedArtistPair, SharedArtist},
+        event_service::EventInterest,
+        EventService,
+    },
+    AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// How many genres per user are compared for overlap
+const GENRES_COMPARED: usize = 20;
+
+#[derive(Debug, Deserialize)]
+pub struct CompatibilityQuery {
+    #[serde(default)]
+    pub period: Period,
+}
+
+/// Everything behind a compatibility score, for explaining "why you matched"
+#[derive(Debug, Serialize)]
+pub struct CompatibilityBreakdown {
+    pub user_id: String,
+    /// The overall score, blended across periods (what discover and matches show)
+    pub score: f64,
+    pub period: Period,
+    /// The artist part of the score for `period`, which `shared_artists` and `related_artists` decompose
+    pub artist_score: f64,
+    pub cosine_similarity: f64,
+    pub niche_bonus: f64,
+    pub shared_artists: Vec<SharedArtist>,
+    pub related_artists: Vec<RelatedArtistPair>,
+    pub shared_genres: Vec<SharedGenre>,
+    pub shared_events: Vec<EventInterest>,
+}
+
+/// Explain the current user's compatibility with another user
+pub async fn get_compatibility_breakdown(
+    Extension(auth_user): Extension<AuthUser>,
+    State(app_state): State<AppState>,
+    Path(user_id): Path<String>,
+    Query(query): Query<CompatibilityQuery>,
+) -> Result<Json<CompatibilityBreakdown>, AppError> {
+    if user_id == auth_user.user_id {
+        return Err(AppError::Validation("Cannot compare a user with themselves".to_string()));
+    }
+
+    // Blocked pairs look the same as missing users
+    let visible: Option<String> = sqlx::query_scalar(
+        "SELECT id FROM users
+         WHERE id = ?
+         AND NOT EXISTS (
+             SELECT 1 FROM blocks
+             WHERE (blocker_id = ? AND blocked_id = users.id)
+                OR (blocker_id = users.id AND blocked_id = ?)
+         )",
+    )
+    .bind(&user_id)
+    .bind(&auth_user.user_id)
+    .bind(&auth_user.user_id)
+    .fetch_optional(&app_state.pool)
+    .await?;
+
+    if visible.is_none() {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    let pool = &app_state.pool;
+    let score = app_state.compatibility_service
+        .calculate_compatibility(pool, &auth_user.user_id, &user_id)
+        .await?;
+    let artists = app_state.compatibility_service
+        .explain_compatibility(pool, &auth_user.user_id, &user_id, query.period)
+        .await?;
+
+    let my_genres = app_state.artist_tag_service
+        .get_user_genres(pool, &auth_user.user_id, query.period, GENRES_COMPARED)
+        .await?;
+    let their_genres = app_state.artist_tag_service
+        .get_user_genres(pool, &user_id, query.period, GENRES_COMPARED)
+        .await?;
+
+    let shared_events = EventService::get_common_events(pool, &auth_user.user_id, &user_id).await?;
+
+    Ok(Json(CompatibilityBreakdown {
+        user_id,
+        score,
+        period: query.period,
+        artist_score: artists.artist_score,
+        cosine_similarity: artists.cosine_similarity,
+        niche_bonus: artists.niche_bonus,
+        shared_artists: artists.shared_artists,
+        related_artists: artists.related_artists,
+        shared_genres: shared_genres(&my_genres, &their_genres),
+        shared_events,
+    }))
+}
@@ -1,7 +1,13 @@
 use crate::{
     errors::AppError,
     middleware::AuthUser,
-    models::User,
+    models::{Artist, Period, User},
+    services::{
+        artist_tag_service::GENRE_TAG_MIN_WEIGHT,
+        compatibility_service::CommonTrack,
+        discover_service::{birth_date_bounds, haversine_km, haversine_sql, BoundingBox, FeedCursor},
+        normalize_gender_list, DiscoverService, TasteHistoryService,
+    },
     AppState,
 };
 use axum::{
@@ -18,131 +24,145 @@ pub struct DiscoverProfile {
     pub bio: Option<String>,
     pub photos: Vec<String>,
     pub top_artists: Vec<String>,
+    /// What they're "currently obsessed with" (last 7 days)
+    pub current_top_artists: Vec<String>,
+    /// All-time favourites
+    pub all_time_top_artists: Vec<String>,
     pub common_artists: Vec<String>,
+    pub common_tracks: Vec<CommonTrack>,
+    /// An artist new in their top 20 this month, for "recently got into X"
+    pub recently_into: Option<String>,
     pub compatibility_score: f64,
     pub distance_km: Option<f64>,
 }
 
+/// One page of the discover feed; pass `next_cursor` back as `cursor` to get the next page
+#[derive(Debug, Serialize)]
+pub struct DiscoverPage {
+    pub profiles: Vec<DiscoverProfile>,
+    pub next_cursor: Option<String>,
+    /// The user's first feed is still being built; ask again shortly
+    pub building: bool,
+}
+
+/// A prospect from the precomputed feed, with the score it was ranked by
+#[derive(sqlx::FromRow)]
+struct FeedEntry {
+    #[sqlx(flatten)]
+    user: User,
+    feed_score: f64,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DiscoverFilters {
     pub min_age: Option<u32>,
     pub max_age: Option<u32>,
-    pub gender: Option<String>,
+    pub gender: Option<String>, // Comma-separated list
     pub max_distance: Option<f64>,
     pub genres: Option<String>, // Comma-separated list
+    pub limit: Option<usize>,
+    pub cursor: Option<String>,
 }
 
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 50;
+
 pub async fn get_discover_profiles(
     Extension(auth_user): Extension<AuthUser>,
     State(app_state): State<AppState>,
     Query(filters): Query<DiscoverFilters>,
-) -> Result<Json<Vec<DiscoverProfile>>, AppError> {
+) -> Result<Json<DiscoverPage>, AppError> {
+    let limit = filters.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let cursor = filters.cursor.as_deref().map(FeedCursor::decode).transpose()?;
+
     // Get current user's top artists
     let current_user_artists = app_state.lastfm_service
-        .get_user_top_artists(&app_state.pool, &auth_user.user_id, 50)
+        .get_user_top_artists(&app_state.pool, &auth_user.user_id, Period::default(), 50)
         .await?;
 
     if current_user_artists.is_empty() {
-        return Ok(Json(vec![]));
+        return Ok(Json(DiscoverPage {
+            profiles: vec![],
+            next_cursor: None,
+            building: false,
+        }));
     }
 
+    let current_user_tracks = app_state.lastfm_service
+        .get_user_top_tracks(&app_state.pool, &auth_user.user_id, Period::default(), 50)
+        .await?;
+
     // Get current user's location for distance filtering
     let current_user: User = sqlx::query_as("SELECT * FROM users WHERE id = ?")
         .bind(&auth_user.user_id)
         .fetch_one(&app_state.pool)
         .await?;
 
-    // Build query with filters
-    let mut query = String::from(
-        "SELECT u.* FROM users u
-         WHERE u.id != ?
-         AND u.id NOT IN (SELECT to_user_id FROM likes WHERE from_user_id = ?)
-         AND u.id NOT IN (SELECT blocked_id FROM blocks WHERE blocker_id = ?)
-         AND u.lastfm_username IS NOT NULL"
-    );
-
-    // Apply filters
-    if filters.gender.is_some() {
-        query.push_str(" AND u.gender = ?");
+    // Feeds are normally rebuilt after each sync; start one for users who have never had one
+    if !DiscoverService::has_built_feed(&app_state.pool, &auth_user.user_id).await? {
+        app_state.discover_service.spawn_first_build(&app_state.pool, &auth_user.user_id);
+        return Ok(Json(DiscoverPage {
+            profiles: vec![],
+            next_cursor: None,
+            building: true,
+        }));
     }
 
-    query.push_str(" LIMIT 50");
-
-    let mut sql_query = sqlx::query_as::<_, User>(&query)
-        .bind(&auth_user.user_id)
-        .bind(&auth_user.user_id)
-        .bind(&auth_user.user_id);
-
-    if let Some(gender) = &filters.gender {
-        sql_query = sql_query.bind(gender);
+    // Nobody can be within range of a user whose own location is unknown
+    let origin = match (current_user.latitude, current_user.longitude) {
+        (Some(lat), Some(lon)) => Some((lat, lon)),
+        _ => None,
+    };
+    if filters.max_distance.is_some() && origin.is_none() {
+        return Ok(Json(DiscoverPage {
+            profiles: vec![],
+            next_cursor: None,
+            building: false,
+        }));
     }
 
-    let potential_matches = sql_query.fetch_all(&app_state.pool).await?;
-
-    let mut profiles = Vec::new();
+    let genres = filters.genres.as_deref().map(parse_genres).unwrap_or_default();
 
-    for user in potential_matches {
-        // Apply age filter
-        if let Some(age) = user.age() {
-            if let Some(min_age) = filters.min_age {
-                if age < min_age {
-                    continue;
-                }
-            }
-            if let Some(max_age) = filters.max_age {
-                if age > max_age {
-                    continue;
-                }
+    // Every filter runs in SQL, so a full page means there may be more and a short one means the end
+    let page = fetch_feed_page(
+        &app_state,
+        &current_user,
+        &filters,
+        &genres,
+        origin,
+        cursor.as_ref(),
+        limit,
+    )
+    .await?;
+    let next_cursor = match page.last() {
+        Some(last) if page.len() == limit => Some(
+            FeedCursor {
+                score: last.feed_score,
+                prospect_id: last.user.id.clone(),
             }
-        }
+            .encode(),
+        ),
+        _ => None,
+    };
 
-        // Calculate distance if both users have location
-        let distance_km = if let (Some(lat1), Some(lon1), Some(lat2), Some(lon2)) = (
-            current_user.latitude,
-            current_user.longitude,
-            user.latitude,
-            user.longitude,
-        ) {
-            Some(calculate_distance(lat1, lon1, lat2, lon2))
-        } else {
-            None
-        };
+    let mut profiles = Vec::new();
 
-        // Apply distance filter
-        if let Some(max_distance) = filters.max_distance {
-            if let Some(distance) = distance_km {
-                if distance > max_distance {
-                    continue;
-                }
-            } else {
-                // Skip users without location if distance filter is applied
-                continue;
+    for FeedEntry { user, feed_score: compatibility_score } in page {
+        // Distance for display; the filter itself was applied in SQL
+        let distance_km = match (origin, user.latitude, user.longitude) {
+            (Some((lat1, lon1)), Some(lat2), Some(lon2)) => {
+                Some(haversine_km(lat1, lon1, lat2, lon2))
             }
-        }
-
-        // Get compatibility score
-        let compatibility_score = app_state.compatibility_service
-            .calculate_compatibility(&app_state.pool, &auth_user.user_id, &user.id)
-            .await
-            .unwrap_or(0.0);
-
-        // Skip users with very low compatibility
-        if compatibility_score < 10.0 {
-            continue;
-        }
+            _ => None,
+        };
 
-        // Get user's top artists
-        let user_artists = app_state.lastfm_service
-            .get_user_top_artists(&app_state.pool, &user.id, 10)
+        // Get user's top artists for every period
+        let mut user_taste = app_state.lastfm_service
+            .get_user_taste(&app_state.pool, &user.id, 10)
             .await?;
-
-        // Apply genre filter if specified
-        if let Some(_genres_str) = &filters.genres {
-            // TODO: Implement genre filtering based on Last.fm artist tags
-            // For now, we skip this filter as it requires artist tag data
-            // In production, you would query artist tags and match against requested genres
-            tracing::warn!("Genre filtering requested but not yet implemented");
-        }
+        let user_artists = user_taste.remove(&Period::default()).unwrap_or_default();
+        let current_top_artists = top_artist_names(user_taste.remove(&Period::SevenDay));
+        let all_time_top_artists = top_artist_names(user_taste.remove(&Period::Overall));
 
         // Get common artists
         let common_artists = app_state.compatibility_service.get_common_artists(
@@ -151,6 +171,18 @@ pub async fn get_discover_profiles(
             3,
         );
 
+        // Get common tracks
+        let user_tracks = app_state.lastfm_service
+            .get_user_top_tracks(&app_state.pool, &user.id, Period::default(), 50)
+            .await?;
+        let common_tracks = app_state.compatibility_service.get_common_tracks(
+            &current_user_tracks,
+            &user_tracks,
+            3,
+        );
+
+        let recently_into = TasteHistoryService::recently_got_into(&app_state.pool, &user.id).await?;
+
         // Get user's photos
         let photos = sqlx::query_as::<_, crate::models::Photo>(
             "SELECT * FROM photos WHERE user_id = ? ORDER BY position ASC"
@@ -160,7 +192,7 @@ pub async fn get_discover_profiles(
         .await?;
 
         let age = user.age();
-        
+
         profiles.push(DiscoverProfile {
             id: user.id,
             name: user.name,
@@ -168,30 +200,179 @@ pub async fn get_discover_profiles(
             bio: user.bio,
             photos: photos.into_iter().map(|p| p.url).collect(),
             top_artists: user_artists.into_iter().take(5).map(|a| a.name).collect(),
+            current_top_artists,
+            all_time_top_artists,
             common_artists,
+            common_tracks,
+            recently_into,
             compatibility_score,
             distance_km,
         });
     }
 
-    // Sort by compatibility score (highest first)
-    profiles.sort_by(|a, b| b.compatibility_score.partial_cmp(&a.compatibility_score).unwrap());
-
-    Ok(Json(profiles))
+    Ok(Json(DiscoverPage {
+        profiles,
+        next_cursor,
+        building: false,
+    }))
 }
 
-/// Calculate distance between two points using Haversine formula
-fn calculate_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
-    let r = 6371.0; // Earth's radius in kilometers
+/// Read the page of feed rows after `after`, with every filter applied in SQL
+async fn fetch_feed_page(
+    app_state: &AppState,
+    current_user: &User,
+    filters: &DiscoverFilters,
+    genres: &[String],
+    origin: Option<(f64, f64)>,
+    after: Option<&FeedCursor>,
+    limit: usize,
+) -> Result<Vec<FeedEntry>, AppError> {
+    // Build query with filters
+    let mut query = String::from(
+        "SELECT u.*, CAST(dc.compatibility_score AS DOUBLE) AS feed_score
+         FROM discover_cache dc
+         INNER JOIN users u ON u.id = dc.prospect_id
+         WHERE dc.user_id = ?
+         AND u.id NOT IN (SELECT to_user_id FROM likes WHERE from_user_id = ?)
+         AND u.id NOT IN (
+            SELECT to_user_id FROM passes
+            WHERE from_user_id = ? AND created_at > DATE_SUB(NOW(), INTERVAL ? DAY)
+         )
+         AND NOT EXISTS (
+            SELECT 1 FROM blocks b
+            WHERE (b.blocker_id = ? AND b.blocked_id = u.id) OR (b.blocker_id = u.id AND b.blocked_id = ?)
+         )
+         AND EXISTS (SELECT 1 FROM scrobbles_cache sc WHERE sc.user_id = u.id)"
+    );
+
+    // Mutual preferences: their gender is one I'm looking for and mine is one they're looking for.
+    // No `looking_for` means open to everyone; a profile without a gender can't meet a preference.
+    if current_user.looking_for.is_some() {
+        query.push_str(" AND FIND_IN_SET(u.gender, ?) > 0");
+    }
+    if current_user.gender.is_some() {
+        query.push_str(" AND (u.looking_for IS NULL OR FIND_IN_SET(?, u.looking_for) > 0)");
+    } else {
+        query.push_str(" AND u.looking_for IS NULL");
+    }
+
+    // Apply filters
+    let genders = filters.gender.as_deref().map(normalize_gender_list).transpose()?;
+    if genders.is_some() {
+        query.push_str(" AND FIND_IN_SET(u.gender, ?) > 0");
+    }
+
+    // Age filter as a birth date range; profiles without a birth date aren't filtered out
+    let (born_after, born_on_or_before) =
+        birth_date_bounds(chrono::Utc::now().date_naive(), filters.min_age, filters.max_age);
+    if born_after.is_some() {
+        query.push_str(" AND (u.birth_date IS NULL OR u.birth_date > ?)");
+    }
+    if born_on_or_before.is_some() {
+        query.push_str(" AND (u.birth_date IS NULL OR u.birth_date <= ?)");
+    }
+
+    // Distance filter: a bounding box the location index can serve, then the exact distance
+    let distance = filters.max_distance.zip(origin);
+    let bbox = distance.map(|(max_distance, (lat, lon))| BoundingBox::around(lat, lon, max_distance));
+    if let Some(bbox) = &bbox {
+        query.push_str(" AND u.latitude BETWEEN ? AND ?");
+        match bbox.longitude {
+            Some((min_lon, max_lon)) if min_lon <= max_lon => {
+                query.push_str(" AND u.longitude BETWEEN ? AND ?")
+            }
+            Some(_) => query.push_str(" AND (u.longitude >= ? OR u.longitude <= ?)"),
+            None => query.push_str(" AND u.longitude IS NOT NULL"),
+        }
+        query.push_str(&format!(" AND {} <= ?", haversine_sql("u")));
+    }
+
+    // Genre filter: the candidate must have a current top artist strongly tagged with one of the genres
+    if !genres.is_empty() {
+        let placeholders = vec!["?"; genres.len()].join(", ");
+        query.push_str(&format!(
+            " AND u.id IN (
+                SELECT sc.user_id FROM scrobbles_cache sc
+                INNER JOIN artist_tags at ON at.artist_key = sc.artist_key
+                WHERE sc.period = ? AND sc.track_name IS NULL
+                AND at.weight >= ? AND at.tag IN ({}))",
+            placeholders
+        ));
+    }
+
+    // Keyset pagination: strictly after the cursor in (score DESC, id ASC) order
+    if after.is_some() {
+        query.push_str(
+            " AND (dc.compatibility_score < ? OR (dc.compatibility_score = ? AND dc.prospect_id > ?))",
+        );
+    }
+
+    query.push_str(" ORDER BY dc.compatibility_score DESC, dc.prospect_id ASC LIMIT ?");
 
-    let lat1_rad = lat1.to_radians();
-    let lat2_rad = lat2.to_radians();
-    let delta_lat = (lat2 - lat1).to_radians();
-    let delta_lon = (lon2 - lon1).to_radians();
+    let mut sql_query = sqlx::query_as::<_, FeedEntry>(&query)
+        .bind(&current_user.id)
+        .bind(&current_user.id)
+        .bind(&current_user.id)
+        .bind(app_state.config.pass_cooldown_days)
+        .bind(&current_user.id)
+        .bind(&current_user.id);
 
-    let a = (delta_lat / 2.0).sin().powi(2)
-        + lat1_rad.cos() * lat2_rad.cos() * (delta_lon / 2.0).sin().powi(2);
-    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+    if let Some(looking_for) = &current_user.looking_for {
+        sql_query = sql_query.bind(looking_for);
+    }
+    if let Some(gender) = &current_user.gender {
+        sql_query = sql_query.bind(gender);
+    }
+
+    if let Some(genders) = genders {
+        sql_query = sql_query.bind(genders);
+    }
+
+    if let Some(born_after) = born_after {
+        sql_query = sql_query.bind(born_after);
+    }
+    if let Some(born_on_or_before) = born_on_or_before {
+        sql_query = sql_query.bind(born_on_or_before);
+    }
+
+    if let (Some(bbox), Some((max_distance, (lat, lon)))) = (&bbox, distance) {
+        sql_query = sql_query.bind(bbox.min_lat).bind(bbox.max_lat);
+        if let Some((min_lon, max_lon)) = bbox.longitude {
+            sql_query = sql_query.bind(min_lon).bind(max_lon);
+        }
+        sql_query = sql_query.bind(lat).bind(lat).bind(lon).bind(max_distance);
+    }
+
+    if !genres.is_empty() {
+        sql_query = sql_query.bind(Period::default().as_str()).bind(GENRE_TAG_MIN_WEIGHT);
+        for genre in genres {
+            sql_query = sql_query.bind(genre);
+        }
+    }
+
+    if let Some(after) = after {
+        sql_query = sql_query.bind(after.score).bind(after.score).bind(&after.prospect_id);
+    }
+
+    let entries = sql_query.bind(limit as i64).fetch_all(&app_state.pool).await?;
+
+    Ok(entries)
+}
+
+fn top_artist_names(artists: Option<Vec<Artist>>) -> Vec<String> {
+    artists
+        .unwrap_or_default()
+        .into_iter()
+        .take(5)
+        .map(|a| a.name)
+        .collect()
+}
 
-    r * c
+/// Parse the comma-separated `genres` filter into lowercased tag names (tags are stored lowercased)
+fn parse_genres(genres: &str) -> Vec<String> {
+    genres
+        .split(',')
+        .map(|g| g.trim().to_lowercase())
+        .filter(|g| !g.is_empty())
+        .collect()
 }
@@ -0,0 +1,81 @@
+use crate::{
+    errors::AppError,
+    middleware::AuthUser,
+    models::Period,
+    services::history_import::{aggregate_listens, parse_export, ExportFormat},
+    AppState,
+};
+use axum::{
+    extract::{Multipart, State},
+    Extension, Json,
+};
+use std::collections::HashMap;
+
+/// Largest export file accepted; years of scrobbles are a few tens of MB as CSV
+pub const MAX_IMPORT_BYTES: usize = 64 * 1024 * 1024;
+
+/// Import listening history from a Last.fm CSV or ListenBrainz export, for users whose Last.fm
+/// profile can't be synced
+/// Multipart fields: `file`, and optionally `format` (`lastfm_csv` or `listenbrainz_json`,
+/// detected from the contents when left out). Replaces the user's cached top artists and tracks.
+pub async fn import_listening_history(
+    Extension(auth_user): Extension<AuthUser>,
+    State(app_state): State<AppState>,
+    mut multipart: Multipart,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let invalid_upload = |e: axum::extract::multipart::MultipartError| {
+        AppError::Validation(format!("Invalid upload: {}", e))
+    };
+
+    let mut contents = None;
+    let mut format = None;
+    while let Some(field) = multipart.next_field().await.map_err(invalid_upload)? {
+        match field.name() {
+            Some("file") => contents = Some(field.text().await.map_err(invalid_upload)?),
+            Some("format") => {
+                let value = field.text().await.map_err(invalid_upload)?;
+                format = Some(value.parse::<ExportFormat>().map_err(AppError::Validation)?);
+            }
+            _ => {}
+        }
+    }
+
+    let contents = contents.ok_or_else(|| AppError::Validation("Missing export file".to_string()))?;
+    let format = format.unwrap_or_else(|| ExportFormat::detect(&contents));
+
+    // Large exports take a while to parse, so keep that off the async workers
+    let (listens_count, data) = tokio::task::spawn_blocking(move || {
+        let listens = parse_export(format, &contents)?;
+        Ok::<_, AppError>((listens.len(), aggregate_listens(&listens, chrono::Utc::now())))
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("Import task failed: {}", e)))??;
+
+    app_state.lastfm_service
+        .store_listening_data(&app_state.pool, &auth_user.user_id, &data, format.source())
+        .await?;
+
+    let period_counts: HashMap<Period, usize> = data
+        .artists
+        .iter()
+        .map(|(period, artists)| (*period, artists.len()))
+        .collect();
+    let tracks_count = data.tracks.get(&Period::default()).map_or(0, Vec::len);
+
+    // Same follow-up work as after a sync: feed, tags, achievements, music DNA
+    let sync_service = app_state.sync_service.clone();
+    let pool = app_state.pool.clone();
+    let user_id = auth_user.user_id.clone();
+    tokio::spawn(async move {
+        sync_service.after_sync(&pool, &user_id).await;
+    });
+
+    Ok(Json(serde_json::json!({
+        "message": "Listening history imported successfully",
+        "source": format.source(),
+        "listens_count": listens_count,
+        "artists_count": period_counts.get(&Period::default()).copied().unwrap_or(0),
+        "tracks_count": tracks_count,
+        "period_counts": period_counts,
+    })))
+}
@@ -1,59 +1,156 @@
 use crate::{
     errors::AppError,
     middleware::AuthUser,
+    models::Period,
+    services::{
+        lastfm_service::LastFmAuthRequest, listening_provider::LASTFM_PROVIDER, ListeningAccount, SyncService,
+    },
     AppState,
 };
 use axum::{
-    extract::State,
+    extract::{Query, State},
     Extension, Json,
 };
 use serde::Deserialize;
+use std::collections::HashMap;
 
 #[derive(Debug, Deserialize)]
-pub struct ConnectLastFmRequest {
-    pub username: String,
+pub struct CompleteLastFmAuthRequest {
+    pub token: String,
 }
 
-pub async fn connect_lastfm(
+/// Start connecting a Last.fm account
+/// Returns a request token and the Last.fm URL where the user must authorize it
+pub async fn start_lastfm_auth(
     Extension(auth_user): Extension<AuthUser>,
     State(app_state): State<AppState>,
-    Json(req): Json<ConnectLastFmRequest>,
+) -> Result<Json<LastFmAuthRequest>, AppError> {
+    let auth_request = app_state.lastfm_service
+        .begin_auth(&app_state.pool, &auth_user.user_id)
+        .await?;
+
+    Ok(Json(auth_request))
+}
+
+/// Finish connecting a Last.fm account after the user authorized the token
+pub async fn complete_lastfm_auth(
+    Extension(auth_user): Extension<AuthUser>,
+    State(app_state): State<AppState>,
+    Json(req): Json<CompleteLastFmAuthRequest>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-    // Update user with Last.fm username
-    sqlx::query("UPDATE users SET lastfm_username = ?, lastfm_connected_at = NOW() WHERE id = ?")
-        .bind(&req.username)
-        .bind(&auth_user.user_id)
-        .execute(&app_state.pool)
+    let username = app_state.lastfm_service
+        .complete_auth(&app_state.pool, &auth_user.user_id, &req.token)
         .await?;
 
     Ok(Json(serde_json::json!({
         "message": "Last.fm account connected successfully",
-        "username": req.username,
+        "username": username,
     })))
 }
 
+/// Sync listening data from the user's chosen provider (Last.fm or ListenBrainz)
 pub async fn sync_scrobbles(
     Extension(auth_user): Extension<AuthUser>,
     State(app_state): State<AppState>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-    // Get user's Last.fm username
-    let user = sqlx::query_as::<_, crate::models::User>("SELECT * FROM users WHERE id = ?")
-        .bind(&auth_user.user_id)
-        .fetch_optional(&app_state.pool)
+    let account = ListeningAccount::for_user(&app_state.pool, &auth_user.user_id)
         .await?
-        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        .ok_or_else(|| AppError::Validation("No account connected for your listening provider".to_string()))?;
 
-    let lastfm_username = user
-        .lastfm_username
-        .ok_or_else(|| AppError::Validation("Last.fm account not connected".to_string()))?;
+    // Charts cached by an earlier sync would hide anything scrobbled since
+    if account.provider == LASTFM_PROVIDER {
+        app_state.lastfm_service.invalidate_cached_charts(&account.username).await;
+    }
 
-    // Sync scrobbles
-    let artists = app_state.lastfm_service
-        .sync_user_scrobbles(&app_state.pool, &auth_user.user_id, &lastfm_username)
+    let synced = app_state.sync_service
+        .sync_user(&app_state.pool, &auth_user.user_id, &account)
         .await?;
 
+    let period_counts: HashMap<Period, usize> = synced
+        .artists
+        .iter()
+        .map(|(period, artists)| (*period, artists.len()))
+        .collect();
+    let tracks_count = synced.tracks.get(&Period::default()).map_or(0, Vec::len);
+
+    // Tag lookups for newly seen artists can take a while, so they run in the background
+    let sync_service = app_state.sync_service.clone();
+    let pool = app_state.pool.clone();
+    let user_id = auth_user.user_id.clone();
+    tokio::spawn(async move {
+        sync_service.after_sync(&pool, &user_id).await;
+    });
+
     Ok(Json(serde_json::json!({
         "message": "Scrobbles synced successfully",
-        "artists_count": artists.len(),
+        "artists_count": period_counts.get(&Period::default()).copied().unwrap_or(0),
+        "tracks_count": tracks_count,
+        "period_counts": period_counts,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TopArtistsQuery {
+    #[serde(default)]
+    pub period: Period,
+    pub limit: Option<i32>,
+}
+
+/// Get the current user's synced top artists and tracks for a Last.fm period
+pub async fn get_top_artists(
+    Extension(auth_user): Extension<AuthUser>,
+    State(app_state): State<AppState>,
+    Query(query): Query<TopArtistsQuery>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 50);
+
+    let artists = app_state.lastfm_service
+        .get_user_top_artists(&app_state.pool, &auth_user.user_id, query.period, limit)
+        .await?;
+    let tracks = app_state.lastfm_service
+        .get_user_top_tracks(&app_state.pool, &auth_user.user_id, query.period, limit)
+        .await?;
+
+    Ok(Json(serde_json::json!({
+        "period": query.period,
+        "artists": artists,
+        "tracks": tracks,
+    })))
+}
+
+/// Get the current user's last sync outcome (for "last synced X ago")
+pub async fn get_sync_status(
+    Extension(auth_user): Extension<AuthUser>,
+    State(app_state): State<AppState>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let status = SyncService::get_status(&app_state.pool, &auth_user.user_id).await?;
+
+    Ok(Json(serde_json::json!({
+        "status": status.as_ref().map(|s| s.status.as_str()),
+        "last_synced_at": status.as_ref().and_then(|s| s.last_synced_at),
+        "last_attempt_at": status.as_ref().and_then(|s| s.last_attempt_at),
+        "last_error": status.as_ref().and_then(|s| s.last_error.as_deref()),
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GenresQuery {
+    #[serde(default)]
+    pub period: Period,
+}
+
+/// Get the current user's genre distribution for a Last.fm period
+pub async fn get_genres(
+    Extension(auth_user): Extension<AuthUser>,
+    State(app_state): State<AppState>,
+    Query(query): Query<GenresQuery>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let genres = app_state.artist_tag_service
+        .get_user_genres(&app_state.pool, &auth_user.user_id, query.period, 20)
+        .await?;
+
+    Ok(Json(serde_json::json!({
+        "period": query.period,
+        "genres": genres,
     })))
 }
@@ -0,0 +1,63 @@
+use crate::{
+    errors::AppError,
+    middleware::AuthUser,
+    services::listening_provider::LISTENBRAINZ_PROVIDER,
+    AppState,
+};
+use axum::{extract::State, Extension, Json};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct ConnectListenBrainzRequest {
+    pub username: String,
+}
+
+/// Connect a ListenBrainz account and sync from it from now on
+/// ListenBrainz statistics are public, so only the username is needed. Call `/lastfm/sync` (or
+/// wait for the background re-sync) to fetch the listening data.
+pub async fn connect_listenbrainz(
+    Extension(auth_user): Extension<AuthUser>,
+    State(app_state): State<AppState>,
+    Json(req): Json<ConnectListenBrainzRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let username = req.username.trim();
+    if username.is_empty() {
+        return Err(AppError::Validation("ListenBrainz username is required".to_string()));
+    }
+
+    // Fails with 404 for users that don't exist
+    let listen_count = app_state.listenbrainz_service.listen_count(username).await?;
+
+    let mut transaction = app_state.pool.begin().await?;
+
+    let previous_username: Option<String> =
+        sqlx::query_scalar("SELECT listenbrainz_username FROM users WHERE id = ? FOR UPDATE")
+            .bind(&auth_user.user_id)
+            .fetch_optional(&mut *transaction)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    sqlx::query("UPDATE users SET listenbrainz_username = ?, listening_provider = ? WHERE id = ?")
+        .bind(username)
+        .bind(LISTENBRAINZ_PROVIDER)
+        .bind(&auth_user.user_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    // Listening data from a previously connected account no longer describes this user
+    if previous_username.is_some_and(|previous| !previous.eq_ignore_ascii_case(username)) {
+        sqlx::query("DELETE FROM scrobbles_cache WHERE user_id = ? AND source = ?")
+            .bind(&auth_user.user_id)
+            .bind(LISTENBRAINZ_PROVIDER)
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "ListenBrainz account connected successfully",
+        "username": username,
+        "listen_count": listen_count,
+    })))
+}
@@ -1,7 +1,8 @@
 use crate::{
     errors::AppError,
     middleware::AuthUser,
-    models::CreateLike,
+    models::{CreateLike, CreatePass, MatchNowPlaying},
+    services::NowPlayingService,
     AppState,
 };
 use axum::{
@@ -31,6 +32,41 @@ pub async fn create_like(
     }
 }
 
+pub async fn create_pass(
+    Extension(auth_user): Extension<AuthUser>,
+    State(app_state): State<AppState>,
+    Json(create_pass): Json<CreatePass>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    app_state.match_service
+        .create_pass(&app_state.pool, &auth_user.user_id, &create_pass.to_user_id)
+        .await?;
+
+    Ok(Json(serde_json::json!({
+        "passed": true,
+    })))
+}
+
+pub async fn undo_last_swipe(
+    Extension(auth_user): Extension<AuthUser>,
+    State(app_state): State<AppState>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let swipe = app_state.match_service
+        .undo_last_swipe(&app_state.pool, &auth_user.user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Nothing to undo".to_string()))?;
+
+    // Liking took them out of the feed, so put them back
+    if swipe.kind == "like" {
+        app_state.discover_service
+            .restore_prospect(&app_state.pool, &auth_user.user_id, &swipe.to_user_id)
+            .await?;
+    }
+
+    Ok(Json(serde_json::json!({
+        "undone": swipe,
+    })))
+}
+
 pub async fn get_matches(
     Extension(auth_user): Extension<AuthUser>,
     State(app_state): State<AppState>,
@@ -39,6 +75,15 @@ pub async fn get_matches(
     Ok(Json(matches))
 }
 
+/// What matches are listening to now; later changes arrive as `now_playing` WebSocket messages
+pub async fn get_matches_now_playing(
+    Extension(auth_user): Extension<AuthUser>,
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<MatchNowPlaying>>, AppError> {
+    let playing = NowPlayingService::get_for_matches(&app_state.pool, &auth_user.user_id).await?;
+    Ok(Json(playing))
+}
+
 pub async fn delete_match(
     Extension(auth_user): Extension<AuthUser>,
     Path(match_id): Path<String>,
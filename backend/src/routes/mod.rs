@@ -9,3 +9,8 @@ pub mod websocket;
 pub mod notifications;
 pub mod events;
 pub mod achievements;
+pub mod compatibility;
+pub mod music_dna;
+pub mod taste_history;
+pub mod history_import;
+pub mod listenbrainz;
@@ -0,0 +1,44 @@
+use crate::{
+    errors::AppError,
+    middleware::AuthUser,
+    services::music_dna_service::{MusicDna, PublicMusicDna},
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+
+/// Get the current user's full music DNA profile
+pub async fn get_my_music_dna(
+    Extension(auth_user): Extension<AuthUser>,
+    State(app_state): State<AppState>,
+) -> Result<Json<MusicDna>, AppError> {
+    let dna = app_state
+        .music_dna_service
+        .get_music_dna(&app_state.pool, &auth_user.user_id)
+        .await?;
+
+    Ok(Json(dna))
+}
+
+/// Get another user's music DNA (public view: label and broad strokes only)
+pub async fn get_music_dna_public(
+    State(app_state): State<AppState>,
+    Path(user_id): Path<String>,
+) -> Result<Json<PublicMusicDna>, AppError> {
+    let exists: Option<String> = sqlx::query_scalar("SELECT id FROM users WHERE id = ?")
+        .bind(&user_id)
+        .fetch_optional(&app_state.pool)
+        .await?;
+    if exists.is_none() {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    let dna = app_state
+        .music_dna_service
+        .get_music_dna(&app_state.pool, &user_id)
+        .await?;
+
+    Ok(Json(dna.into()))
+}
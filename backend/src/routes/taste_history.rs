@@ -0,0 +1,31 @@
+use crate::{
+    errors::AppError,
+    middleware::AuthUser,
+    services::{taste_history_service::TasteTimeline, TasteHistoryService},
+    AppState,
+};
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use serde::Deserialize;
+
+const DEFAULT_TIMELINE_MONTHS: u32 = 6;
+const MAX_TIMELINE_MONTHS: u32 = 24;
+
+#[derive(Debug, Deserialize)]
+pub struct TimelineQuery {
+    pub months: Option<u32>,
+}
+
+/// How the current user's top artists and genres shifted month over month
+pub async fn get_taste_timeline(
+    Extension(auth_user): Extension<AuthUser>,
+    State(app_state): State<AppState>,
+    Query(query): Query<TimelineQuery>,
+) -> Result<Json<TasteTimeline>, AppError> {
+    let months = query.months.unwrap_or(DEFAULT_TIMELINE_MONTHS).clamp(1, MAX_TIMELINE_MONTHS);
+    let timeline = TasteHistoryService::get_timeline(&app_state.pool, &auth_user.user_id, months).await?;
+
+    Ok(Json(timeline))
+}
@@ -2,6 +2,10 @@ use crate::{
     errors::AppError,
     middleware::AuthUser,
     models::{UpdateUser, User},
+    services::{
+        listening_provider::{validate_listening_provider, LISTENBRAINZ_PROVIDER},
+        normalize_gender, normalize_gender_list, DiscoverService, ListeningAccount,
+    },
     AppState,
 };
 use axum::{
@@ -34,12 +38,37 @@ pub async fn update_me(
         || update_user.gender.is_some()
         || update_user.looking_for.is_some()
         || update_user.latitude.is_some()
-        || update_user.longitude.is_some();
+        || update_user.longitude.is_some()
+        || update_user.hide_now_playing.is_some()
+        || update_user.listening_provider.is_some();
 
     if !has_updates {
         return Err(AppError::Validation("No fields to update".to_string()));
     }
 
+    // Only switch to a provider the user has an account on
+    let listening_provider = match &update_user.listening_provider {
+        Some(provider) => {
+            let provider = validate_listening_provider(provider)?;
+            let (lastfm_username, listenbrainz_username): (Option<String>, Option<String>) =
+                sqlx::query_as("SELECT lastfm_username, listenbrainz_username FROM users WHERE id = ?")
+                    .bind(&auth_user.user_id)
+                    .fetch_optional(&app_state.pool)
+                    .await?
+                    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+            let connected = if provider == LISTENBRAINZ_PROVIDER {
+                listenbrainz_username.is_some()
+            } else {
+                lastfm_username.is_some()
+            };
+            if !connected {
+                return Err(AppError::Validation(format!("Connect a {} account first", provider)));
+            }
+            Some(provider)
+        }
+        None => None,
+    };
+
     // Use a transaction to ensure atomicity
     let mut transaction = app_state.pool.begin().await?;
 
@@ -68,14 +97,14 @@ pub async fn update_me(
     }
     if let Some(gender) = update_user.gender {
         sqlx::query("UPDATE users SET gender = ? WHERE id = ?")
-            .bind(&gender)
+            .bind(normalize_gender(&gender)?)
             .bind(&auth_user.user_id)
             .execute(&mut *transaction)
             .await?;
     }
     if let Some(looking_for) = update_user.looking_for {
         sqlx::query("UPDATE users SET looking_for = ? WHERE id = ?")
-            .bind(&looking_for)
+            .bind(normalize_gender_list(&looking_for)?)
             .bind(&auth_user.user_id)
             .execute(&mut *transaction)
             .await?;
@@ -95,9 +124,48 @@ pub async fn update_me(
             .await?;
     }
 
+    if let Some(hide_now_playing) = update_user.hide_now_playing {
+        sqlx::query("UPDATE users SET hide_now_playing = ? WHERE id = ?")
+            .bind(hide_now_playing)
+            .bind(&auth_user.user_id)
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    if let Some(provider) = listening_provider {
+        sqlx::query("UPDATE users SET listening_provider = ? WHERE id = ?")
+            .bind(provider)
+            .bind(&auth_user.user_id)
+            .execute(&mut *transaction)
+            .await?;
+    }
+
     // Commit transaction
     transaction.commit().await?;
 
+    // The cached listening data came from the old provider, so refresh it from the new one
+    if listening_provider.is_some() {
+        let sync_service = app_state.sync_service.clone();
+        let pool = app_state.pool.clone();
+        let user_id = auth_user.user_id.clone();
+        tokio::spawn(async move {
+            let synced = match ListeningAccount::for_user(&pool, &user_id).await {
+                Ok(Some(account)) => sync_service.sync_user(&pool, &user_id, &account).await,
+                Ok(None) => return,
+                Err(e) => Err(e),
+            };
+            match synced {
+                Ok(_) => sync_service.after_sync(&pool, &user_id).await,
+                Err(e) => tracing::warn!("Sync after switching provider failed for user {}: {}", user_id, e),
+            }
+        });
+    }
+
+    // Matches shouldn't keep seeing a track from before it was hidden
+    if update_user.hide_now_playing == Some(true) {
+        app_state.now_playing_service.clear(&app_state.pool, &auth_user.user_id).await?;
+    }
+
     // Fetch updated user
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = ?")
         .bind(&auth_user.user_id)
@@ -120,3 +188,26 @@ pub async fn get_user(
 
     Ok(Json(user))
 }
+
+pub async fn block_user(
+    Extension(auth_user): Extension<AuthUser>,
+    Path(user_id): Path<String>,
+    State(app_state): State<AppState>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    if user_id == auth_user.user_id {
+        return Err(AppError::Validation("You cannot block yourself".to_string()));
+    }
+
+    sqlx::query("INSERT IGNORE INTO blocks (id, blocker_id, blocked_id) VALUES (?, ?, ?)")
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(&auth_user.user_id)
+        .bind(&user_id)
+        .execute(&app_state.pool)
+        .await?;
+
+    DiscoverService::remove_pair(&app_state.pool, &auth_user.user_id, &user_id).await?;
+
+    Ok(Json(serde_json::json!({
+        "message": "User blocked successfully"
+    })))
+}
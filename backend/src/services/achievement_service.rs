@@ -1,4 +1,4 @@
-use crate::{db::DbPool, errors::AppError};
+use crate::{db::DbPool, errors::AppError, services::ArtistTagService};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -71,6 +71,7 @@ impl AchievementService {
         .await?;
 
         let stats = Self::get_user_stats(pool, user_id).await?;
+        let genre_count = ArtistTagService::count_user_genres(pool, user_id).await?;
 
         let mut results = Vec::new();
         for achievement in achievements {
@@ -81,7 +82,7 @@ impl AchievementService {
             let (unlocked, unlocked_at, progress) = if let Some(ua) = user_achievement {
                 (true, Some(ua.unlocked_at), ua.progress)
             } else {
-                let progress = Self::calculate_progress(&achievement, &stats);
+                let progress = Self::calculate_progress(&achievement, &stats, genre_count);
                 (false, None, progress)
             };
 
@@ -190,6 +191,14 @@ impl AchievementService {
         Self::check_and_unlock_achievements(pool, user_id, 0.0).await
     }
 
+    /// Check music achievements after the user's listening data (and artist tags) were refreshed
+    pub async fn on_scrobbles_synced(
+        pool: &DbPool,
+        user_id: &str,
+    ) -> Result<Vec<Achievement>, AppError> {
+        Self::check_and_unlock_achievements(pool, user_id, 0.0).await
+    }
+
     /// Update user stats on message sent
     pub async fn on_message_sent(
         pool: &DbPool,
@@ -270,6 +279,7 @@ impl AchievementService {
         compatibility_score: f64,
     ) -> Result<Vec<Achievement>, AppError> {
         let stats = Self::get_user_stats(pool, user_id).await?;
+        let genre_count = ArtistTagService::count_user_genres(pool, user_id).await?;
         let achievements = sqlx::query_as::<_, Achievement>(
             "SELECT * FROM achievements",
         )
@@ -300,6 +310,7 @@ impl AchievementService {
                 "messages_sent" => stats.messages_sent >= achievement.requirement_value,
                 "streak_days" => stats.current_streak_days >= achievement.requirement_value,
                 "likes_received" => stats.total_likes_received >= achievement.requirement_value,
+                "genre_count" => genre_count >= achievement.requirement_value as i64,
                 _ => false,
             };
 
@@ -336,12 +347,13 @@ impl AchievementService {
     }
 
     /// Calculate progress towards an achievement
-    fn calculate_progress(achievement: &Achievement, stats: &UserStats) -> i32 {
+    fn calculate_progress(achievement: &Achievement, stats: &UserStats, genre_count: i64) -> i32 {
         let current = match achievement.requirement_type.as_str() {
             "matches" => stats.total_matches,
             "messages_sent" => stats.messages_sent,
             "streak_days" => stats.current_streak_days,
             "likes_received" => stats.total_likes_received,
+            "genre_count" => genre_count as i32,
             _ => 0,
         };
 
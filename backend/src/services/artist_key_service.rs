@@ -0,0 +1,184 @@
+use crate::{errors::AppError, models::scrobble::artist_key};
+use sqlx::MySqlConnection;
+use std::collections::HashMap;
+
+/// Stored counterpart of `ArtistIdentities`, shared by all users.
+/// Rows are keyed on `artist_key` in SQL, so an artist one user synced with an MBID and another
+/// without would never join. Name-only artists are stored under the MBID their normalized name is
+/// known to belong to instead.
+///
+/// When a name turns out to belong to more than one MBID, `scrobbles_cache` rows moved onto the
+/// first one go back to the name key. Similarity edges keep the MBID: `artist_similarities` doesn't
+/// record whether Last.fm gave one, and a stale edge only nudges related-artist credit.
+pub struct ArtistKeyService;
+
+impl ArtistKeyService {
+    /// Learn the MBIDs of artists that have one, then map the name keys of those that don't to
+    /// their MBID key where it's known. Returns name key -> MBID key.
+    /// Runs on the caller's connection, so it's part of the transaction that stores the rows.
+    pub async fn resolve<'a>(
+        conn: &mut MySqlConnection,
+        artists: impl IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    ) -> Result<HashMap<String, String>, AppError> {
+        // Like `ArtistIdentities`: a name seen with two MBIDs is ambiguous (None)
+        let mut learned: HashMap<String, Option<String>> = HashMap::new();
+        let mut name_only = Vec::new();
+        for (name, mbid) in artists {
+            match mbid.filter(|m| !m.is_empty()) {
+                Some(mbid) => {
+                    let mbid = artist_key(name, Some(mbid));
+                    learned
+                        .entry(artist_key(name, None))
+                        .and_modify(|known| {
+                            if known.as_deref() != Some(mbid.as_str()) {
+                                *known = None;
+                            }
+                        })
+                        .or_insert(Some(mbid));
+                }
+                None => name_only.push(artist_key(name, None)),
+            }
+        }
+
+        let mut name_keys: Vec<&String> = learned.keys().chain(&name_only).collect();
+        name_keys.sort();
+        name_keys.dedup();
+        if name_keys.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let sql = format!(
+            "SELECT name_key, mbid FROM artist_name_mbids WHERE name_key IN ({})",
+            vec!["?"; name_keys.len()].join(", ")
+        );
+        let mut query = sqlx::query_as::<_, (String, Option<String>)>(&sql);
+        for name_key in &name_keys {
+            query = query.bind(*name_key);
+        }
+        let mut known: HashMap<String, Option<String>> = query.fetch_all(&mut *conn).await?.into_iter().collect();
+
+        // Only names that are new, or newly ambiguous, need writing; mapped names are skipped
+        let mut changed = Vec::new();
+        let mut mapped = Vec::new();
+        let mut now_ambiguous = Vec::new();
+        for (name_key, mbid) in learned {
+            match known.get(&name_key) {
+                None => {
+                    if let Some(mbid) = &mbid {
+                        mapped.push((name_key.clone(), mbid.clone()));
+                    }
+                    changed.push((name_key.clone(), mbid.clone()));
+                    known.insert(name_key, mbid);
+                }
+                Some(Some(previous)) if mbid.as_ref() != Some(previous) => {
+                    now_ambiguous.push((name_key.clone(), previous.clone()));
+                    changed.push((name_key.clone(), None));
+                    known.insert(name_key, None);
+                }
+                Some(_) => {}
+            }
+        }
+
+        // The same order in every transaction, so concurrent syncs don't deadlock on these rows
+        changed.sort();
+        mapped.sort();
+        Self::store(conn, &changed).await?;
+        Self::move_to_mbids(conn, &mapped).await?;
+        for (name_key, previous) in &now_ambiguous {
+            Self::move_back_to_name(conn, name_key, previous).await?;
+        }
+
+        Ok(name_only
+            .into_iter()
+            .filter_map(|name_key| {
+                let mbid = known.get(&name_key)?.clone()?;
+                Some((name_key, mbid))
+            })
+            .collect())
+    }
+
+    /// Upsert name -> MBID rows; a different MBID than the stored one makes the name ambiguous
+    async fn store(conn: &mut MySqlConnection, rows: &[(String, Option<String>)]) -> Result<(), AppError> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let sql = format!(
+            "INSERT INTO artist_name_mbids (name_key, mbid) VALUES {}
+             ON DUPLICATE KEY UPDATE mbid = IF(mbid <=> VALUES(mbid), mbid, NULL)",
+            vec!["(?, ?)"; rows.len()].join(", ")
+        );
+        let mut query = sqlx::query(&sql);
+        for (name_key, mbid) in rows {
+            query = query.bind(name_key).bind(mbid);
+        }
+        query.execute(&mut *conn).await?;
+
+        Ok(())
+    }
+
+    /// Re-key rows stored under names that now have a known MBID
+    async fn move_to_mbids(conn: &mut MySqlConnection, mapped: &[(String, String)]) -> Result<(), AppError> {
+        if mapped.is_empty() {
+            return Ok(());
+        }
+
+        let cases = "WHEN ? THEN ? ".repeat(mapped.len());
+        let placeholders = vec!["?"; mapped.len()].join(", ");
+        let statements = [
+            format!(
+                "UPDATE scrobbles_cache SET artist_key = CASE artist_key {}END WHERE artist_key IN ({})",
+                cases, placeholders
+            ),
+            // An artist can already be listed under both keys; the name-keyed edge is dropped then
+            format!(
+                "UPDATE IGNORE artist_similarities SET similar_key = CASE similar_key {}END WHERE similar_key IN ({})",
+                cases, placeholders
+            ),
+        ];
+        for sql in &statements {
+            let mut query = sqlx::query(sql);
+            for (name_key, mbid) in mapped {
+                query = query.bind(name_key).bind(mbid);
+            }
+            for (name_key, _) in mapped {
+                query = query.bind(name_key);
+            }
+            query.execute(&mut *conn).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Put rows that were moved onto `mbid` only because of their name back under `name_key`
+    async fn move_back_to_name(conn: &mut MySqlConnection, name_key: &str, mbid: &str) -> Result<(), AppError> {
+        // Several spellings can map to one MBID, so pick this name's rows out by normalizing in Rust
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT id, artist_name FROM scrobbles_cache
+             WHERE artist_key = ? AND (artist_mbid IS NULL OR artist_mbid = '')",
+        )
+        .bind(mbid)
+        .fetch_all(&mut *conn)
+        .await?;
+        let ids: Vec<String> = rows
+            .into_iter()
+            .filter(|(_, name)| artist_key(name, None) == name_key)
+            .map(|(id, _)| id)
+            .collect();
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let sql = format!(
+            "UPDATE scrobbles_cache SET artist_key = ? WHERE id IN ({})",
+            vec!["?"; ids.len()].join(", ")
+        );
+        let mut query = sqlx::query(&sql).bind(name_key);
+        for id in &ids {
+            query = query.bind(id);
+        }
+        query.execute(&mut *conn).await?;
+
+        Ok(())
+    }
+}
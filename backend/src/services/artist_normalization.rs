@@ -0,0 +1,136 @@
+//! Artist identity, so the same artist matches across users however Last.fm spelled it
+//! Artists are keyed on their MusicBrainz ID when Last.fm has one and on a normalized name
+//! otherwise: "The Beatles", "Beatles" and "beatles, the" are one artist, as are "Sigur Rós" and
+//! "Sigur Ros", and "Daft Punk feat. Pharrell Williams" counts as Daft Punk.
+
+use crate::models::Artist;
+use std::collections::HashMap;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Words that start a featured-artist suffix
+const FEATURING: [&str; 3] = ["feat", "ft", "featuring"];
+
+/// Canonical form of an artist name for matching: case and diacritics folded, punctuation
+/// dropped, "&" read as "and", and a leading or trailing "the" and featured artists removed
+pub fn normalize_artist_name(name: &str) -> String {
+    let mut folded = String::with_capacity(name.len());
+    for c in name.nfkd().filter(|c| !is_combining_mark(*c)).flat_map(char::to_lowercase) {
+        match c {
+            // Dropped rather than split on, so "R.E.M." is "rem" and "Guns N' Roses" "guns n roses"
+            '.' | '\'' | '\u{2019}' => {}
+            '&' => folded.push_str(" and "),
+            c if c.is_alphanumeric() => folded.push(c),
+            _ => folded.push(' '),
+        }
+    }
+
+    let mut words: Vec<&str> = folded.split_whitespace().collect();
+    if let Some(featuring) = words.iter().skip(1).position(|w| FEATURING.contains(w)) {
+        words.truncate(featuring + 1);
+    }
+    if words.len() > 1 && words[0] == "the" {
+        words.remove(0);
+    } else if words.len() > 1 && words[words.len() - 1] == "the" {
+        words.pop();
+    }
+
+    if words.is_empty() {
+        // Nothing alphanumeric, e.g. "!!!"; the name itself is the best key there is
+        return name.trim().to_lowercase();
+    }
+    words.join(" ")
+}
+
+/// Resolves artists to shared keys when comparing two users' artists
+/// Last.fm only sometimes returns an MBID, so one user's artist can arrive with one and the other's
+/// without; a name-only artist takes the MBID of an artist with the same normalized name. Names
+/// shared by artists with different MBIDs are ambiguous and stay unresolved.
+#[derive(Debug, Clone, Default)]
+pub struct ArtistIdentities {
+    mbids: HashMap<String, Option<String>>,
+}
+
+impl ArtistIdentities {
+    pub fn new<'a>(artists: impl IntoIterator<Item = &'a Artist>) -> Self {
+        let mut mbids: HashMap<String, Option<String>> = HashMap::new();
+        for artist in artists {
+            let Some(mbid) = artist.mbid.as_deref().filter(|m| !m.is_empty()) else {
+                continue;
+            };
+            let mbid = mbid.to_lowercase();
+            mbids
+                .entry(normalize_artist_name(&artist.name))
+                .and_modify(|known| {
+                    if known.as_deref() != Some(mbid.as_str()) {
+                        *known = None;
+                    }
+                })
+                .or_insert(Some(mbid));
+        }
+
+        Self { mbids }
+    }
+
+    /// Key for comparing this artist, see `artist_key`
+    pub fn key(&self, artist: &Artist) -> String {
+        if artist.mbid.as_deref().is_some_and(|m| !m.is_empty()) {
+            return artist.key();
+        }
+
+        match self.mbids.get(&normalize_artist_name(&artist.name)) {
+            Some(Some(mbid)) => mbid.clone(),
+            _ => artist.key(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artist(name: &str, mbid: Option<&str>) -> Artist {
+        Artist {
+            name: name.to_string(),
+            mbid: mbid.map(str::to_string),
+            play_count: 0,
+            listeners: 0,
+        }
+    }
+
+    #[test]
+    fn test_normalize_artist_name() {
+        assert_eq!(normalize_artist_name("The Beatles"), "beatles");
+        assert_eq!(normalize_artist_name("Beatles, The"), "beatles");
+        assert_eq!(normalize_artist_name("  BEATLES "), "beatles");
+        assert_eq!(normalize_artist_name("Sigur Rós"), "sigur ros");
+        assert_eq!(normalize_artist_name("Motörhead"), "motorhead");
+        assert_eq!(normalize_artist_name("Simon & Garfunkel"), "simon and garfunkel");
+        assert_eq!(normalize_artist_name("R.E.M."), "rem");
+        assert_eq!(normalize_artist_name("Guns N' Roses"), "guns n roses");
+        assert_eq!(normalize_artist_name("Daft Punk feat. Pharrell Williams"), "daft punk");
+        assert_eq!(normalize_artist_name("Gorillaz (ft. De La Soul)"), "gorillaz");
+        // A lone "the" or "ft" is the name, not something to strip
+        assert_eq!(normalize_artist_name("The The"), "the");
+        assert_eq!(normalize_artist_name("Ft"), "ft");
+        assert_eq!(normalize_artist_name("!!!"), "!!!");
+    }
+
+    #[test]
+    fn test_name_only_artists_resolve_to_mbid() {
+        let beatles = artist("The Beatles", Some("B10BBBFC-CF9E-42E0-BE17-E2C3E1D2600D"));
+        let identities = ArtistIdentities::new([&beatles]);
+
+        assert_eq!(identities.key(&artist("Beatles", None)), identities.key(&beatles));
+        assert_eq!(identities.key(&artist("Grouper", None)), "name:grouper");
+    }
+
+    #[test]
+    fn test_ambiguous_names_stay_unresolved() {
+        let nirvana = artist("Nirvana", Some("5b11f4ce-a62d-471e-81fc-a69a8278c7da"));
+        let other_nirvana = artist("Nirvana", Some("9282c8b4-ca0b-4c6b-b7e3-4f7762dfc4d6"));
+        let identities = ArtistIdentities::new([&nirvana, &other_nirvana]);
+
+        assert_eq!(identities.key(&artist("Nirvana", None)), "name:nirvana");
+        assert_ne!(identities.key(&nirvana), identities.key(&other_nirvana));
+    }
+}
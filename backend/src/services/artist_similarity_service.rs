@@ -0,0 +1,209 @@
+use crate::{
+    db::DbPool,
+    errors::AppError,
+    models::artist_key,
+    services::{
+        lastfm_service::{LastFmService, SimilarArtist},
+        ArtistKeyService,
+    },
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Similar artists requested per artist
+const MAX_SIMILAR_PER_ARTIST: usize = 30;
+/// Matches weaker than this say little about the artist and are not stored
+const MIN_SIMILARITY: f64 = 0.2;
+/// How long fetched similar artists are trusted before being refreshed
+const SIMILAR_TTL_DAYS: i64 = 30;
+
+#[derive(sqlx::FromRow)]
+struct PendingArtist {
+    artist_key: String,
+    artist_name: String,
+    artist_mbid: Option<String>,
+}
+
+/// Undirected artist-similarity graph keyed by artist key
+/// Last.fm's matches aren't symmetric, so an edge keeps the stronger of the two directions.
+#[derive(Debug, Clone, Default)]
+pub struct SimilarityGraph {
+    edges: HashMap<String, HashMap<String, f64>>,
+}
+
+impl SimilarityGraph {
+    pub fn insert(&mut self, artist1: &str, artist2: &str, similarity: f64) {
+        if artist1 == artist2 {
+            return;
+        }
+        for (from, to) in [(artist1, artist2), (artist2, artist1)] {
+            let edge = self
+                .edges
+                .entry(from.to_string())
+                .or_default()
+                .entry(to.to_string())
+                .or_insert(0.0);
+            *edge = edge.max(similarity);
+        }
+    }
+
+    /// Similarity between two different artists, 0.0 when unrelated
+    pub fn similarity(&self, artist1: &str, artist2: &str) -> f64 {
+        self.edges
+            .get(artist1)
+            .and_then(|related| related.get(artist2))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+}
+
+/// Artist similarity ingestion (artist.getsimilar), so related-but-different taste can count
+/// Like tags, similar artists are stored per artist and fetched from Last.fm once for everyone
+pub struct ArtistSimilarityService {
+    lastfm_service: Arc<LastFmService>,
+}
+
+impl ArtistSimilarityService {
+    pub fn new(lastfm_service: Arc<LastFmService>) -> Self {
+        Self { lastfm_service }
+    }
+
+    /// Fetch similar artists for the user's synced artists that were never fetched or are stale
+    /// Returns the number of artists refreshed
+    pub async fn refresh_user_similar_artists(&self, pool: &DbPool, user_id: &str) -> Result<usize, AppError> {
+        let pending = sqlx::query_as::<_, PendingArtist>(
+            "SELECT sc.artist_key, MIN(sc.artist_name) AS artist_name, MIN(sc.artist_mbid) AS artist_mbid
+             FROM scrobbles_cache sc
+             LEFT JOIN artists a ON a.artist_key = sc.artist_key
+             WHERE sc.user_id = ? AND sc.track_name IS NULL
+             AND (a.similar_fetched_at IS NULL OR a.similar_fetched_at < DATE_SUB(NOW(), INTERVAL ? DAY))
+             GROUP BY sc.artist_key",
+        )
+        .bind(user_id)
+        .bind(SIMILAR_TTL_DAYS)
+        .fetch_all(pool)
+        .await?;
+
+        let mut refreshed = 0;
+        for artist in pending {
+            let similar = match self
+                .lastfm_service
+                .fetch_similar_artists(&artist.artist_name, artist.artist_mbid.as_deref(), MAX_SIMILAR_PER_ARTIST)
+                .await
+            {
+                Ok(similar) => similar,
+                Err(e) => {
+                    // Leave it unfetched so the next refresh retries
+                    tracing::warn!("Failed to fetch similar artists for {}: {}", artist.artist_name, e);
+                    continue;
+                }
+            };
+
+            Self::store_similar_artists(pool, &artist, &similar).await?;
+            refreshed += 1;
+        }
+
+        Ok(refreshed)
+    }
+
+    async fn store_similar_artists(
+        pool: &DbPool,
+        artist: &PendingArtist,
+        similar: &[SimilarArtist],
+    ) -> Result<(), AppError> {
+        let mut transaction = pool.begin().await?;
+        let known_mbids =
+            ArtistKeyService::resolve(&mut transaction, similar.iter().map(|s| (s.name.as_str(), s.mbid.as_deref())))
+                .await?;
+
+        sqlx::query(
+            "INSERT INTO artists (artist_key, name, mbid, similar_fetched_at) VALUES (?, ?, ?, NOW())
+             ON DUPLICATE KEY UPDATE name = VALUES(name), mbid = VALUES(mbid), similar_fetched_at = NOW()",
+        )
+        .bind(&artist.artist_key)
+        .bind(&artist.artist_name)
+        .bind(&artist.artist_mbid)
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query("DELETE FROM artist_similarities WHERE artist_key = ?")
+            .bind(&artist.artist_key)
+            .execute(&mut *transaction)
+            .await?;
+
+        for related in similar.iter().filter(|s| s.similarity >= MIN_SIMILARITY) {
+            let mut similar_key = artist_key(&related.name, related.mbid.as_deref());
+            if let Some(mbid_key) = known_mbids.get(&similar_key) {
+                similar_key = mbid_key.clone();
+            }
+            if similar_key == artist.artist_key {
+                continue;
+            }
+
+            sqlx::query(
+                "INSERT INTO artist_similarities (artist_key, similar_key, similar_name, similarity)
+                 VALUES (?, ?, ?, ?)
+                 ON DUPLICATE KEY UPDATE similarity = GREATEST(similarity, VALUES(similarity))",
+            )
+            .bind(&artist.artist_key)
+            .bind(&similar_key)
+            .bind(&related.name)
+            .bind(related.similarity.min(1.0))
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Similarity edges among a set of artists, in either direction
+    pub async fn load_graph(pool: &DbPool, artist_keys: &[String]) -> Result<SimilarityGraph, AppError> {
+        let mut graph = SimilarityGraph::default();
+        if artist_keys.is_empty() {
+            return Ok(graph);
+        }
+
+        let placeholders = vec!["?"; artist_keys.len()].join(", ");
+        let sql = format!(
+            "SELECT artist_key, similar_key, CAST(similarity AS DOUBLE)
+             FROM artist_similarities
+             WHERE artist_key IN ({}) AND similar_key IN ({})",
+            placeholders, placeholders
+        );
+
+        let mut query = sqlx::query_as::<_, (String, String, f64)>(&sql);
+        for key in artist_keys.iter().chain(artist_keys) {
+            query = query.bind(key);
+        }
+
+        for (artist1, artist2, similarity) in query.fetch_all(pool).await? {
+            graph.insert(&artist1, &artist2, similarity);
+        }
+
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_similarity_graph_is_symmetric() {
+        let mut graph = SimilarityGraph::default();
+        graph.insert("name:slowdive", "name:ride", 0.6);
+        graph.insert("name:ride", "name:slowdive", 0.9);
+        graph.insert("name:slowdive", "name:slowdive", 1.0);
+
+        assert_eq!(graph.similarity("name:slowdive", "name:ride"), 0.9);
+        assert_eq!(graph.similarity("name:ride", "name:slowdive"), 0.9);
+        assert_eq!(graph.similarity("name:slowdive", "name:slowdive"), 0.0);
+        assert_eq!(graph.similarity("name:slowdive", "name:metallica"), 0.0);
+    }
+}
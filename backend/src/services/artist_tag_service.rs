@@ -0,0 +1,357 @@
+use crate::{
+    db::DbPool,
+    errors::AppError,
+    models::Period,
+    services::lastfm_service::LastFmService,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Tags below this relative weight (0-100) are too weak to describe the artist and are not stored
+const MIN_TAG_WEIGHT: i32 = 10;
+/// Tags kept per artist
+const MAX_TAGS_PER_ARTIST: usize = 10;
+/// How long fetched tags are trusted before being refreshed
+const TAG_TTL_DAYS: i64 = 30;
+/// How long fetched listener counts are trusted; they grow slowly, and only their scale matters
+const LISTENERS_TTL_DAYS: i64 = 30;
+/// Minimum weight for a tag to count as one of an artist's genres (discover filter, achievements)
+pub const GENRE_TAG_MIN_WEIGHT: i32 = 50;
+
+/// Popular Last.fm tags that describe the listener or the artist's origin rather than the music
+const NON_GENRE_TAGS: &[&str] = &[
+    "seen live",
+    "favorites",
+    "favourites",
+    "favorite",
+    "favourite",
+    "my favorites",
+    "albums i own",
+    "love",
+    "awesome",
+    "beautiful",
+    "female vocalists",
+    "male vocalists",
+    "female vocalist",
+    "male vocalist",
+    "british",
+    "american",
+    "uk",
+    "usa",
+    "canadian",
+    "german",
+    "french",
+    "swedish",
+    "japanese",
+    "australian",
+    "under 2000 listeners",
+    "spotify",
+    "all",
+];
+
+/// Share of a user's listening attributed to a genre
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenreWeight {
+    pub genre: String,
+    pub weight: f64,
+}
+
+/// A genre both users listen to, with each side's share of listening
+#[derive(Debug, Clone, Serialize)]
+pub struct SharedGenre {
+    pub genre: String,
+    pub my_weight: f64,
+    pub their_weight: f64,
+}
+
+/// Genres in both distributions, strongest overlap (the smaller of the two weights) first
+pub fn shared_genres(mine: &[GenreWeight], theirs: &[GenreWeight]) -> Vec<SharedGenre> {
+    let mut shared: Vec<SharedGenre> = mine
+        .iter()
+        .filter_map(|m| {
+            theirs.iter().find(|t| t.genre == m.genre).map(|t| SharedGenre {
+                genre: m.genre.clone(),
+                my_weight: m.weight,
+                their_weight: t.weight,
+            })
+        })
+        .collect();
+    shared.sort_by(|a, b| {
+        b.my_weight
+            .min(b.their_weight)
+            .total_cmp(&a.my_weight.min(a.their_weight))
+    });
+    shared
+}
+
+#[derive(sqlx::FromRow)]
+struct UntaggedArtist {
+    artist_key: String,
+    artist_name: String,
+    artist_mbid: Option<String>,
+}
+
+/// Artist tag (genre) and listener count ingestion, and per-user genre distributions
+/// Both are stored per artist, not per user, so each artist is fetched from Last.fm once
+pub struct ArtistTagService {
+    lastfm_service: Arc<LastFmService>,
+}
+
+impl ArtistTagService {
+    pub fn new(lastfm_service: Arc<LastFmService>) -> Self {
+        Self { lastfm_service }
+    }
+
+    /// Fetch tags for the user's synced artists that were never tagged or whose tags are stale
+    /// Returns the number of artists refreshed
+    pub async fn refresh_user_artist_tags(&self, pool: &DbPool, user_id: &str) -> Result<usize, AppError> {
+        let pending = sqlx::query_as::<_, UntaggedArtist>(
+            "SELECT sc.artist_key, MIN(sc.artist_name) AS artist_name, MIN(sc.artist_mbid) AS artist_mbid
+             FROM scrobbles_cache sc
+             LEFT JOIN artists a ON a.artist_key = sc.artist_key
+             WHERE sc.user_id = ?
+             AND (a.tags_fetched_at IS NULL OR a.tags_fetched_at < DATE_SUB(NOW(), INTERVAL ? DAY))
+             GROUP BY sc.artist_key",
+        )
+        .bind(user_id)
+        .bind(TAG_TTL_DAYS)
+        .fetch_all(pool)
+        .await?;
+
+        let mut refreshed = 0;
+        for artist in pending {
+            let tags = match self
+                .lastfm_service
+                .fetch_artist_top_tags(&artist.artist_name, artist.artist_mbid.as_deref())
+                .await
+            {
+                Ok(tags) => tags,
+                Err(e) => {
+                    // Leave it untagged so the next refresh retries
+                    tracing::warn!("Failed to fetch tags for artist {}: {}", artist.artist_name, e);
+                    continue;
+                }
+            };
+
+            Self::store_artist_tags(pool, &artist, &filter_genre_tags(tags)).await?;
+            refreshed += 1;
+        }
+
+        Ok(refreshed)
+    }
+
+    /// Fetch Last.fm listener counts for the user's synced artists that have none or a stale one
+    /// Returns the number of artists refreshed
+    pub async fn refresh_user_artist_listeners(&self, pool: &DbPool, user_id: &str) -> Result<usize, AppError> {
+        let pending = sqlx::query_as::<_, UntaggedArtist>(
+            "SELECT sc.artist_key, MIN(sc.artist_name) AS artist_name, MIN(sc.artist_mbid) AS artist_mbid
+             FROM scrobbles_cache sc
+             LEFT JOIN artists a ON a.artist_key = sc.artist_key
+             WHERE sc.user_id = ? AND sc.track_name IS NULL
+             AND (a.info_fetched_at IS NULL OR a.info_fetched_at < DATE_SUB(NOW(), INTERVAL ? DAY))
+             GROUP BY sc.artist_key",
+        )
+        .bind(user_id)
+        .bind(LISTENERS_TTL_DAYS)
+        .fetch_all(pool)
+        .await?;
+
+        let mut refreshed = 0;
+        for artist in pending {
+            let listeners = match self
+                .lastfm_service
+                .fetch_artist_listeners(&artist.artist_name, artist.artist_mbid.as_deref())
+                .await
+            {
+                Ok(listeners) => listeners,
+                Err(e) => {
+                    tracing::warn!("Failed to fetch listeners for artist {}: {}", artist.artist_name, e);
+                    continue;
+                }
+            };
+
+            sqlx::query(
+                "INSERT INTO artists (artist_key, name, mbid, listeners, info_fetched_at) VALUES (?, ?, ?, ?, NOW())
+                 ON DUPLICATE KEY UPDATE listeners = VALUES(listeners), info_fetched_at = NOW()",
+            )
+            .bind(&artist.artist_key)
+            .bind(&artist.artist_name)
+            .bind(&artist.artist_mbid)
+            .bind(listeners)
+            .execute(pool)
+            .await?;
+            refreshed += 1;
+        }
+
+        // Cached taste was loaded without these counts
+        if refreshed > 0 {
+            self.lastfm_service.invalidate_cached_taste(user_id).await;
+        }
+
+        Ok(refreshed)
+    }
+
+    async fn store_artist_tags(
+        pool: &DbPool,
+        artist: &UntaggedArtist,
+        tags: &[(String, i32)],
+    ) -> Result<(), AppError> {
+        let mut transaction = pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO artists (artist_key, name, mbid, tags_fetched_at) VALUES (?, ?, ?, NOW())
+             ON DUPLICATE KEY UPDATE name = VALUES(name), mbid = VALUES(mbid), tags_fetched_at = NOW()",
+        )
+        .bind(&artist.artist_key)
+        .bind(&artist.artist_name)
+        .bind(&artist.artist_mbid)
+        .execute(&mut *transaction)
+        .await?;
+
+        sqlx::query("DELETE FROM artist_tags WHERE artist_key = ?")
+            .bind(&artist.artist_key)
+            .execute(&mut *transaction)
+            .await?;
+
+        for (tag, weight) in tags {
+            sqlx::query("INSERT INTO artist_tags (artist_key, tag, weight) VALUES (?, ?, ?)")
+                .bind(&artist.artist_key)
+                .bind(tag)
+                .bind(weight)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// A user's genre distribution for a period: each top artist spreads its (log-scaled) play count
+    /// across its tags by tag weight. Weights are normalized to sum to 1.
+    pub async fn get_user_genres(
+        &self,
+        pool: &DbPool,
+        user_id: &str,
+        period: Period,
+        limit: usize,
+    ) -> Result<Vec<GenreWeight>, AppError> {
+        let rows: Vec<(String, f64)> = sqlx::query_as(
+            "SELECT at.tag, SUM(LN(GREATEST(sc.play_count, 0) + 2) * at.weight) AS score
+             FROM scrobbles_cache sc
+             INNER JOIN artist_tags at ON at.artist_key = sc.artist_key
+             WHERE sc.user_id = ? AND sc.period = ? AND sc.track_name IS NULL
+             GROUP BY at.tag
+             ORDER BY score DESC",
+        )
+        .bind(user_id)
+        .bind(period.as_str())
+        .fetch_all(pool)
+        .await?;
+
+        let total: f64 = rows.iter().map(|(_, score)| score).sum();
+        if total == 0.0 {
+            return Ok(vec![]);
+        }
+
+        Ok(rows
+            .into_iter()
+            .take(limit)
+            .map(|(genre, score)| GenreWeight {
+                genre,
+                weight: score / total,
+            })
+            .collect())
+    }
+
+    /// Number of distinct genres among any of the user's synced artists
+    pub async fn count_user_genres(pool: &DbPool, user_id: &str) -> Result<i64, AppError> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(DISTINCT at.tag)
+             FROM scrobbles_cache sc
+             INNER JOIN artist_tags at ON at.artist_key = sc.artist_key
+             WHERE sc.user_id = ? AND at.weight >= ?",
+        )
+        .bind(user_id)
+        .bind(GENRE_TAG_MIN_WEIGHT)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(count)
+    }
+}
+
+/// Drop weak and non-genre tags and keep the strongest few
+fn filter_genre_tags(tags: Vec<(String, i32)>) -> Vec<(String, i32)> {
+    let mut tags: Vec<_> = tags
+        .into_iter()
+        .filter(|(tag, weight)| {
+            *weight >= MIN_TAG_WEIGHT && !tag.is_empty() && tag.len() <= 100 && !NON_GENRE_TAGS.contains(&tag.as_str())
+        })
+        .collect();
+
+    tags.sort_by_key(|tag| std::cmp::Reverse(tag.1));
+
+    // Tags are case-folded on fetch, so "Rock" and "rock" may both appear; keep the stronger one
+    let mut seen = HashSet::new();
+    tags.retain(|(tag, _)| seen.insert(tag.clone()));
+    tags.truncate(MAX_TAGS_PER_ARTIST);
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn genre(genre: &str, weight: f64) -> GenreWeight {
+        GenreWeight {
+            genre: genre.to_string(),
+            weight,
+        }
+    }
+
+    #[test]
+    fn test_shared_genres() {
+        let mine = vec![genre("shoegaze", 0.5), genre("dream pop", 0.3), genre("trip-hop", 0.2)];
+        let theirs = vec![genre("pop", 0.6), genre("dream pop", 0.25), genre("shoegaze", 0.15)];
+
+        let shared: Vec<_> = shared_genres(&mine, &theirs)
+            .into_iter()
+            .map(|g| (g.genre, g.my_weight, g.their_weight))
+            .collect();
+        assert_eq!(
+            shared,
+            vec![
+                ("dream pop".to_string(), 0.3, 0.25),
+                ("shoegaze".to_string(), 0.5, 0.15),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_genre_tags() {
+        let tags = vec![
+            ("seen live".to_string(), 100),
+            ("shoegaze".to_string(), 95),
+            ("dream pop".to_string(), 60),
+            ("british".to_string(), 40),
+            ("noise".to_string(), 5),
+            ("shoegaze".to_string(), 30),
+        ];
+
+        assert_eq!(
+            filter_genre_tags(tags),
+            vec![("shoegaze".to_string(), 95), ("dream pop".to_string(), 60)]
+        );
+    }
+
+    #[test]
+    fn test_filter_genre_tags_keeps_strongest() {
+        let tags = (0..20).map(|i| (format!("tag{}", i), 20 + i)).collect();
+        let filtered = filter_genre_tags(tags);
+
+        assert_eq!(filtered.len(), MAX_TAGS_PER_ARTIST);
+        assert_eq!(filtered[0], ("tag19".to_string(), 39));
+    }
+}
@@ -3,7 +3,7 @@ use crate::{
     db::DbPool,
     errors::AppError,
     models::{CreateUser, User},
-    services::normalize_email,
+    services::{normalize_email, normalize_gender},
 };
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
@@ -65,6 +65,8 @@ impl AuthService {
             return Err(AppError::Validation("Email already registered".to_string()));
         }
 
+        let gender = create_user.gender.as_deref().map(normalize_gender).transpose()?;
+
         // Hash password
         let password_hash = self.hash_password(&create_user.password)?;
 
@@ -74,7 +76,7 @@ impl AuthService {
             password_hash,
             create_user.name,
             create_user.birth_date,
-            create_user.gender,
+            gender.map(str::to_string),
         );
 
         // Insert into database
@@ -1,37 +1,52 @@
-use crate::errors::AppError;
-use redis::{aio::ConnectionManager, AsyncCommands};
+use crate::{
+    errors::AppError,
+    services::{MemoryCache, RedisCache},
+};
+use async_trait::async_trait;
 use serde::{de::DeserializeOwned, Serialize};
+use std::future::Future;
+use std::sync::Arc;
 use std::time::Duration;
 
-/// Redis cache service for caching expensive operations
+/// String key/value store behind `CacheService`
+/// Semantics follow Redis: keys expire after their TTL, patterns are Redis globs, and `ttl`
+/// returns -2 for a missing key and -1 for a key without expiry.
+#[async_trait]
+pub trait Cache: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<String>, AppError>;
+    async fn set(&self, key: &str, value: String, ttl: Duration) -> Result<(), AppError>;
+    async fn delete(&self, key: &str) -> Result<(), AppError>;
+    async fn delete_pattern(&self, pattern: &str) -> Result<(), AppError>;
+    async fn exists(&self, key: &str) -> Result<bool, AppError>;
+    /// Increment a counter, starting its TTL on the first increment
+    async fn increment(&self, key: &str, ttl: Duration) -> Result<i64, AppError>;
+    async fn ttl(&self, key: &str) -> Result<i64, AppError>;
+}
+
+/// Cache service for caching expensive operations
+/// Stores JSON-serialized values in whichever `Cache` backend is configured
 #[derive(Clone)]
 pub struct CacheService {
-    client: ConnectionManager,
+    backend: Arc<dyn Cache>,
 }
 
 impl CacheService {
-    pub async fn new(redis_url: &str) -> Result<Self, AppError> {
-        let client = redis::Client::open(redis_url)
-            .map_err(|e| AppError::Internal(format!("Failed to connect to Redis: {}", e)))?;
-        
-        let connection_manager = ConnectionManager::new(client)
-            .await
-            .map_err(|e| AppError::Internal(format!("Failed to create Redis connection manager: {}", e)))?;
+    pub fn new(backend: Arc<dyn Cache>) -> Self {
+        Self { backend }
+    }
+
+    pub async fn redis(redis_url: &str) -> Result<Self, AppError> {
+        Ok(Self::new(Arc::new(RedisCache::new(redis_url).await?)))
+    }
 
-        Ok(Self {
-            client: connection_manager,
-        })
+    /// Process-local cache holding at most `max_entries` values
+    pub fn in_memory(max_entries: usize) -> Self {
+        Self::new(Arc::new(MemoryCache::new(max_entries)))
     }
 
     /// Get a cached value by key
     pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, AppError> {
-        let mut conn = self.client.clone();
-        let value: Option<String> = conn
-            .get(key)
-            .await
-            .map_err(|e| AppError::Internal(format!("Redis get error: {}", e)))?;
-
-        match value {
+        match self.backend.get(key).await? {
             Some(json) => {
                 let data = serde_json::from_str(&json)
                     .map_err(|e| AppError::Internal(format!("Failed to deserialize cache: {}", e)))?;
@@ -48,101 +63,97 @@ impl CacheService {
         value: &T,
         ttl: Duration,
     ) -> Result<(), AppError> {
-        let mut conn = self.client.clone();
         let json = serde_json::to_string(value)
             .map_err(|e| AppError::Internal(format!("Failed to serialize cache: {}", e)))?;
 
-        conn.set_ex::<_, _, ()>(key, json, ttl.as_secs())
-            .await
-            .map_err(|e| AppError::Internal(format!("Redis set error: {}", e)))?;
+        self.backend.set(key, json, ttl).await
+    }
 
-        Ok(())
+    /// Read-through: return the cached value for `key`, or compute it with `load` and cache it
+    /// Cache errors are logged and treated as a miss, so a cache outage only costs performance
+    pub async fn get_or_load<T, F, Fut>(&self, key: &str, ttl: Duration, load: F) -> Result<T, AppError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, AppError>>,
+    {
+        match self.get(key).await {
+            Ok(Some(value)) => return Ok(value),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Cache read failed for {}: {}", key, e),
+        }
+
+        let value = load().await?;
+        if let Err(e) = self.set(key, &value, ttl).await {
+            tracing::warn!("Cache write failed for {}: {}", key, e);
+        }
+
+        Ok(value)
     }
 
     /// Delete a cached value
     pub async fn delete(&self, key: &str) -> Result<(), AppError> {
-        let mut conn = self.client.clone();
-        conn.del::<_, ()>(key)
-            .await
-            .map_err(|e| AppError::Internal(format!("Redis delete error: {}", e)))?;
-
-        Ok(())
+        self.backend.delete(key).await
     }
 
     /// Delete multiple cached values by pattern
     pub async fn delete_pattern(&self, pattern: &str) -> Result<(), AppError> {
-        let mut conn = self.client.clone();
-        
-        // Get all keys matching the pattern
-        let keys: Vec<String> = conn
-            .keys(pattern)
-            .await
-            .map_err(|e| AppError::Internal(format!("Redis keys error: {}", e)))?;
-
-        if !keys.is_empty() {
-            conn.del::<_, ()>(keys)
-                .await
-                .map_err(|e| AppError::Internal(format!("Redis delete pattern error: {}", e)))?;
-        }
+        self.backend.delete_pattern(pattern).await
+    }
 
+    /// Drop everything cached about a user's listening data: their top artists/taste,
+    /// derived profiles, and every compatibility score involving them
+    pub async fn invalidate_user_taste(&self, user_id: &str) -> Result<(), AppError> {
+        self.delete_pattern(&format!("user:{}:*", user_id)).await?;
+        self.delete_pattern(&format!("compatibility:{}:*", user_id)).await?;
+        self.delete_pattern(&format!("compatibility:*:{}:*", user_id)).await?;
         Ok(())
     }
 
     /// Check if a key exists
     pub async fn exists(&self, key: &str) -> Result<bool, AppError> {
-        let mut conn = self.client.clone();
-        let exists: bool = conn
-            .exists(key)
-            .await
-            .map_err(|e| AppError::Internal(format!("Redis exists error: {}", e)))?;
-
-        Ok(exists)
+        self.backend.exists(key).await
     }
 
     /// Increment a counter with TTL
     pub async fn increment(&self, key: &str, ttl: Duration) -> Result<i64, AppError> {
-        let mut conn = self.client.clone();
-        
-        // Increment the counter
-        let value: i64 = conn
-            .incr(key, 1)
-            .await
-            .map_err(|e| AppError::Internal(format!("Redis incr error: {}", e)))?;
-
-        // Set TTL if this is the first increment
-        if value == 1 {
-            conn.expire::<_, ()>(key, ttl.as_secs() as i64)
-                .await
-                .map_err(|e| AppError::Internal(format!("Redis expire error: {}", e)))?;
-        }
-
-        Ok(value)
+        self.backend.increment(key, ttl).await
     }
 
     /// Get time to live for a key
     pub async fn ttl(&self, key: &str) -> Result<i64, AppError> {
-        let mut conn = self.client.clone();
-        let ttl: i64 = conn
-            .ttl(key)
-            .await
-            .map_err(|e| AppError::Internal(format!("Redis ttl error: {}", e)))?;
-
-        Ok(ttl)
+        self.backend.ttl(key).await
     }
 }
 
 // Cache key builders for consistency
 pub mod keys {
-    /// Cache key for user's top artists
-    pub fn user_top_artists(user_id: &str, limit: usize) -> String {
-        format!("user:{}:top_artists:{}", user_id, limit)
+    /// Cache key for user's top artists in one period
+    pub fn user_top_artists(user_id: &str, period: &str, limit: usize) -> String {
+        format!("user:{}:top_artists:{}:{}", user_id, period, limit)
+    }
+
+    /// Cache key for user's top tracks in one period
+    pub fn user_top_tracks(user_id: &str, period: &str, limit: usize) -> String {
+        format!("user:{}:top_tracks:{}:{}", user_id, period, limit)
+    }
+
+    /// Cache key for user's top artists across every period
+    pub fn user_taste(user_id: &str, limit: usize) -> String {
+        format!("user:{}:taste:{}", user_id, limit)
+    }
+
+    /// Cache key for user's top tracks across every period
+    pub fn user_track_taste(user_id: &str, limit: usize) -> String {
+        format!("user:{}:track_taste:{}", user_id, limit)
     }
 
-    /// Cache key for compatibility score between two users
-    pub fn compatibility(user1_id: &str, user2_id: &str) -> String {
+    /// Cache key for the raw taste similarity between two users, per algorithm version so
+    /// switching algorithms never serves similarities from the old one
+    pub fn compatibility(user1_id: &str, user2_id: &str, algorithm_version: &str) -> String {
         let mut ids = vec![user1_id, user2_id];
         ids.sort();
-        format!("compatibility:{}:{}", ids[0], ids[1])
+        format!("compatibility:{}:{}:{}:similarity", ids[0], ids[1], algorithm_version)
     }
 
     /// Cache key for Last.fm API data
@@ -165,3 +176,43 @@ pub mod keys {
         format!("rate_limit:{}:{}", identifier, endpoint)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_or_load_only_loads_on_miss() {
+        let cache = CacheService::in_memory(100);
+        let loads = std::sync::atomic::AtomicUsize::new(0);
+        let load = || async {
+            loads.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok::<_, AppError>(vec!["Slowdive".to_string()])
+        };
+
+        let first = cache.get_or_load("k", Duration::from_secs(60), load).await.unwrap();
+        let second = cache.get_or_load("k", Duration::from_secs(60), load).await.unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(loads.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_user_taste() {
+        let cache = CacheService::in_memory(100);
+        let ttl = Duration::from_secs(60);
+        cache.set(&keys::user_taste("a", 50), &1, ttl).await.unwrap();
+        cache.set(&keys::compatibility("a", "b", "v1"), &1.0, ttl).await.unwrap();
+        cache.set(&keys::compatibility("0", "a", "v1"), &1.0, ttl).await.unwrap();
+        cache.set(&keys::user_taste("b", 50), &1, ttl).await.unwrap();
+        cache.set(&keys::compatibility("b", "c", "v1"), &1.0, ttl).await.unwrap();
+
+        cache.invalidate_user_taste("a").await.unwrap();
+
+        assert!(!cache.exists(&keys::user_taste("a", 50)).await.unwrap());
+        assert!(!cache.exists(&keys::compatibility("a", "b", "v1")).await.unwrap());
+        assert!(!cache.exists(&keys::compatibility("0", "a", "v1")).await.unwrap());
+        assert!(cache.exists(&keys::user_taste("b", 50)).await.unwrap());
+        assert!(cache.exists(&keys::compatibility("b", "c", "v1")).await.unwrap());
+    }
+}
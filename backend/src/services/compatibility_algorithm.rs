@@ -0,0 +1,473 @@
+//! Compatibility scoring algorithms
+//! Each algorithm has a stable version name that is stored with the matches it scored, so
+//! algorithms can be compared against real outcomes later (see the `replay_compatibility` binary).
+
+use crate::{
+    models::Artist,
+    services::{
+        artist_normalization::ArtistIdentities,
+        artist_similarity_service::SimilarityGraph,
+        compatibility_service::cosine_similarity,
+    },
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Number of top artists per user that an algorithm looks at
+pub const ARTIST_VECTOR_SIZE: usize = 50;
+
+/// How much a related artist counts compared to sharing the artist itself, at a Last.fm
+/// match of 1.0. Two shoegaze bands are close, but not the same band.
+pub const RELATED_ARTIST_WEIGHT: f64 = 0.5;
+
+pub const DEFAULT_ALGORITHM: &str = SoftCosine::VERSION;
+
+/// Every algorithm that can be selected with `COMPATIBILITY_ALGORITHM`
+pub const ALGORITHM_VERSIONS: [&str; 3] = [SoftCosine::VERSION, VectorCosine::VERSION, ArtistOverlap::VERSION];
+
+/// How strongly each factor shapes an artist's weight
+/// Factors are raised to these powers: 1.0 uses a factor as-is, 0.0 ignores it and
+/// values above 1.0 exaggerate it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlgorithmWeights {
+    /// Earlier in a user's top artists = more important
+    pub position: f64,
+    /// More plays = more important
+    pub play_count: f64,
+    /// Fewer listeners = more important
+    pub popularity: f64,
+}
+
+impl Default for AlgorithmWeights {
+    fn default() -> Self {
+        Self {
+            position: 1.0,
+            play_count: 1.0,
+            popularity: 1.0,
+        }
+    }
+}
+
+impl AlgorithmWeights {
+    /// The same weights with popularity ignored, to measure what favoring niche artists adds
+    pub fn without_popularity(self) -> Self {
+        Self {
+            popularity: 0.0,
+            ..self
+        }
+    }
+}
+
+/// What a pair of artists adds to an artist similarity
+/// The keys are equal for an artist both users listen to and differ for related artists.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarityTerm {
+    pub user1_key: String,
+    pub user2_key: String,
+    pub contribution: f64,
+}
+
+/// Scores how similar two users' top artists for a period are
+pub trait CompatibilityAlgorithm: Send + Sync {
+    /// Stable name, stored in `matches.algorithm_version`
+    fn version(&self) -> &'static str;
+
+    fn weights(&self) -> AlgorithmWeights;
+
+    /// Whether `artist_similarity` looks at related artists; the graph is only loaded when it does
+    fn uses_similarity_graph(&self) -> bool {
+        false
+    }
+
+    /// Artist similarity from 0.0 (nothing in common) to 1.0 (identical taste); artists are in
+    /// rank order and `related` holds similarity edges between their artist keys
+    fn artist_similarity(&self, user1_artists: &[Artist], user2_artists: &[Artist], related: &SimilarityGraph) -> f64;
+
+    /// `artist_similarity` split into the artist pairs behind it; contributions sum to it
+    fn explain_similarity(
+        &self,
+        user1_artists: &[Artist],
+        user2_artists: &[Artist],
+        related: &SimilarityGraph,
+    ) -> Vec<SimilarityTerm>;
+}
+
+/// Look up an algorithm by version name
+pub fn by_version(version: &str, weights: AlgorithmWeights) -> Option<Arc<dyn CompatibilityAlgorithm>> {
+    match version {
+        SoftCosine::VERSION => Some(Arc::new(SoftCosine::new(weights))),
+        VectorCosine::VERSION => Some(Arc::new(VectorCosine::new(weights))),
+        ArtistOverlap::VERSION => Some(Arc::new(ArtistOverlap::new(weights))),
+        _ => None,
+    }
+}
+
+/// Soft cosine similarity: like `VectorCosine`, but related artists (from Last.fm's similar-artists
+/// graph) partly count as shared, so two fans of different shoegaze bands aren't strangers
+pub struct SoftCosine {
+    weights: AlgorithmWeights,
+}
+
+impl SoftCosine {
+    pub const VERSION: &'static str = "soft-cosine-v1";
+
+    pub fn new(weights: AlgorithmWeights) -> Self {
+        Self { weights }
+    }
+}
+
+impl CompatibilityAlgorithm for SoftCosine {
+    fn version(&self) -> &'static str {
+        Self::VERSION
+    }
+
+    fn weights(&self) -> AlgorithmWeights {
+        self.weights
+    }
+
+    fn uses_similarity_graph(&self) -> bool {
+        true
+    }
+
+    fn artist_similarity(&self, user1_artists: &[Artist], user2_artists: &[Artist], related: &SimilarityGraph) -> f64 {
+        let identities = ArtistIdentities::new(user1_artists.iter().chain(user2_artists));
+        let user1_vector = artist_vector(user1_artists, self.weights, &identities);
+        let user2_vector = artist_vector(user2_artists, self.weights, &identities);
+        soft_cosine_similarity(&user1_vector, &user2_vector, related)
+    }
+
+    fn explain_similarity(&self, user1_artists: &[Artist], user2_artists: &[Artist], related: &SimilarityGraph) -> Vec<SimilarityTerm> {
+        let identities = ArtistIdentities::new(user1_artists.iter().chain(user2_artists));
+        let user1_vector = artist_vector(user1_artists, self.weights, &identities);
+        let user2_vector = artist_vector(user2_artists, self.weights, &identities);
+        soft_cosine_terms(&user1_vector, &user2_vector, related)
+    }
+}
+
+/// Cosine similarity between weighted artist vectors (inspired by Duolicious)
+pub struct VectorCosine {
+    weights: AlgorithmWeights,
+}
+
+impl VectorCosine {
+    pub const VERSION: &'static str = "vector-v1";
+
+    pub fn new(weights: AlgorithmWeights) -> Self {
+        Self { weights }
+    }
+}
+
+impl CompatibilityAlgorithm for VectorCosine {
+    fn version(&self) -> &'static str {
+        Self::VERSION
+    }
+
+    fn weights(&self) -> AlgorithmWeights {
+        self.weights
+    }
+
+    fn artist_similarity(&self, user1_artists: &[Artist], user2_artists: &[Artist], _related: &SimilarityGraph) -> f64 {
+        let identities = ArtistIdentities::new(user1_artists.iter().chain(user2_artists));
+        cosine_similarity(
+            &artist_vector(user1_artists, self.weights, &identities),
+            &artist_vector(user2_artists, self.weights, &identities),
+        )
+    }
+
+    fn explain_similarity(&self, user1_artists: &[Artist], user2_artists: &[Artist], _related: &SimilarityGraph) -> Vec<SimilarityTerm> {
+        // Without related artists the soft cosine is the plain cosine
+        let identities = ArtistIdentities::new(user1_artists.iter().chain(user2_artists));
+        soft_cosine_terms(
+            &artist_vector(user1_artists, self.weights, &identities),
+            &artist_vector(user2_artists, self.weights, &identities),
+            &SimilarityGraph::default(),
+        )
+    }
+}
+
+/// The original score: how many artists overlap, and how close together they rank
+/// Ignores play counts.
+pub struct ArtistOverlap {
+    weights: AlgorithmWeights,
+}
+
+impl ArtistOverlap {
+    pub const VERSION: &'static str = "overlap-v0";
+
+    pub fn new(weights: AlgorithmWeights) -> Self {
+        Self { weights }
+    }
+}
+
+impl CompatibilityAlgorithm for ArtistOverlap {
+    fn version(&self) -> &'static str {
+        Self::VERSION
+    }
+
+    fn weights(&self) -> AlgorithmWeights {
+        self.weights
+    }
+
+    fn artist_similarity(&self, user1_artists: &[Artist], user2_artists: &[Artist], related: &SimilarityGraph) -> f64 {
+        self.explain_similarity(user1_artists, user2_artists, related)
+            .iter()
+            .map(|term| term.contribution)
+            .sum()
+    }
+
+    fn explain_similarity(&self, user1_artists: &[Artist], user2_artists: &[Artist], _related: &SimilarityGraph) -> Vec<SimilarityTerm> {
+        let user1_artists = &user1_artists[..user1_artists.len().min(ARTIST_VECTOR_SIZE)];
+        let user2_artists = &user2_artists[..user2_artists.len().min(ARTIST_VECTOR_SIZE)];
+
+        let identities = ArtistIdentities::new(user1_artists.iter().chain(user2_artists));
+        let user1_keys: Vec<String> = user1_artists.iter().map(|a| identities.key(a)).collect();
+        let user2_keys: Vec<String> = user2_artists.iter().map(|a| identities.key(a)).collect();
+
+        let user2_set: HashSet<_> = user2_keys.iter().collect();
+        let common_artists: HashSet<_> = user1_keys
+            .iter()
+            .filter(|key| user2_set.contains(key))
+            .collect();
+
+        // Originally a 0-100 score: 30 points for ten shared artists, 70 for how well they line up
+        let mut terms = Vec::new();
+        for key in &common_artists {
+            let (Some(pos1), Some(pos2)) = (
+                user1_keys.iter().position(|k| k == *key),
+                user2_keys.iter().position(|k| k == *key),
+            ) else {
+                continue;
+            };
+
+            let avg_listeners = (user1_artists[pos1].listeners as f64 + user2_artists[pos2].listeners as f64) / 2.0;
+            let popularity_weight = if avg_listeners > 0.0 {
+                1.0 / avg_listeners.log10().max(1.0)
+            } else {
+                1.0
+            };
+
+            let position_diff = (pos1 as f64 - pos2 as f64).abs();
+            let position_weight = 1.0 / (1.0 + position_diff / 10.0);
+
+            let weight = popularity_weight.powf(self.weights.popularity) * position_weight.powf(self.weights.position);
+            terms.push(SimilarityTerm {
+                user1_key: (*key).clone(),
+                user2_key: (*key).clone(),
+                contribution: 0.3 / 10.0 + weight / common_artists.len() as f64 * 0.7,
+            });
+        }
+
+        // The score is capped at 1.0, so scale the terms down to the cap
+        scale_to_at_most_one(&mut terms);
+        terms
+    }
+}
+
+/// Weighted artist vector, keyed by artist identity (which also lines up with the similarity graph)
+/// An artist listed twice under different spellings keeps its higher-ranked weight.
+pub fn artist_vector(artists: &[Artist], weights: AlgorithmWeights, identities: &ArtistIdentities) -> HashMap<String, f64> {
+    let mut vector = HashMap::new();
+    for (i, artist) in artists.iter().take(ARTIST_VECTOR_SIZE).enumerate() {
+        vector
+            .entry(identities.key(artist))
+            .or_insert_with(|| artist_weight(i, artist, weights));
+    }
+    vector
+}
+
+/// Weight of the artist at rank `i` (0-based) in a user's top artists
+fn artist_weight(i: usize, artist: &Artist, weights: AlgorithmWeights) -> f64 {
+    let position_weight = 1.0 - (i as f64 / ARTIST_VECTOR_SIZE as f64);
+
+    let play_weight = if artist.play_count > 0 {
+        (artist.play_count as f64).ln().max(1.0)
+    } else {
+        1.0
+    };
+
+    position_weight.powf(weights.position)
+        * play_weight.powf(weights.play_count)
+        * popularity_weight(artist.listeners).powf(weights.popularity)
+}
+
+/// Entry of S in the soft cosine: 1 for the same artist, the discounted graph similarity otherwise
+fn artist_pair_similarity(key_a: &str, key_b: &str, related: &SimilarityGraph) -> f64 {
+    if key_a == key_b {
+        1.0
+    } else if related.is_empty() {
+        0.0
+    } else {
+        RELATED_ARTIST_WEIGHT * related.similarity(key_a, key_b)
+    }
+}
+
+/// a·S·b
+fn soft_inner_product(a: &HashMap<String, f64>, b: &HashMap<String, f64>, related: &SimilarityGraph) -> f64 {
+    let mut sum = 0.0;
+    for (key_a, weight_a) in a {
+        for (key_b, weight_b) in b {
+            sum += weight_a * weight_b * artist_pair_similarity(key_a, key_b, related);
+        }
+    }
+    sum
+}
+
+/// Cosine similarity where different artists still overlap by their (discounted) graph
+/// similarity: a·S·b / sqrt(a·S·a · b·S·b), with S = 1 on the diagonal
+fn soft_cosine_similarity(v1: &HashMap<String, f64>, v2: &HashMap<String, f64>, related: &SimilarityGraph) -> f64 {
+    let norm = (soft_inner_product(v1, v1, related) * soft_inner_product(v2, v2, related)).sqrt();
+    if norm == 0.0 {
+        return 0.0;
+    }
+
+    // S isn't guaranteed to be positive semi-definite, so keep the result a valid cosine
+    (soft_inner_product(v1, v2, related) / norm).clamp(0.0, 1.0)
+}
+
+/// The nonzero terms of `soft_cosine_similarity`, one per pair of artists
+fn soft_cosine_terms(v1: &HashMap<String, f64>, v2: &HashMap<String, f64>, related: &SimilarityGraph) -> Vec<SimilarityTerm> {
+    let norm = (soft_inner_product(v1, v1, related) * soft_inner_product(v2, v2, related)).sqrt();
+    if norm == 0.0 {
+        return Vec::new();
+    }
+
+    let mut terms = Vec::new();
+    for (key_a, weight_a) in v1 {
+        for (key_b, weight_b) in v2 {
+            let similarity = artist_pair_similarity(key_a, key_b, related);
+            if similarity > 0.0 {
+                terms.push(SimilarityTerm {
+                    user1_key: key_a.clone(),
+                    user2_key: key_b.clone(),
+                    contribution: weight_a * weight_b * similarity / norm,
+                });
+            }
+        }
+    }
+
+    // Mirrors the clamp in `soft_cosine_similarity`
+    scale_to_at_most_one(&mut terms);
+    terms
+}
+
+/// Scale terms down proportionally so they sum to at most 1.0
+fn scale_to_at_most_one(terms: &mut [SimilarityTerm]) {
+    let total: f64 = terms.iter().map(|term| term.contribution).sum();
+    if total > 1.0 {
+        for term in terms {
+            term.contribution /= total;
+        }
+    }
+}
+
+/// Less popular artists have more weight; artists without a listener count yet get full weight
+pub fn popularity_weight(listeners: i32) -> f64 {
+    if listeners > 0 {
+        1.0 / (listeners as f64).log10().max(1.0)
+    } else {
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artist(name: &str, play_count: i32, listeners: i32) -> Artist {
+        Artist {
+            name: name.to_string(),
+            mbid: None,
+            play_count,
+            listeners,
+        }
+    }
+
+    #[test]
+    fn test_by_version() {
+        for version in ALGORITHM_VERSIONS {
+            let algorithm = by_version(version, AlgorithmWeights::default()).unwrap();
+            assert_eq!(algorithm.version(), version);
+        }
+        assert!(by_version("vector-v0", AlgorithmWeights::default()).is_none());
+    }
+
+    #[test]
+    fn test_soft_cosine_matches_cosine_without_related_artists() {
+        let user1 = vec![artist("Slowdive", 300, 900_000), artist("Radiohead", 200, 7_000_000)];
+        let user2 = vec![artist("Radiohead", 500, 7_000_000), artist("Grouper", 100, 200_000)];
+        let weights = AlgorithmWeights::default();
+
+        let soft = SoftCosine::new(weights).artist_similarity(&user1, &user2, &SimilarityGraph::default());
+        let plain = VectorCosine::new(weights).artist_similarity(&user1, &user2, &SimilarityGraph::default());
+        assert!((soft - plain).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_related_artists_count_partly() {
+        let user1 = vec![artist("Slowdive", 300, 900_000)];
+        let user2 = vec![artist("Ride", 300, 900_000)];
+        let algorithm = SoftCosine::new(AlgorithmWeights::default());
+
+        let unrelated = algorithm.artist_similarity(&user1, &user2, &SimilarityGraph::default());
+
+        let mut related = SimilarityGraph::default();
+        related.insert("name:slowdive", "name:ride", 0.92);
+        let score = algorithm.artist_similarity(&user1, &user2, &related);
+
+        let same = algorithm.artist_similarity(&user1, &user1, &related);
+        assert!(unrelated < score && score < same);
+
+        // One artist each, equal weights: the similarity is exactly the discounted match
+        let similarity = soft_cosine_similarity(
+            &artist_vector(&user1, AlgorithmWeights::default(), &ArtistIdentities::default()),
+            &artist_vector(&user2, AlgorithmWeights::default(), &ArtistIdentities::default()),
+            &related,
+        );
+        assert!((similarity - RELATED_ARTIST_WEIGHT * 0.92).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_zero_weight_ignores_a_factor() {
+        let plays_only = AlgorithmWeights {
+            position: 0.0,
+            play_count: 1.0,
+            popularity: 0.0,
+        };
+        let vector = artist_vector(
+            &[artist("Grouper", 100, 10), artist("Radiohead", 100, 7_000_000)],
+            plays_only,
+            &ArtistIdentities::default(),
+        );
+        assert!((vector["name:grouper"] - vector["name:radiohead"]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_spelling_differences_still_match() {
+        let user1 = vec![artist("The Beatles", 300, 4_000_000), artist("Sigur Rós", 100, 1_000_000)];
+        let mut user2 = vec![artist("beatles", 300, 4_000_000), artist("Sigur Ros", 100, 1_000_000)];
+        user2[0].mbid = Some("b10bbbfc-cf9e-42e0-be17-e2c3e1d2600d".to_string());
+
+        for version in ALGORITHM_VERSIONS {
+            let algorithm = by_version(version, AlgorithmWeights::default()).unwrap();
+            let similarity = algorithm.artist_similarity(&user1, &user2, &SimilarityGraph::default());
+            let same = algorithm.artist_similarity(&user1, &user1, &SimilarityGraph::default());
+            assert!((similarity - same).abs() < 1e-9, "{} scored {} for the same artists", version, similarity);
+        }
+    }
+
+    #[test]
+    fn test_algorithms_agree_on_extremes() {
+        let user1 = vec![artist("Slowdive", 300, 900_000), artist("Grouper", 150, 200_000)];
+        let user2 = vec![artist("Metallica", 500, 3_000_000)];
+
+        for version in ALGORITHM_VERSIONS {
+            let algorithm = by_version(version, AlgorithmWeights::default()).unwrap();
+            let same = algorithm.artist_similarity(&user1, &user1, &SimilarityGraph::default());
+            let disjoint = algorithm.artist_similarity(&user1, &user2, &SimilarityGraph::default());
+            assert!(same > disjoint, "{} scored identical taste {} <= disjoint {}", version, same, disjoint);
+            assert!(same <= 1.0 + 1e-9, "{} scored identical taste {}", version, same);
+            // Calibration maps 0.0 to a score of 0, so nothing in common must mean exactly that
+            assert!(disjoint.abs() < 1e-9, "{} scored disjoint taste {}", version, disjoint);
+        }
+    }
+}
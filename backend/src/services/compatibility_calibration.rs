@@ -0,0 +1,195 @@
+//! Maps raw taste similarity (0-1) to the 0-100 compatibility score users see
+//! Raw cosine similarities bunch up near the bottom of the range, so a calibration turns them into
+//! percentiles against a sample of real pairs: a score of 80 means the pair is more similar than
+//! 80% of pairs that share any taste at all. Pairs with nothing in common always score 0.
+
+use crate::{db::DbPool, errors::AppError, services::CompatibilityService};
+use uuid::Uuid;
+
+/// Percentile knots stored per calibration (0th to 100th)
+pub const CALIBRATION_KNOTS: usize = 101;
+
+/// Stored quantiles of raw similarity for one algorithm version
+#[derive(Debug, Clone, Default)]
+pub struct Calibration {
+    /// `quantiles[i]` is the raw similarity at percentile `100 * i / (len - 1)`;
+    /// empty means uncalibrated, where the score is just the similarity as a percentage
+    quantiles: Vec<f64>,
+}
+
+impl Calibration {
+    /// Build a calibration from raw similarities of sampled pairs
+    /// Pairs with no similarity at all are left out: they always score 0, and counting them would
+    /// hand a big head start to every pair with the faintest overlap.
+    pub fn from_sample(mut similarities: Vec<f64>) -> Self {
+        similarities.retain(|s| *s > 0.0 && s.is_finite());
+        if similarities.is_empty() {
+            return Self::default();
+        }
+        similarities.sort_by(f64::total_cmp);
+
+        let last = similarities.len() - 1;
+        let quantiles = (0..CALIBRATION_KNOTS)
+            .map(|i| {
+                // Linear interpolation between the closest ranks
+                let rank = i as f64 / (CALIBRATION_KNOTS - 1) as f64 * last as f64;
+                let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+                let fraction = rank - lower as f64;
+                similarities[lower] + (similarities[upper] - similarities[lower]) * fraction
+            })
+            .collect();
+
+        Self { quantiles }
+    }
+
+    pub fn is_calibrated(&self) -> bool {
+        !self.quantiles.is_empty()
+    }
+
+    /// Compatibility score (0-100) for a raw similarity (0-1)
+    pub fn score(&self, similarity: f64) -> f64 {
+        if similarity.is_nan() || similarity <= 0.0 {
+            return 0.0;
+        }
+        if self.quantiles.is_empty() {
+            return (similarity * 100.0).clamp(0.0, 100.0);
+        }
+
+        let steps = (self.quantiles.len() - 1) as f64;
+        // The lowest knot is still a real pair, so scores ramp up to it from zero
+        let mut previous = (0.0, 0.0);
+        for (i, knot) in self.quantiles.iter().enumerate() {
+            let percentile = 100.0 * i as f64 / steps;
+            if similarity < *knot {
+                let (previous_knot, previous_percentile) = previous;
+                let fraction = (similarity - previous_knot) / (knot - previous_knot);
+                return previous_percentile + (percentile - previous_percentile) * fraction;
+            }
+            previous = (*knot, percentile);
+        }
+
+        100.0
+    }
+
+    /// The most recent calibration for an algorithm version, if one was ever stored
+    pub async fn load_latest(pool: &DbPool, algorithm_version: &str) -> Result<Option<Self>, AppError> {
+        let quantiles: Option<String> = sqlx::query_scalar(
+            "SELECT quantiles FROM compatibility_calibrations
+             WHERE algorithm_version = ?
+             ORDER BY created_at DESC
+             LIMIT 1",
+        )
+        .bind(algorithm_version)
+        .fetch_optional(pool)
+        .await?;
+
+        quantiles
+            .map(|q| {
+                serde_json::from_str(&q)
+                    .map(|quantiles| Self { quantiles })
+                    .map_err(|e| AppError::Internal(format!("Invalid stored calibration: {}", e)))
+            })
+            .transpose()
+    }
+
+    /// Store this calibration as the latest one for an algorithm version
+    pub async fn save(&self, pool: &DbPool, algorithm_version: &str, sample_size: usize) -> Result<(), AppError> {
+        let quantiles = serde_json::to_string(&self.quantiles)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize calibration: {}", e)))?;
+
+        sqlx::query(
+            "INSERT INTO compatibility_calibrations (id, algorithm_version, quantiles, sample_size)
+             VALUES (?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(algorithm_version)
+        .bind(quantiles)
+        .bind(sample_size as i64)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Random users with listening data from any source (Last.fm, ListenBrainz or an imported history),
+/// the population scores are calibrated against
+pub async fn load_sample_users(pool: &DbPool, limit: u32) -> Result<Vec<String>, AppError> {
+    let user_ids = sqlx::query_scalar(
+        "SELECT u.id FROM users u
+         WHERE EXISTS (SELECT 1 FROM scrobbles_cache sc WHERE sc.user_id = u.id)
+         ORDER BY RAND()
+         LIMIT ?",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(user_ids)
+}
+
+/// Calibrate `service`'s algorithm from the raw similarity of every pair of `user_ids`
+/// Returns the calibration and the number of pairs it was built from
+pub async fn calibrate(
+    pool: &DbPool,
+    service: &CompatibilityService,
+    user_ids: &[String],
+) -> Result<(Calibration, usize), AppError> {
+    let mut similarities = Vec::new();
+    for (i, user1_id) in user_ids.iter().enumerate() {
+        for user2_id in &user_ids[i + 1..] {
+            similarities.push(service.calculate_similarity(pool, user1_id, user2_id).await?);
+        }
+    }
+
+    let pairs = similarities.len();
+    Ok((Calibration::from_sample(similarities), pairs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disjoint_taste_scores_zero() {
+        let calibrated = Calibration::from_sample((1..=100).map(|i| i as f64 / 200.0).collect());
+        assert_eq!(calibrated.score(0.0), 0.0);
+        assert_eq!(Calibration::default().score(0.0), 0.0);
+        // Barely any overlap is still near the bottom
+        assert!(calibrated.score(0.001) < 1.0);
+    }
+
+    #[test]
+    fn test_uncalibrated_score_is_a_percentage() {
+        let calibration = Calibration::default();
+        assert!(!calibration.is_calibrated());
+        assert!((calibration.score(0.42) - 42.0).abs() < 1e-9);
+        assert_eq!(calibration.score(1.5), 100.0);
+    }
+
+    #[test]
+    fn test_scores_are_percentiles_of_the_sample() {
+        // Real similarities are skewed towards 0; calibration spreads them out
+        let sample: Vec<f64> = (1..=1000).map(|i| (i as f64 / 1000.0).powi(3) * 0.6).collect();
+        let calibration = Calibration::from_sample(sample.clone());
+        assert!(calibration.is_calibrated());
+
+        let median = sample[499];
+        assert!((calibration.score(median) - 50.0).abs() < 0.5);
+        assert!((calibration.score(sample[899]) - 90.0).abs() < 0.5);
+        assert_eq!(calibration.score(0.6), 100.0);
+        assert_eq!(calibration.score(0.9), 100.0);
+
+        let scores: Vec<f64> = sample.iter().map(|s| calibration.score(*s)).collect();
+        assert!(scores.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn test_zero_similarities_are_left_out_of_the_sample() {
+        let mut sample = vec![0.0; 900];
+        sample.extend((1..=100).map(|i| i as f64 / 100.0));
+        let calibration = Calibration::from_sample(sample);
+        assert!((calibration.score(0.5) - 50.0).abs() < 1.5);
+        assert!(!Calibration::from_sample(vec![0.0; 10]).is_calibrated());
+    }
+}
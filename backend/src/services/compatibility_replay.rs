@@ -0,0 +1,145 @@
+//! Offline evaluation of compatibility algorithms against historical likes
+//! Every pair where at least one user liked the other is replayed through each algorithm; a good
+//! algorithm scores the pairs that became mutual likes above the ones that stayed one-sided.
+//! Scores use the users' current listening data, not their taste at the time of the like.
+
+use crate::{
+    db::DbPool,
+    errors::AppError,
+    services::{CompatibilityAlgorithm, CompatibilityService, LastFmService},
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Two users where at least one liked the other
+#[derive(Debug, Clone)]
+pub struct LikedPair {
+    pub user1_id: String,
+    pub user2_id: String,
+    pub mutual: bool,
+}
+
+/// How well one algorithm separates mutual likes from one-sided ones
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayReport {
+    pub algorithm_version: String,
+    pub pairs: usize,
+    pub mutual_pairs: usize,
+    pub mean_mutual_score: Option<f64>,
+    pub mean_one_sided_score: Option<f64>,
+    /// Chance that a random mutual pair outscores a random one-sided pair (0.5 = no better
+    /// than guessing); `None` unless there are pairs of both kinds
+    pub auc: Option<f64>,
+}
+
+/// Every pair of users with a like between them, most recent first
+pub async fn load_liked_pairs(pool: &DbPool, limit: Option<u32>) -> Result<Vec<LikedPair>, AppError> {
+    let rows: Vec<(String, String, i64)> = sqlx::query_as(
+        "SELECT LEAST(from_user_id, to_user_id) AS user1_id,
+                GREATEST(from_user_id, to_user_id) AS user2_id,
+                COUNT(*) AS likes
+         FROM likes
+         GROUP BY user1_id, user2_id
+         ORDER BY MAX(created_at) DESC
+         LIMIT ?",
+    )
+    .bind(limit.unwrap_or(u32::MAX))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(user1_id, user2_id, likes)| LikedPair {
+            user1_id,
+            user2_id,
+            mutual: likes == 2,
+        })
+        .collect())
+}
+
+/// Score every pair with `algorithm` and summarize how well the scores predict mutual likes
+pub async fn replay(
+    pool: &DbPool,
+    lastfm_service: Arc<LastFmService>,
+    algorithm: Arc<dyn CompatibilityAlgorithm>,
+    pairs: &[LikedPair],
+) -> Result<ReplayReport, AppError> {
+    let algorithm_version = algorithm.version().to_string();
+    let service = CompatibilityService::new(lastfm_service).with_algorithm(algorithm);
+
+    let mut samples = Vec::with_capacity(pairs.len());
+    for pair in pairs {
+        let score = service
+            .calculate_compatibility(pool, &pair.user1_id, &pair.user2_id)
+            .await?;
+        samples.push((score, pair.mutual));
+    }
+
+    Ok(summarize(algorithm_version, &samples))
+}
+
+fn summarize(algorithm_version: String, samples: &[(f64, bool)]) -> ReplayReport {
+    let mutual: Vec<f64> = samples.iter().filter(|(_, m)| *m).map(|(s, _)| *s).collect();
+    let one_sided: Vec<f64> = samples.iter().filter(|(_, m)| !*m).map(|(s, _)| *s).collect();
+
+    ReplayReport {
+        algorithm_version,
+        pairs: samples.len(),
+        mutual_pairs: mutual.len(),
+        mean_mutual_score: mean(&mutual),
+        mean_one_sided_score: mean(&one_sided),
+        auc: auc(&mutual, &one_sided),
+    }
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// Area under the ROC curve, as the share of (positive, negative) pairs ranked correctly;
+/// ties count as half
+fn auc(positives: &[f64], negatives: &[f64]) -> Option<f64> {
+    if positives.is_empty() || negatives.is_empty() {
+        return None;
+    }
+
+    let mut correct = 0.0;
+    for p in positives {
+        for n in negatives {
+            if p > n {
+                correct += 1.0;
+            } else if p == n {
+                correct += 0.5;
+            }
+        }
+    }
+    Some(correct / (positives.len() * negatives.len()) as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auc() {
+        assert_eq!(auc(&[90.0, 80.0], &[10.0, 20.0]), Some(1.0));
+        assert_eq!(auc(&[10.0], &[90.0]), Some(0.0));
+        assert_eq!(auc(&[50.0, 90.0], &[50.0, 10.0]), Some(0.875));
+        assert_eq!(auc(&[50.0], &[]), None);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let report = summarize(
+            "vector-v1".to_string(),
+            &[(80.0, true), (60.0, true), (40.0, false), (70.0, false)],
+        );
+        assert_eq!((report.pairs, report.mutual_pairs), (4, 2));
+        assert_eq!(report.mean_mutual_score, Some(70.0));
+        assert_eq!(report.mean_one_sided_score, Some(55.0));
+        assert_eq!(report.auc, Some(0.75));
+    }
+}
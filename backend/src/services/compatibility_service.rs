@@ -1,204 +1,575 @@
 use crate::{
     db::DbPool,
     errors::AppError,
-    models::Artist,
-    services::lastfm_service::LastFmService,
+    models::{Artist, Period, Track},
+    services::{
+        cache_service::keys,
+        artist_normalization::{normalize_artist_name, ArtistIdentities},
+        artist_similarity_service::{ArtistSimilarityService, SimilarityGraph},
+        compatibility_algorithm::{self, popularity_weight, AlgorithmWeights, CompatibilityAlgorithm, ARTIST_VECTOR_SIZE},
+        compatibility_calibration::Calibration,
+        lastfm_service::LastFmService,
+        CacheService,
+    },
 };
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
 use std::sync::Arc;
+use std::time::Duration;
+
+/// How much each listening period contributes to the overall score
+/// Short-term taste captures what someone is into right now, long-term taste who they are musically
+const PERIOD_WEIGHTS: [(Period, f64); 3] = [
+    (Period::OneMonth, 0.25),
+    (Period::SixMonth, 0.45),
+    (Period::Overall, 0.30),
+];
+
+/// Share of a period's similarity that comes from overlapping top tracks, when both users have track data
+const TRACK_WEIGHT: f64 = 0.3;
+
+/// Similarities only change when either user syncs, which invalidates them
+const COMPATIBILITY_CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// An artist in both users' top artists, and how much it adds to their similarity
+#[derive(Debug, Clone, Serialize)]
+pub struct SharedArtist {
+    pub name: String,
+    /// 1-based positions in each user's top artists
+    pub my_rank: usize,
+    pub their_rank: usize,
+    pub my_play_count: i32,
+    pub their_play_count: i32,
+    /// What sharing this artist adds to the similarity; with `related_artists`, contributions sum
+    /// to `cosine_similarity`
+    pub contribution: f64,
+    /// Popularity weighting applied to the artist: 1.0 when niche, lower the more listeners it has
+    pub niche_weight: f64,
+}
+
+/// Two different artists, one from each user, that count partly as shared because they're similar
+#[derive(Debug, Clone, Serialize)]
+pub struct RelatedArtistPair {
+    pub my_artist: String,
+    pub their_artist: String,
+    /// 1-based positions in each user's top artists
+    pub my_rank: usize,
+    pub their_rank: usize,
+    pub contribution: f64,
+}
+
+/// Why a period's artist score came out the way it did
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtistBreakdown {
+    /// Calibrated like the overall score, so the two are comparable
+    pub artist_score: f64,
+    /// The configured algorithm's artist similarity, before calibration
+    pub cosine_similarity: f64,
+    /// Score points gained by weighting niche artists up, compared to ignoring popularity
+    pub niche_bonus: f64,
+    /// Ordered by contribution, largest first
+    pub shared_artists: Vec<SharedArtist>,
+    /// Credit for related artists, when the algorithm gives any; ordered by contribution
+    pub related_artists: Vec<RelatedArtistPair>,
+}
+
+/// A track both users have in their top tracks
+#[derive(Debug, Clone, Serialize)]
+pub struct CommonTrack {
+    pub name: String,
+    pub artist: String,
+}
 
 pub struct CompatibilityService {
     lastfm_service: Arc<LastFmService>,
+    cache: Option<Arc<CacheService>>,
+    algorithm: Arc<dyn CompatibilityAlgorithm>,
+    calibration: Calibration,
 }
 
 impl CompatibilityService {
     pub fn new(lastfm_service: Arc<LastFmService>) -> Self {
-        Self { lastfm_service }
+        Self {
+            lastfm_service,
+            cache: None,
+            algorithm: compatibility_algorithm::by_version(compatibility_algorithm::DEFAULT_ALGORITHM, AlgorithmWeights::default())
+                .expect("the default algorithm exists"),
+            calibration: Calibration::default(),
+        }
+    }
+
+    /// Score artists with `algorithm` instead of the default one
+    pub fn with_algorithm(mut self, algorithm: Arc<dyn CompatibilityAlgorithm>) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Map similarities to scores with `calibration`, which must be for the same algorithm
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = calibration;
+        self
+    }
+
+    /// Version of the algorithm behind this service's scores, stored with each match
+    pub fn algorithm_version(&self) -> &'static str {
+        self.algorithm.version()
     }
 
+    /// Whether related (not just shared) artists affect scores
+    pub fn uses_similarity_graph(&self) -> bool {
+        self.algorithm.uses_similarity_graph()
+    }
+
+    /// Cache pairwise similarities (read-through; `LastFmService` invalidates them on sync)
+    pub fn with_cache(mut self, cache: Arc<CacheService>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Compatibility score from 0 (nothing in common) to 100
     pub async fn calculate_compatibility(
         &self,
         pool: &DbPool,
         user1_id: &str,
         user2_id: &str,
     ) -> Result<f64, AppError> {
-        // Get top 50 artists for both users
-        let user1_artists = self.lastfm_service.get_user_top_artists(pool, user1_id, 50).await?;
-        let user2_artists = self.lastfm_service.get_user_top_artists(pool, user2_id, 50).await?;
+        let similarity = self.calculate_similarity(pool, user1_id, user2_id).await?;
+        Ok(self.calibration.score(similarity))
+    }
 
-        if user1_artists.is_empty() || user2_artists.is_empty() {
-            return Ok(0.0);
+    /// Raw taste similarity from 0.0 to 1.0, before calibration
+    pub async fn calculate_similarity(
+        &self,
+        pool: &DbPool,
+        user1_id: &str,
+        user2_id: &str,
+    ) -> Result<f64, AppError> {
+        match &self.cache {
+            Some(cache) => {
+                cache
+                    .get_or_load(&keys::compatibility(user1_id, user2_id, self.algorithm.version()), COMPATIBILITY_CACHE_TTL, || {
+                        self.compute_similarity(pool, user1_id, user2_id)
+                    })
+                    .await
+            }
+            None => self.compute_similarity(pool, user1_id, user2_id).await,
+        }
+    }
+
+    async fn compute_similarity(
+        &self,
+        pool: &DbPool,
+        user1_id: &str,
+        user2_id: &str,
+    ) -> Result<f64, AppError> {
+        // Get top 50 artists and tracks per period for both users
+        let user1_taste = self.lastfm_service.get_user_taste(pool, user1_id, 50).await?;
+        let user2_taste = self.lastfm_service.get_user_taste(pool, user2_id, 50).await?;
+        let user1_tracks = self.lastfm_service.get_user_track_taste(pool, user1_id, 50).await?;
+        let user2_tracks = self.lastfm_service.get_user_track_taste(pool, user2_id, 50).await?;
+
+        let mut all_artists = Vec::new();
+        for (period, _) in PERIOD_WEIGHTS {
+            for taste in [&user1_taste, &user2_taste] {
+                all_artists.extend(taste.get(&period).into_iter().flatten());
+            }
         }
+        let related = self.load_similarity_graph(pool, &all_artists).await?;
 
-        // Use enhanced vector-based algorithm (inspired by Duolicious)
-        let score = self.compute_vector_score(&user1_artists, &user2_artists);
-        Ok(score)
+        Ok(self.blend_period_similarities(&user1_taste, &user2_taste, &user1_tracks, &user2_tracks, &related))
     }
 
-    /// Vector-based compatibility calculation (inspired by Duolicious)
-    /// Uses cosine similarity between music preference vectors
-    fn compute_vector_score(&self, user1_artists: &[Artist], user2_artists: &[Artist]) -> f64 {
-        const VECTOR_SIZE: usize = 50;
-        
-        // Build all artists universe for vector space
-        let mut all_artists = HashSet::new();
-        for artist in user1_artists.iter().chain(user2_artists.iter()) {
-            all_artists.insert(artist.name.clone());
+    /// Similarity edges between the given artists, if the algorithm uses them
+    async fn load_similarity_graph(&self, pool: &DbPool, artists: &[&Artist]) -> Result<SimilarityGraph, AppError> {
+        if !self.algorithm.uses_similarity_graph() {
+            return Ok(SimilarityGraph::default());
         }
-        
-        // Create artist to index mapping
-        let artist_to_idx: HashMap<String, usize> = all_artists
+
+        let artist_keys: Vec<String> = artists
             .iter()
-            .enumerate()
-            .map(|(i, name)| (name.clone(), i))
+            .map(|a| a.key())
+            .collect::<HashSet<_>>()
+            .into_iter()
             .collect();
-        
-        let vector_size = all_artists.len();
-        let mut user1_vector = vec![0.0; vector_size];
-        let mut user2_vector = vec![0.0; vector_size];
-        
-        // Build weighted vectors for user 1
-        for (i, artist) in user1_artists.iter().enumerate().take(VECTOR_SIZE) {
-            if let Some(&idx) = artist_to_idx.get(&artist.name) {
-                // Position weight: earlier in list = more important
-                let position_weight = 1.0 - (i as f64 / VECTOR_SIZE as f64);
-                
-                // Play count weight: normalize by total if available
-                let play_weight = if artist.play_count > 0 {
-                    (artist.play_count as f64).ln().max(1.0)
-                } else {
-                    1.0
-                };
-                
-                // Popularity weight: less popular artists have more weight
-                let popularity_weight = if artist.listeners > 0 {
-                    1.0 / (artist.listeners as f64).log10().max(1.0)
-                } else {
-                    1.0
-                };
-                
-                user1_vector[idx] = position_weight * play_weight * popularity_weight;
-            }
+        ArtistSimilarityService::load_graph(pool, &artist_keys).await
+    }
+
+    /// Compatibility for a single listening period, e.g. to compare what two people are into right now
+    pub async fn calculate_period_compatibility(
+        &self,
+        pool: &DbPool,
+        user1_id: &str,
+        user2_id: &str,
+        period: Period,
+    ) -> Result<f64, AppError> {
+        let user1_artists = self.lastfm_service.get_user_top_artists(pool, user1_id, period, 50).await?;
+        let user2_artists = self.lastfm_service.get_user_top_artists(pool, user2_id, period, 50).await?;
+
+        if user1_artists.is_empty() || user2_artists.is_empty() {
+            return Ok(0.0);
         }
-        
-        // Build weighted vectors for user 2
-        for (i, artist) in user2_artists.iter().enumerate().take(VECTOR_SIZE) {
-            if let Some(&idx) = artist_to_idx.get(&artist.name) {
-                let position_weight = 1.0 - (i as f64 / VECTOR_SIZE as f64);
-                let play_weight = if artist.play_count > 0 {
-                    (artist.play_count as f64).ln().max(1.0)
-                } else {
-                    1.0
-                };
-                let popularity_weight = if artist.listeners > 0 {
-                    1.0 / (artist.listeners as f64).log10().max(1.0)
-                } else {
-                    1.0
-                };
-                
-                user2_vector[idx] = position_weight * play_weight * popularity_weight;
+
+        let user1_tracks = self.lastfm_service.get_user_top_tracks(pool, user1_id, period, 50).await?;
+        let user2_tracks = self.lastfm_service.get_user_top_tracks(pool, user2_id, period, 50).await?;
+        let all_artists: Vec<&Artist> = user1_artists.iter().chain(&user2_artists).collect();
+        let related = self.load_similarity_graph(pool, &all_artists).await?;
+
+        let similarity = self.compute_period_similarity(&user1_artists, &user2_artists, &user1_tracks, &user2_tracks, &related);
+        Ok(self.calibration.score(similarity))
+    }
+
+    /// Weighted blend of per-period similarities
+    /// Periods where either user has no data are skipped and the remaining weights renormalized
+    fn blend_period_similarities(
+        &self,
+        user1_taste: &HashMap<Period, Vec<Artist>>,
+        user2_taste: &HashMap<Period, Vec<Artist>>,
+        user1_tracks: &HashMap<Period, Vec<Track>>,
+        user2_tracks: &HashMap<Period, Vec<Track>>,
+        related: &SimilarityGraph,
+    ) -> f64 {
+        let no_tracks = Vec::new();
+
+        let mut weighted_sum = 0.0;
+        let mut total_weight = 0.0;
+
+        for (period, weight) in PERIOD_WEIGHTS {
+            let (Some(user1_artists), Some(user2_artists)) = (user1_taste.get(&period), user2_taste.get(&period)) else {
+                continue;
+            };
+
+            if user1_artists.is_empty() || user2_artists.is_empty() {
+                continue;
             }
+
+            let similarity = self.compute_period_similarity(
+                user1_artists,
+                user2_artists,
+                user1_tracks.get(&period).unwrap_or(&no_tracks),
+                user2_tracks.get(&period).unwrap_or(&no_tracks),
+                related,
+            );
+
+            weighted_sum += weight * similarity;
+            total_weight += weight;
         }
-        
-        // Normalize vectors (L2 normalization - like Duolicious does)
-        let norm1 = user1_vector.iter().map(|x| x * x).sum::<f64>().sqrt();
-        let norm2 = user2_vector.iter().map(|x| x * x).sum::<f64>().sqrt();
-        
-        if norm1 == 0.0 || norm2 == 0.0 {
-            return 0.0;
-        }
-        
-        for v in &mut user1_vector {
-            *v /= norm1;
-        }
-        for v in &mut user2_vector {
-            *v /= norm2;
-        }
-        
-        // Calculate cosine similarity (dot product of normalized vectors)
-        let dot_product: f64 = user1_vector
-            .iter()
-            .zip(user2_vector.iter())
-            .map(|(a, b)| a * b)
-            .sum();
-        
-        // Convert cosine similarity [-1, 1] to percentage [0, 99]
-        // Cosine similarity of 1.0 (identical vectors) = 99%
-        // Cosine similarity of 0.0 (orthogonal) = 49.5%
-        // Cosine similarity of -1.0 (opposite) = 0%
-        let similarity = (dot_product + 1.0) / 2.0; // Maps [-1,1] to [0,1]
-        (similarity * 99.0).max(0.0).min(99.0)
-    }
-
-    /// Original compatibility calculation (kept for comparison)
-    #[allow(dead_code)]
-    fn compute_score_original(&self, user1_artists: &[Artist], user2_artists: &[Artist]) -> f64 {
-        // Create HashSets for quick lookup
-        let user1_set: HashSet<_> = user1_artists.iter().map(|a| a.name.clone()).collect();
-        let user2_set: HashSet<_> = user2_artists.iter().map(|a| a.name.clone()).collect();
-
-        // Find common artists
-        let common_artists: Vec<_> = user1_set.intersection(&user2_set).collect();
-
-        if common_artists.is_empty() {
+
+        if total_weight == 0.0 {
             return 0.0;
         }
 
-        // Create lookup maps for artist data
-        let user1_map: HashMap<_, _> = user1_artists
-            .iter()
-            .map(|a| (a.name.clone(), a))
-            .collect();
+        weighted_sum / total_weight
+    }
 
-        let user2_map: HashMap<_, _> = user2_artists
-            .iter()
-            .map(|a| (a.name.clone(), a))
-            .collect();
+    /// Similarity for one period: artist similarity, mixed with track similarity when both users have tracks
+    fn compute_period_similarity(
+        &self,
+        user1_artists: &[Artist],
+        user2_artists: &[Artist],
+        user1_tracks: &[Track],
+        user2_tracks: &[Track],
+        related: &SimilarityGraph,
+    ) -> f64 {
+        let artist_similarity = self.algorithm.artist_similarity(user1_artists, user2_artists, related);
 
-        // Calculate weighted score
-        let mut weighted_score = 0.0;
-        
-        for artist_name in &common_artists {
-            if let (Some(artist1), Some(artist2)) = (user1_map.get(*artist_name), user2_map.get(*artist_name)) {
-                let avg_listeners = ((artist1.listeners + artist2.listeners) as f64) / 2.0;
-                let popularity_weight = if avg_listeners > 0.0 {
-                    1.0 / avg_listeners.log10().max(1.0)
-                } else {
-                    1.0
-                };
-
-                let pos1 = user1_artists.iter().position(|a| &a.name == *artist_name).unwrap_or(50) as f64;
-                let pos2 = user2_artists.iter().position(|a| &a.name == *artist_name).unwrap_or(50) as f64;
-                let position_diff = (pos1 - pos2).abs();
-                let position_weight = 1.0 / (1.0 + position_diff / 10.0);
-
-                weighted_score += popularity_weight * position_weight;
-            }
+        if user1_tracks.is_empty() || user2_tracks.is_empty() {
+            return artist_similarity;
         }
 
-        let common_count_score = (common_artists.len() as f64 / 10.0) * 30.0;
-        let weighted_normalized = (weighted_score / common_artists.len() as f64) * 70.0;
-        let total_score = (common_count_score + weighted_normalized).min(100.0);
+        let track_similarity = self.compute_track_similarity(user1_tracks, user2_tracks);
+        artist_similarity * (1.0 - TRACK_WEIGHT) + track_similarity * TRACK_WEIGHT
+    }
+
+    /// Cosine similarity between top-track vectors
+    /// Anyone can share a popular artist; sharing the same songs usually means sharing the deep cuts
+    fn compute_track_similarity(&self, user1_tracks: &[Track], user2_tracks: &[Track]) -> f64 {
+        cosine_similarity(&track_vector(user1_tracks), &track_vector(user2_tracks))
+    }
+
+    /// Break a period's artist similarity down into the shared and related artists behind it, so
+    /// the UI can explain why two people matched
+    pub async fn explain_compatibility(
+        &self,
+        pool: &DbPool,
+        user1_id: &str,
+        user2_id: &str,
+        period: Period,
+    ) -> Result<ArtistBreakdown, AppError> {
+        let user1_artists = self.lastfm_service.get_user_top_artists(pool, user1_id, period, 50).await?;
+        let user2_artists = self.lastfm_service.get_user_top_artists(pool, user2_id, period, 50).await?;
 
-        total_score
+        let all_artists: Vec<&Artist> = user1_artists.iter().chain(&user2_artists).collect();
+        let related = self.load_similarity_graph(pool, &all_artists).await?;
+
+        Ok(explain_artist_similarity(&user1_artists, &user2_artists, self.algorithm.as_ref(), &related, &self.calibration))
     }
 
+    /// Artists both users listen to, by the first user's spelling and in their order
     pub fn get_common_artists(
         &self,
         user1_artists: &[Artist],
         user2_artists: &[Artist],
         limit: usize,
     ) -> Vec<String> {
-        let user1_set: HashSet<_> = user1_artists.iter().map(|a| a.name.clone()).collect();
-        let user2_set: HashSet<_> = user2_artists.iter().map(|a| a.name.clone()).collect();
+        let identities = ArtistIdentities::new(user1_artists.iter().chain(user2_artists));
+        let user2_set: HashSet<_> = user2_artists.iter().map(|a| identities.key(a)).collect();
 
-        let common: Vec<_> = user1_set
-            .intersection(&user2_set)
+        let mut seen = HashSet::new();
+        user1_artists
+            .iter()
+            .filter(|a| {
+                let key = identities.key(a);
+                user2_set.contains(&key) && seen.insert(key)
+            })
             .take(limit)
-            .cloned()
-            .collect();
+            .map(|a| a.name.clone())
+            .collect()
+    }
+
+    /// Tracks both users have in their top tracks, in the first user's order
+    pub fn get_common_tracks(
+        &self,
+        user1_tracks: &[Track],
+        user2_tracks: &[Track],
+        limit: usize,
+    ) -> Vec<CommonTrack> {
+        let user2_set: HashSet<_> = user2_tracks.iter().map(track_key).collect();
+
+        user1_tracks
+            .iter()
+            .filter(|t| user2_set.contains(&track_key(t)))
+            .take(limit)
+            .map(|t| CommonTrack {
+                name: t.name.clone(),
+                artist: t.artist_name.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Split `algorithm`'s artist similarity into what each shared artist and each pair of related
+/// artists adds, using the algorithm's own terms so they sum to the similarity it scores with
+fn explain_artist_similarity(
+    user1_artists: &[Artist],
+    user2_artists: &[Artist],
+    algorithm: &dyn CompatibilityAlgorithm,
+    related: &SimilarityGraph,
+    calibration: &Calibration,
+) -> ArtistBreakdown {
+    let weights = algorithm.weights();
+    let identities = ArtistIdentities::new(user1_artists.iter().chain(user2_artists));
+    // Vectors are keyed by identity, so only the first occurrence of an artist counts
+    let ranks = |artists: &[Artist]| {
+        let mut ranks = HashMap::new();
+        for (rank, artist) in artists.iter().enumerate().take(ARTIST_VECTOR_SIZE) {
+            ranks.entry(identities.key(artist)).or_insert(rank);
+        }
+        ranks
+    };
+    let user1_ranks = ranks(user1_artists);
+    let user2_ranks = ranks(user2_artists);
+
+    let terms = algorithm.explain_similarity(user1_artists, user2_artists, related);
+    let similarity = terms.iter().map(|term| term.contribution).sum();
+
+    let mut shared_artists = Vec::new();
+    let mut related_artists = Vec::new();
+    for term in terms {
+        let (Some(&my_rank), Some(&their_rank)) = (user1_ranks.get(&term.user1_key), user2_ranks.get(&term.user2_key)) else {
+            continue;
+        };
+        let (mine, theirs) = (&user1_artists[my_rank], &user2_artists[their_rank]);
+
+        if term.user1_key == term.user2_key {
+            shared_artists.push(SharedArtist {
+                name: mine.name.clone(),
+                my_rank: my_rank + 1,
+                their_rank: their_rank + 1,
+                my_play_count: mine.play_count,
+                their_play_count: theirs.play_count,
+                contribution: term.contribution,
+                niche_weight: popularity_weight(mine.listeners.max(theirs.listeners)).powf(weights.popularity),
+            });
+        } else {
+            related_artists.push(RelatedArtistPair {
+                my_artist: mine.name.clone(),
+                their_artist: theirs.name.clone(),
+                my_rank: my_rank + 1,
+                their_rank: their_rank + 1,
+                contribution: term.contribution,
+            });
+        }
+    }
+    shared_artists.sort_by(|a, b| b.contribution.total_cmp(&a.contribution));
+    related_artists.sort_by(|a, b| b.contribution.total_cmp(&a.contribution));
+
+    let artist_score = calibration.score(similarity);
+    let unweighted = compatibility_algorithm::by_version(algorithm.version(), weights.without_popularity())
+        .expect("the algorithm's own version exists");
+    let unweighted_score = calibration.score(unweighted.artist_similarity(user1_artists, user2_artists, related));
+
+    ArtistBreakdown {
+        artist_score,
+        cosine_similarity: similarity,
+        niche_bonus: artist_score - unweighted_score,
+        shared_artists,
+        related_artists,
+    }
+}
+
+fn track_key(track: &Track) -> (String, String) {
+    (normalize_artist_name(&track.artist_name), track.name.trim().to_lowercase())
+}
 
-        common
+/// Weighted track vector: earlier and more-played tracks count more
+fn track_vector(tracks: &[Track]) -> HashMap<(String, String), f64> {
+    const VECTOR_SIZE: usize = 50;
+
+    let mut vector = HashMap::new();
+    for (i, track) in tracks.iter().enumerate().take(VECTOR_SIZE) {
+        let position_weight = 1.0 - (i as f64 / VECTOR_SIZE as f64);
+        let play_weight = if track.play_count > 0 {
+            (track.play_count as f64).ln().max(1.0)
+        } else {
+            1.0
+        };
+        vector.insert(track_key(track), position_weight * play_weight);
     }
+    vector
+}
+
+/// Cosine similarity between two sparse vectors
+pub(crate) fn cosine_similarity<K: Eq + Hash>(v1: &HashMap<K, f64>, v2: &HashMap<K, f64>) -> f64 {
+    let norm1 = v1.values().map(|x| x * x).sum::<f64>().sqrt();
+    let norm2 = v2.values().map(|x| x * x).sum::<f64>().sqrt();
+
+    if norm1 == 0.0 || norm2 == 0.0 {
+        return 0.0;
+    }
+
+    let dot_product: f64 = v1
+        .iter()
+        .filter_map(|(k, a)| v2.get(k).map(|b| a * b))
+        .sum();
+
+    dot_product / (norm1 * norm2)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::compatibility_algorithm::{artist_vector, SoftCosine, VectorCosine};
+
+    fn track(name: &str, artist: &str, play_count: i32) -> Track {
+        Track {
+            name: name.to_string(),
+            artist_name: artist.to_string(),
+            artist_mbid: None,
+            play_count,
+        }
+    }
+
+    fn artist(name: &str, play_count: i32, listeners: i32) -> Artist {
+        Artist {
+            name: name.to_string(),
+            mbid: None,
+            play_count,
+            listeners,
+        }
+    }
+
+    #[test]
+    fn test_contributions_sum_to_the_artist_score() {
+        let user1 = vec![
+            artist("Slowdive", 300, 900_000),
+            artist("Radiohead", 200, 7_000_000),
+            artist("Grouper", 150, 200_000),
+        ];
+        let user2 = vec![
+            artist("Radiohead", 500, 7_000_000),
+            artist("Taylor Swift", 400, 5_000_000),
+            artist("Slowdive", 100, 900_000),
+        ];
+
+        let algorithm = VectorCosine::new(AlgorithmWeights::default());
+        let breakdown = explain_artist_similarity(&user1, &user2, &algorithm, &SimilarityGraph::default(), &Calibration::default());
+        let names: Vec<_> = breakdown.shared_artists.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"Slowdive") && names.contains(&"Radiohead"));
+
+        let radiohead = breakdown.shared_artists.iter().find(|s| s.name == "Radiohead").unwrap();
+        assert_eq!((radiohead.my_rank, radiohead.their_rank), (2, 1));
+        assert_eq!((radiohead.my_play_count, radiohead.their_play_count), (200, 500));
+
+        let total: f64 = breakdown.shared_artists.iter().map(|s| s.contribution).sum();
+        assert!((total - breakdown.cosine_similarity).abs() < 1e-9);
+        let identities = ArtistIdentities::new(user1.iter().chain(&user2));
+        let similarity = cosine_similarity(
+            &artist_vector(&user1, AlgorithmWeights::default(), &identities),
+            &artist_vector(&user2, AlgorithmWeights::default(), &identities),
+        );
+        assert!((breakdown.cosine_similarity - similarity).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_contributions_sum_to_the_soft_cosine_similarity() {
+        let user1 = vec![artist("Slowdive", 300, 900_000), artist("Radiohead", 200, 7_000_000)];
+        let user2 = vec![artist("Radiohead", 500, 7_000_000), artist("Ride", 100, 600_000)];
+        let mut related = SimilarityGraph::default();
+        related.insert("name:slowdive", "name:ride", 0.92);
+
+        let algorithm = SoftCosine::new(AlgorithmWeights::default());
+        let breakdown = explain_artist_similarity(&user1, &user2, &algorithm, &related, &Calibration::default());
+        assert_eq!(breakdown.shared_artists.len(), 1);
+        let pair = &breakdown.related_artists[0];
+        assert_eq!((pair.my_artist.as_str(), pair.their_artist.as_str()), ("Slowdive", "Ride"));
+        assert_eq!((pair.my_rank, pair.their_rank), (1, 2));
+
+        let total: f64 = breakdown.shared_artists.iter().map(|s| s.contribution).sum::<f64>()
+            + breakdown.related_artists.iter().map(|r| r.contribution).sum::<f64>();
+        let similarity = algorithm.artist_similarity(&user1, &user2, &related);
+        assert!((total - similarity).abs() < 1e-9);
+        assert!((breakdown.cosine_similarity - similarity).abs() < 1e-9);
+        // Related credit is what sets it apart from the plain cosine
+        let plain = VectorCosine::new(AlgorithmWeights::default()).artist_similarity(&user1, &user2, &related);
+        assert!(similarity > plain);
+    }
+
+    #[test]
+    fn test_niche_overlap_earns_a_bonus() {
+        // Sharing the niche artist rather than the mainstream one should count for more
+        let user1 = vec![artist("Grouper", 100, 200_000), artist("Taylor Swift", 100, 5_000_000)];
+        let user2 = vec![artist("Grouper", 100, 200_000), artist("Radiohead", 100, 7_000_000)];
+
+        let algorithm = SoftCosine::new(AlgorithmWeights::default());
+        let no_related = SimilarityGraph::default();
+        let breakdown = explain_artist_similarity(&user1, &user2, &algorithm, &no_related, &Calibration::default());
+        assert!(breakdown.niche_bonus > 0.0);
+
+        let no_overlap = explain_artist_similarity(&user1, &[artist("Metallica", 100, 3_000_000)], &algorithm, &no_related, &Calibration::default());
+        assert!(no_overlap.shared_artists.is_empty());
+        assert_eq!(no_overlap.cosine_similarity, 0.0);
+    }
+
+    #[test]
+    fn test_identical_tracks_are_fully_similar() {
+        let tracks = vec![track("Alison", "Slowdive", 40), track("Roads", "Portishead", 20)];
+        let similarity = cosine_similarity(&track_vector(&tracks), &track_vector(&tracks));
+        assert!((similarity - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_same_artist_different_tracks_are_not_similar() {
+        let user1 = vec![track("Creep", "Radiohead", 50)];
+        let user2 = vec![track("Let Down", "Radiohead", 50)];
+        assert_eq!(cosine_similarity(&track_vector(&user1), &track_vector(&user2)), 0.0);
+    }
+
+    #[test]
+    fn test_track_matching_ignores_case() {
+        let user1 = vec![track("Alison", "Slowdive", 10)];
+        let user2 = vec![track("alison", "SLOWDIVE", 80)];
+        assert!(cosine_similarity(&track_vector(&user1), &track_vector(&user2)) > 0.99);
+    }
+}
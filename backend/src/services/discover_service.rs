@@ -0,0 +1,470 @@
+use crate::{
+    db::DbPool,
+    errors::AppError,
+    models::Period,
+    services::compatibility_service::CompatibilityService,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Months, NaiveDate};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+
+/// Candidates scored per feed rebuild, picked by number of shared artists
+const CANDIDATE_POOL_SIZE: i64 = 500;
+/// Prospects below this compatibility score are left out of the feed
+pub const MIN_COMPATIBILITY_SCORE: f64 = 10.0;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Great-circle distance between two points in kilometres (Haversine formula)
+pub fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let delta_lat = (lat2 - lat1).to_radians();
+    let delta_lon = (lon2 - lon1).to_radians();
+
+    let a = (delta_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (delta_lon / 2.0).sin().powi(2);
+    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+
+    EARTH_RADIUS_KM * c
+}
+
+/// SQL expression for the Haversine distance in km from a bound point (`?` lat, `?` lat, `?` lon)
+/// to `{table}.latitude/longitude`; keep in sync with `haversine_km`
+pub fn haversine_sql(table: &str) -> String {
+    format!(
+        "{r} * 2 * ASIN(SQRT(POW(SIN(RADIANS({t}.latitude - ?) / 2), 2) \
+         + COS(RADIANS(?)) * COS(RADIANS({t}.latitude)) * POW(SIN(RADIANS({t}.longitude - ?) / 2), 2)))",
+        r = EARTH_RADIUS_KM,
+        t = table
+    )
+}
+
+/// Latitude/longitude box containing every point within a distance of a centre point
+/// Cheap to check against the `(latitude, longitude)` index before computing exact distances.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    /// `None` when the box covers a pole and so every longitude. When `min > max` the range
+    /// crosses the antimeridian and means `longitude >= min OR longitude <= max`.
+    pub longitude: Option<(f64, f64)>,
+}
+
+impl BoundingBox {
+    pub fn around(lat: f64, lon: f64, distance_km: f64) -> Self {
+        let angular = distance_km / EARTH_RADIUS_KM;
+        let min_lat = lat - angular.to_degrees();
+        let max_lat = lat + angular.to_degrees();
+
+        if min_lat <= -90.0 || max_lat >= 90.0 {
+            return Self {
+                min_lat: min_lat.max(-90.0),
+                max_lat: max_lat.min(90.0),
+                longitude: None,
+            };
+        }
+
+        let sin_delta = angular.sin() / lat.to_radians().cos();
+        if sin_delta >= 1.0 {
+            return Self { min_lat, max_lat, longitude: None };
+        }
+        let delta_lon = sin_delta.asin().to_degrees();
+
+        let mut min_lon = lon - delta_lon;
+        let mut max_lon = lon + delta_lon;
+        if min_lon < -180.0 {
+            min_lon += 360.0;
+        }
+        if max_lon > 180.0 {
+            max_lon -= 360.0;
+        }
+
+        Self {
+            min_lat,
+            max_lat,
+            longitude: Some((min_lon, max_lon)),
+        }
+    }
+}
+
+/// Birth date range for an age filter as `(born_after, born_on_or_before)`
+/// Someone is at least `min_age` if born on or before `today - min_age years`, and at most
+/// `max_age` if born after `today - (max_age + 1) years`.
+pub fn birth_date_bounds(
+    today: NaiveDate,
+    min_age: Option<u32>,
+    max_age: Option<u32>,
+) -> (Option<NaiveDate>, Option<NaiveDate>) {
+    let years_ago = |years: u32| today.checked_sub_months(Months::new(years.saturating_mul(12)));
+
+    (
+        max_age.and_then(|age| years_ago(age.saturating_add(1))),
+        min_age.and_then(years_ago),
+    )
+}
+
+/// Position in a feed ordered by score (highest first), then prospect id
+/// Handed to clients as an opaque string so the encoding can change without breaking them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedCursor {
+    pub score: f64,
+    pub prospect_id: String,
+}
+
+impl FeedCursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}:{}", self.score, self.prospect_id))
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, AppError> {
+        let invalid = || AppError::Validation("Invalid cursor".to_string());
+
+        let decoded = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+        let (score, prospect_id) = decoded.split_once(':').ok_or_else(invalid)?;
+        let score: f64 = score.parse().map_err(|_| invalid())?;
+
+        if !score.is_finite() || prospect_id.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            score,
+            prospect_id: prospect_id.to_string(),
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct Candidate {
+    id: String,
+    common_artists_count: i64,
+}
+
+/// Precomputed discover feeds stored in `discover_cache`
+/// Each user's feed is rebuilt after they sync; since compatibility is symmetric, the same
+/// scores also refresh the user's entry in every candidate's feed.
+pub struct DiscoverService {
+    compatibility_service: Arc<CompatibilityService>,
+    /// Users whose first feed is being built in the background
+    first_builds: Mutex<HashSet<String>>,
+}
+
+impl DiscoverService {
+    pub fn new(compatibility_service: Arc<CompatibilityService>) -> Self {
+        Self {
+            compatibility_service,
+            first_builds: Mutex::default(),
+        }
+    }
+
+    /// Build the feed of a user who has never had one in the background, so the request that
+    /// noticed doesn't wait for hundreds of candidates to be scored
+    /// Returns None when that user's first build is already running.
+    pub fn spawn_first_build(self: &Arc<Self>, pool: &DbPool, user_id: &str) -> Option<JoinHandle<()>> {
+        if !self.first_builds.lock().unwrap().insert(user_id.to_string()) {
+            return None;
+        }
+
+        let service = self.clone();
+        let pool = pool.clone();
+        let user_id = user_id.to_string();
+        Some(tokio::spawn(async move {
+            if let Err(e) = service.rebuild_feed(&pool, &user_id).await {
+                tracing::warn!("First discover feed build failed for user {}: {}", user_id, e);
+            }
+            service.first_builds.lock().unwrap().remove(&user_id);
+        }))
+    }
+
+    /// Recompute a user's feed and their entry in other users' feeds
+    /// Returns the number of prospects in the user's feed
+    pub async fn rebuild_feed(&self, pool: &DbPool, user_id: &str) -> Result<usize, AppError> {
+        let short_term = Period::default().as_str();
+        let long_term = Period::Overall.as_str();
+
+        // Anyone sharing a recent or all-time top artist, excluding blocks in either direction
+        let mut candidates = sqlx::query_as::<_, Candidate>(
+            "SELECT u.id, COUNT(DISTINCT theirs.artist_key) AS common_artists_count
+             FROM scrobbles_cache mine
+             INNER JOIN scrobbles_cache theirs
+                ON theirs.artist_key = mine.artist_key AND theirs.period = mine.period AND theirs.track_name IS NULL
+             INNER JOIN users u ON u.id = theirs.user_id
+             WHERE mine.user_id = ? AND mine.track_name IS NULL AND mine.period IN (?, ?)
+             AND theirs.user_id != ?
+             AND NOT EXISTS (
+                SELECT 1 FROM blocks b
+                WHERE (b.blocker_id = ? AND b.blocked_id = u.id) OR (b.blocker_id = u.id AND b.blocked_id = ?)
+             )
+             GROUP BY u.id
+             ORDER BY common_artists_count DESC
+             LIMIT ?",
+        )
+        .bind(user_id)
+        .bind(short_term)
+        .bind(long_term)
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .bind(CANDIDATE_POOL_SIZE)
+        .fetch_all(pool)
+        .await?;
+
+        // Related-but-different taste can score well too, so fill up with people who listen to
+        // artists similar to this user's
+        if self.compatibility_service.uses_similarity_graph() && candidates.len() < CANDIDATE_POOL_SIZE as usize {
+            let related = sqlx::query_as::<_, Candidate>(
+                "SELECT u.id, CAST(0 AS SIGNED) AS common_artists_count
+                 FROM scrobbles_cache mine
+                 INNER JOIN artist_similarities s ON s.artist_key = mine.artist_key
+                 INNER JOIN scrobbles_cache theirs
+                    ON theirs.artist_key = s.similar_key AND theirs.period = mine.period AND theirs.track_name IS NULL
+                 INNER JOIN users u ON u.id = theirs.user_id
+                 WHERE mine.user_id = ? AND mine.track_name IS NULL AND mine.period IN (?, ?)
+                 AND theirs.user_id != ?
+                 AND NOT EXISTS (
+                    SELECT 1 FROM blocks b
+                    WHERE (b.blocker_id = ? AND b.blocked_id = u.id) OR (b.blocker_id = u.id AND b.blocked_id = ?)
+                 )
+                 GROUP BY u.id
+                 ORDER BY COUNT(DISTINCT theirs.artist_key) DESC
+                 LIMIT ?",
+            )
+            .bind(user_id)
+            .bind(short_term)
+            .bind(long_term)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(CANDIDATE_POOL_SIZE)
+            .fetch_all(pool)
+            .await?;
+
+            let known: HashSet<String> = candidates.iter().map(|c| c.id.clone()).collect();
+            candidates.extend(related.into_iter().filter(|c| !known.contains(&c.id)));
+            candidates.truncate(CANDIDATE_POOL_SIZE as usize);
+        }
+
+        // People this user already liked, and people who already liked this user
+        let liked: HashSet<String> = sqlx::query_scalar("SELECT to_user_id FROM likes WHERE from_user_id = ?")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .collect();
+        let liked_by: HashSet<String> = sqlx::query_scalar("SELECT from_user_id FROM likes WHERE to_user_id = ?")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .collect();
+
+        let mut scored = Vec::with_capacity(candidates.len());
+        for candidate in candidates {
+            let score = self
+                .compatibility_service
+                .calculate_compatibility(pool, user_id, &candidate.id)
+                .await?;
+            if score >= MIN_COMPATIBILITY_SCORE {
+                scored.push((candidate, score));
+            }
+        }
+
+        let mut transaction = pool.begin().await?;
+
+        sqlx::query("DELETE FROM discover_cache WHERE user_id = ? OR prospect_id = ?")
+            .bind(user_id)
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        let mut feed_size = 0;
+        for (candidate, score) in &scored {
+            if !liked.contains(&candidate.id) {
+                Self::insert_entry(&mut transaction, user_id, &candidate.id, *score, candidate.common_artists_count).await?;
+                feed_size += 1;
+            }
+            if !liked_by.contains(&candidate.id) {
+                Self::insert_entry(&mut transaction, &candidate.id, user_id, *score, candidate.common_artists_count).await?;
+            }
+        }
+
+        sqlx::query("UPDATE users SET discover_feed_built_at = NOW() WHERE id = ?")
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(feed_size)
+    }
+
+    async fn insert_entry(
+        transaction: &mut sqlx::Transaction<'_, sqlx::MySql>,
+        user_id: &str,
+        prospect_id: &str,
+        score: f64,
+        common_artists_count: i64,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            "INSERT INTO discover_cache (user_id, prospect_id, compatibility_score, common_artists_count)
+             VALUES (?, ?, ?, ?)
+             ON DUPLICATE KEY UPDATE compatibility_score = VALUES(compatibility_score),
+                common_artists_count = VALUES(common_artists_count), cached_at = NOW()",
+        )
+        .bind(user_id)
+        .bind(prospect_id)
+        .bind(score)
+        .bind(common_artists_count)
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Put a prospect back into a user's feed, e.g. after undoing a like
+    pub async fn restore_prospect(&self, pool: &DbPool, user_id: &str, prospect_id: &str) -> Result<(), AppError> {
+        let score = self
+            .compatibility_service
+            .calculate_compatibility(pool, user_id, prospect_id)
+            .await?;
+        if score < MIN_COMPATIBILITY_SCORE {
+            return Ok(());
+        }
+
+        let common_artists_count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(DISTINCT theirs.artist_key)
+             FROM scrobbles_cache mine
+             INNER JOIN scrobbles_cache theirs
+                ON theirs.artist_key = mine.artist_key AND theirs.period = mine.period AND theirs.track_name IS NULL
+             WHERE mine.user_id = ? AND theirs.user_id = ? AND mine.track_name IS NULL AND mine.period IN (?, ?)",
+        )
+        .bind(user_id)
+        .bind(prospect_id)
+        .bind(Period::default().as_str())
+        .bind(Period::Overall.as_str())
+        .fetch_one(pool)
+        .await?;
+
+        let mut transaction = pool.begin().await?;
+        Self::insert_entry(&mut transaction, user_id, prospect_id, score, common_artists_count).await?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Whether the user's own feed has ever been built; a built feed may still be empty
+    pub async fn has_built_feed(pool: &DbPool, user_id: &str) -> Result<bool, AppError> {
+        let built: i64 = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM users WHERE id = ? AND discover_feed_built_at IS NOT NULL)",
+        )
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(built != 0)
+    }
+
+    /// Drop a prospect from one user's feed, e.g. after they liked them
+    pub async fn remove_prospect(pool: &DbPool, user_id: &str, prospect_id: &str) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM discover_cache WHERE user_id = ? AND prospect_id = ?")
+            .bind(user_id)
+            .bind(prospect_id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Drop two users from each other's feeds, e.g. after a block
+    pub async fn remove_pair(pool: &DbPool, user1_id: &str, user2_id: &str) -> Result<(), AppError> {
+        sqlx::query(
+            "DELETE FROM discover_cache
+             WHERE (user_id = ? AND prospect_id = ?) OR (user_id = ? AND prospect_id = ?)",
+        )
+        .bind(user1_id)
+        .bind(user2_id)
+        .bind(user2_id)
+        .bind(user1_id)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_haversine_km() {
+        // London to Paris
+        let distance = haversine_km(51.5074, -0.1278, 48.8566, 2.3522);
+        assert!((distance - 343.5).abs() < 1.0, "{}", distance);
+    }
+
+    #[test]
+    fn test_bounding_box_contains_points_at_the_distance() {
+        let bbox = BoundingBox::around(51.5074, -0.1278, 50.0);
+        let (min_lon, max_lon) = bbox.longitude.unwrap();
+
+        // The circle touches the north/south edges due north/south of the centre, and the
+        // east/west edges slightly poleward of it
+        let touch_lat = (51.5074f64.to_radians().sin() / (50.0 / EARTH_RADIUS_KM).cos())
+            .asin()
+            .to_degrees();
+        for (lat, lon) in [
+            (bbox.max_lat, -0.1278),
+            (bbox.min_lat, -0.1278),
+            (touch_lat, max_lon),
+            (touch_lat, min_lon),
+        ] {
+            let distance = haversine_km(51.5074, -0.1278, lat, lon);
+            assert!((distance - 50.0).abs() < 0.01, "{}", distance);
+        }
+    }
+
+    #[test]
+    fn test_bounding_box_wraps_antimeridian_and_poles() {
+        let fiji = BoundingBox::around(-17.7, 179.9, 100.0);
+        let (min_lon, max_lon) = fiji.longitude.unwrap();
+        assert!(min_lon > max_lon);
+
+        let svalbard = BoundingBox::around(89.5, 15.0, 100.0);
+        assert_eq!(svalbard.longitude, None);
+        assert_eq!(svalbard.max_lat, 90.0);
+    }
+
+    #[test]
+    fn test_birth_date_bounds_match_user_age() {
+        let today = date(2024, 6, 15);
+        let (born_after, born_on_or_before) = birth_date_bounds(today, Some(25), Some(30));
+
+        // Turned 25 today: included; turns 31 tomorrow: included; turned 31 today: excluded
+        assert_eq!(born_on_or_before, Some(date(1999, 6, 15)));
+        assert_eq!(born_after, Some(date(1993, 6, 15)));
+        assert_eq!(birth_date_bounds(today, None, None), (None, None));
+    }
+
+    #[test]
+    fn test_cursor_round_trips() {
+        let cursor = FeedCursor {
+            score: 72.35,
+            prospect_id: "5f0c2a7e-1b8e-4c57-9d1a-3a2b6f4e8c10".to_string(),
+        };
+        assert_eq!(FeedCursor::decode(&cursor.encode()).unwrap(), cursor);
+    }
+
+    #[test]
+    fn test_invalid_cursors_are_rejected() {
+        for cursor in ["", "not base64!", &URL_SAFE_NO_PAD.encode("72.35"), &URL_SAFE_NO_PAD.encode("NaN:abc")] {
+            assert!(matches!(FeedCursor::decode(cursor), Err(AppError::Validation(_))));
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Gender and `looking_for` normalization so preferences can be matched in SQL
+//! `gender` holds one canonical value; `looking_for` holds a comma-separated set of them
+//! (e.g. "woman,non-binary"), which MySQL's FIND_IN_SET can check.
+
+use crate::errors::AppError;
+
+/// Canonical genders, in the order they're stored in `looking_for`
+pub const GENDERS: [&str; 3] = ["man", "woman", "non-binary"];
+
+/// Map a gender to its canonical value, accepting a few common spellings
+pub fn normalize_gender(gender: &str) -> Result<&'static str, AppError> {
+    match gender.trim().to_lowercase().as_str() {
+        "man" | "male" | "m" => Ok("man"),
+        "woman" | "female" | "f" | "w" => Ok("woman"),
+        "non-binary" | "nonbinary" | "non binary" | "nb" | "enby" => Ok("non-binary"),
+        other => Err(AppError::Validation(format!(
+            "Unknown gender '{}', expected one of: {}",
+            other,
+            GENDERS.join(", ")
+        ))),
+    }
+}
+
+/// Normalize a comma-separated list of genders into the stored form: canonical values,
+/// no duplicates, in `GENDERS` order
+pub fn normalize_gender_list(genders: &str) -> Result<String, AppError> {
+    let mut selected = Vec::new();
+    for gender in genders.split(',').filter(|g| !g.trim().is_empty()) {
+        selected.push(normalize_gender(gender)?);
+    }
+
+    if selected.is_empty() {
+        return Err(AppError::Validation("Select at least one gender".to_string()));
+    }
+
+    Ok(GENDERS
+        .iter()
+        .filter(|g| selected.contains(g))
+        .copied()
+        .collect::<Vec<_>>()
+        .join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_gender() {
+        assert_eq!(normalize_gender("Female").unwrap(), "woman");
+        assert_eq!(normalize_gender(" man ").unwrap(), "man");
+        assert_eq!(normalize_gender("Non Binary").unwrap(), "non-binary");
+        assert!(normalize_gender("robot").is_err());
+    }
+
+    #[test]
+    fn test_normalize_gender_list() {
+        assert_eq!(
+            normalize_gender_list("non-binary, Woman, female").unwrap(),
+            "woman,non-binary"
+        );
+        assert_eq!(normalize_gender_list("male").unwrap(), "man");
+        assert!(normalize_gender_list(" , ").is_err());
+        assert!(normalize_gender_list("woman,robot").is_err());
+    }
+}
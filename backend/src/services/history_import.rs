@@ -0,0 +1,422 @@
+//! Listening history from export files, for users whose Last.fm profile can't be synced
+//! Parses a Last.fm scrobble CSV or a ListenBrainz listens export into individual listens and
+//! aggregates them into the same per-period top artists and tracks a Last.fm sync produces.
+
+use crate::{
+    errors::AppError,
+    models::{artist_key, Artist, Period, Track},
+    services::{lastfm_service::SyncResult, normalize_artist_name},
+};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Top artists and tracks kept per period, as many as a Last.fm sync fetches
+const IMPORT_TOP_LIMIT: usize = 50;
+
+/// Date formats seen in Last.fm CSV exports, tried in order
+const CSV_DATE_FORMATS: [&str; 4] = ["%d %b %Y %H:%M", "%d %b %Y, %H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"];
+
+/// Supported export file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Last.fm scrobbles as CSV: `artist,album,track,date` rows without a header (as produced by
+    /// the common export tools), or any column order with a header row
+    LastFmCsv,
+    /// ListenBrainz listens export: a JSON array of listens, or one listen per line
+    ListenBrainzJson,
+}
+
+impl ExportFormat {
+    /// Guess the format from the file contents: ListenBrainz exports are JSON
+    pub fn detect(contents: &str) -> Self {
+        match contents.trim_start_matches('\u{feff}').trim_start().chars().next() {
+            Some('[') | Some('{') => ExportFormat::ListenBrainzJson,
+            _ => ExportFormat::LastFmCsv,
+        }
+    }
+
+    /// Stored in `scrobbles_cache.source` for rows aggregated from this format
+    pub fn source(&self) -> &'static str {
+        match self {
+            ExportFormat::LastFmCsv => "lastfm_export",
+            ExportFormat::ListenBrainzJson => "listenbrainz_export",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "lastfm" | "lastfm_csv" => Ok(ExportFormat::LastFmCsv),
+            "listenbrainz" | "listenbrainz_json" => Ok(ExportFormat::ListenBrainzJson),
+            other => Err(format!("Unknown export format: {}", other)),
+        }
+    }
+}
+
+/// One play of a track
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listen {
+    pub artist_name: String,
+    pub artist_mbid: Option<String>,
+    pub track_name: String,
+    /// `None` when the export has no usable date; such listens only count towards all-time
+    pub listened_at: Option<DateTime<Utc>>,
+}
+
+/// Parse an export file into listens, skipping entries without an artist or track
+pub fn parse_export(format: ExportFormat, contents: &str) -> Result<Vec<Listen>, AppError> {
+    let contents = contents.trim_start_matches('\u{feff}');
+    let listens = match format {
+        ExportFormat::LastFmCsv => parse_lastfm_csv(contents)?,
+        ExportFormat::ListenBrainzJson => parse_listenbrainz_json(contents)?,
+    };
+
+    if listens.is_empty() {
+        return Err(AppError::Validation("The export file contains no listens".to_string()));
+    }
+    Ok(listens)
+}
+
+/// Column positions in a Last.fm CSV
+struct CsvColumns {
+    artist: usize,
+    artist_mbid: Option<usize>,
+    track: usize,
+    date: Option<usize>,
+}
+
+impl CsvColumns {
+    /// Columns named in a header row, if the first record is one
+    fn from_header(header: &[String]) -> Option<Self> {
+        let position = |names: &[&str]| {
+            header
+                .iter()
+                .position(|h| names.contains(&h.trim().to_lowercase().as_str()))
+        };
+
+        Some(Self {
+            artist: position(&["artist", "artist_name", "artist name"])?,
+            artist_mbid: position(&["artist_mbid", "artist mbid"]),
+            track: position(&["track", "track_name", "track name", "title", "name"])?,
+            date: position(&["uts", "date", "utc_time", "timestamp", "listened_at"]),
+        })
+    }
+}
+
+fn parse_lastfm_csv(contents: &str) -> Result<Vec<Listen>, AppError> {
+    let records = csv_records(contents);
+    let Some(first) = records.first() else {
+        return Ok(Vec::new());
+    };
+
+    let (columns, rows) = match CsvColumns::from_header(first) {
+        Some(columns) => (columns, &records[1..]),
+        None if first.len() >= 3 => (
+            CsvColumns { artist: 0, artist_mbid: None, track: 2, date: Some(3) },
+            &records[..],
+        ),
+        None => {
+            return Err(AppError::Validation(
+                "Expected a Last.fm CSV with artist, album, track and date columns".to_string(),
+            ))
+        }
+    };
+
+    let field = |row: &Vec<String>, column: usize| {
+        row.get(column).map(|f| f.trim()).filter(|f| !f.is_empty()).map(str::to_string)
+    };
+
+    Ok(rows
+        .iter()
+        .filter_map(|row| {
+            Some(Listen {
+                artist_name: field(row, columns.artist)?,
+                artist_mbid: columns.artist_mbid.and_then(|c| field(row, c)),
+                track_name: field(row, columns.track)?,
+                listened_at: columns.date.and_then(|c| field(row, c)).and_then(|d| parse_csv_date(&d)),
+            })
+        })
+        .collect())
+}
+
+/// Unix seconds, or one of `CSV_DATE_FORMATS` in UTC
+fn parse_csv_date(date: &str) -> Option<DateTime<Utc>> {
+    if let Ok(seconds) = date.parse::<i64>() {
+        return DateTime::from_timestamp(seconds, 0);
+    }
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Some(date.with_timezone(&Utc));
+    }
+
+    CSV_DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(date, format).ok())
+        .map(|date| date.and_utc())
+}
+
+/// Split CSV text into records of fields, with RFC 4180 quoting (quoted fields may contain
+/// commas, newlines and doubled quotes)
+fn csv_records(contents: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => record.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    records
+}
+
+#[derive(Debug, Deserialize)]
+struct ListenBrainzListen {
+    listened_at: Option<i64>,
+    track_metadata: ListenBrainzTrackMetadata,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListenBrainzTrackMetadata {
+    artist_name: Option<String>,
+    track_name: Option<String>,
+    #[serde(default)]
+    additional_info: Option<ListenBrainzMbids>,
+    #[serde(default)]
+    mbid_mapping: Option<ListenBrainzMbids>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListenBrainzMbids {
+    #[serde(default)]
+    artist_mbids: Option<Vec<String>>,
+}
+
+impl ListenBrainzMbids {
+    /// The artist MBID for a single-artist credit; collaborations have several and match none
+    fn artist_mbid(&self) -> Option<String> {
+        match self.artist_mbids.as_deref() {
+            Some([mbid]) if !mbid.is_empty() => Some(mbid.clone()),
+            _ => None,
+        }
+    }
+}
+
+fn parse_listenbrainz_json(contents: &str) -> Result<Vec<Listen>, AppError> {
+    let invalid = |e: serde_json::Error| AppError::Validation(format!("Invalid ListenBrainz export: {}", e));
+
+    let listens: Vec<ListenBrainzListen> = if contents.trim_start().starts_with('[') {
+        serde_json::from_str(contents).map_err(invalid)?
+    } else {
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .map_err(invalid)?
+    };
+
+    Ok(listens
+        .into_iter()
+        .filter_map(|listen| {
+            let metadata = listen.track_metadata;
+            let artist_mbid = metadata
+                .mbid_mapping
+                .as_ref()
+                .and_then(ListenBrainzMbids::artist_mbid)
+                .or_else(|| metadata.additional_info.as_ref().and_then(ListenBrainzMbids::artist_mbid));
+
+            Some(Listen {
+                artist_name: metadata.artist_name.filter(|a| !a.trim().is_empty())?.trim().to_string(),
+                artist_mbid,
+                track_name: metadata.track_name.filter(|t| !t.trim().is_empty())?.trim().to_string(),
+                listened_at: listen.listened_at.and_then(|t| DateTime::from_timestamp(t, 0)),
+            })
+        })
+        .collect())
+}
+
+/// Days of listening a period covers, counting back from now; `None` for all time
+fn period_days(period: Period) -> Option<i64> {
+    match period {
+        Period::SevenDay => Some(7),
+        Period::OneMonth => Some(30),
+        Period::ThreeMonth => Some(90),
+        Period::SixMonth => Some(180),
+        Period::TwelveMonth => Some(365),
+        Period::Overall => None,
+    }
+}
+
+/// Per-period top artists and tracks by play count, like Last.fm's charts
+/// Spellings of the same artist are counted together, under the first spelling seen.
+pub fn aggregate_listens(listens: &[Listen], now: DateTime<Utc>) -> SyncResult {
+    let mut result = SyncResult::default();
+
+    for period in Period::ALL {
+        let cutoff = period_days(period).map(|days| now - Duration::days(days));
+        let in_period = listens.iter().filter(|listen| match (cutoff, listen.listened_at) {
+            (None, _) => true,
+            (Some(cutoff), Some(listened_at)) => listened_at >= cutoff && listened_at <= now,
+            (Some(_), None) => false,
+        });
+
+        let mut artists: HashMap<String, Artist> = HashMap::new();
+        let mut tracks: HashMap<(String, String), Track> = HashMap::new();
+        for listen in in_period {
+            let key = artist_key(&listen.artist_name, listen.artist_mbid.as_deref());
+            artists
+                .entry(key.clone())
+                .or_insert_with(|| Artist {
+                    name: listen.artist_name.clone(),
+                    mbid: listen.artist_mbid.clone(),
+                    play_count: 0,
+                    listeners: 0,
+                })
+                .play_count += 1;
+            tracks
+                .entry((key, listen.track_name.trim().to_lowercase()))
+                .or_insert_with(|| Track {
+                    name: listen.track_name.clone(),
+                    artist_name: listen.artist_name.clone(),
+                    artist_mbid: listen.artist_mbid.clone(),
+                    play_count: 0,
+                })
+                .play_count += 1;
+        }
+
+        let mut artists: Vec<Artist> = artists.into_values().collect();
+        artists.sort_by(|a, b| {
+            b.play_count
+                .cmp(&a.play_count)
+                .then_with(|| normalize_artist_name(&a.name).cmp(&normalize_artist_name(&b.name)))
+        });
+        artists.truncate(IMPORT_TOP_LIMIT);
+
+        let mut tracks: Vec<Track> = tracks.into_values().collect();
+        tracks.sort_by(|a, b| b.play_count.cmp(&a.play_count).then_with(|| a.name.cmp(&b.name)));
+        tracks.truncate(IMPORT_TOP_LIMIT);
+
+        result.artists.insert(period, artists);
+        result.tracks.insert(period, tracks);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(ExportFormat::detect("[{\"listened_at\": 1}]"), ExportFormat::ListenBrainzJson);
+        assert_eq!(ExportFormat::detect("\u{feff}{\"listened_at\": 1}\n"), ExportFormat::ListenBrainzJson);
+        assert_eq!(ExportFormat::detect("Slowdive,Souvlaki,Alison,14 Nov 2023 22:13"), ExportFormat::LastFmCsv);
+    }
+
+    #[test]
+    fn test_parse_headerless_lastfm_csv() {
+        let csv = "Slowdive,Souvlaki,Alison,14 Nov 2023 22:13\r\n\
+                   \"Crosby, Stills & Nash\",\"Crosby, Stills & Nash\",\"Helplessly Hoping\",14 Nov 2023 22:09\r\n\
+                   Grouper,,Heavy Water/I'd Rather Be Sleeping,\n";
+        let listens = parse_export(ExportFormat::LastFmCsv, csv).unwrap();
+
+        assert_eq!(listens.len(), 3);
+        assert_eq!(listens[0].artist_name, "Slowdive");
+        assert_eq!(listens[0].track_name, "Alison");
+        assert_eq!(listens[0].listened_at, Some(Utc.with_ymd_and_hms(2023, 11, 14, 22, 13, 0).unwrap()));
+        assert_eq!(listens[1].artist_name, "Crosby, Stills & Nash");
+        // No date (e.g. the track playing during the export) still counts for all time
+        assert_eq!(listens[2].listened_at, None);
+    }
+
+    #[test]
+    fn test_parse_lastfm_csv_with_header() {
+        let csv = "uts,utc_time,artist,artist_mbid,album,album_mbid,track,track_mbid\n\
+                   1700000000,\"14 Nov 2023, 22:13\",Radiohead,a74b1b7f-71a5-4011-9441-d0b5e4122711,In Rainbows,,Reckoner,\n";
+        let listens = parse_export(ExportFormat::LastFmCsv, csv).unwrap();
+
+        assert_eq!(listens.len(), 1);
+        assert_eq!(listens[0].artist_mbid.as_deref(), Some("a74b1b7f-71a5-4011-9441-d0b5e4122711"));
+        assert_eq!(listens[0].listened_at, DateTime::from_timestamp(1_700_000_000, 0));
+    }
+
+    #[test]
+    fn test_parse_listenbrainz_export() {
+        let array = r#"[
+            {"listened_at": 1700000000, "track_metadata": {"artist_name": "Slowdive", "track_name": "Alison",
+             "mbid_mapping": {"artist_mbids": ["6a2a3db1-97a0-4d5a-92b9-7a5b8f7a62e5"]}}},
+            {"listened_at": 1700000300, "track_metadata": {"artist_name": "Daft Punk feat. Pharrell Williams",
+             "track_name": "Get Lucky", "additional_info": {"artist_mbids": ["a", "b"]}}},
+            {"listened_at": 1700000600, "track_metadata": {"artist_name": "", "track_name": "Untitled"}}
+        ]"#;
+        let listens = parse_export(ExportFormat::ListenBrainzJson, array).unwrap();
+
+        assert_eq!(listens.len(), 2);
+        assert_eq!(listens[0].artist_mbid.as_deref(), Some("6a2a3db1-97a0-4d5a-92b9-7a5b8f7a62e5"));
+        assert_eq!(listens[1].artist_mbid, None);
+
+        let lines = "{\"listened_at\": 1700000000, \"track_metadata\": {\"artist_name\": \"Slowdive\", \"track_name\": \"Alison\"}}\n\
+                     {\"listened_at\": 1700000300, \"track_metadata\": {\"artist_name\": \"Slowdive\", \"track_name\": \"Dagger\"}}\n";
+        assert_eq!(parse_export(ExportFormat::ListenBrainzJson, lines).unwrap().len(), 2);
+
+        assert!(parse_export(ExportFormat::ListenBrainzJson, "[]").is_err());
+        assert!(parse_export(ExportFormat::ListenBrainzJson, "{not json").is_err());
+    }
+
+    #[test]
+    fn test_aggregate_listens_into_periods() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let listen = |artist: &str, track: &str, days_ago: Option<i64>| Listen {
+            artist_name: artist.to_string(),
+            artist_mbid: None,
+            track_name: track.to_string(),
+            listened_at: days_ago.map(|days| now - Duration::days(days)),
+        };
+        let listens = vec![
+            listen("Slowdive", "Alison", Some(1)),
+            listen("slowdive", "alison", Some(2)),
+            listen("Grouper", "Heavy Water", Some(3)),
+            listen("Radiohead", "Reckoner", Some(100)),
+            listen("Radiohead", "Nude", Some(200)),
+            listen("Radiohead", "Reckoner", None),
+        ];
+        let result = aggregate_listens(&listens, now);
+
+        let week = &result.artists[&Period::SevenDay];
+        assert_eq!(week.len(), 2);
+        assert_eq!((week[0].name.as_str(), week[0].play_count), ("Slowdive", 2));
+        assert_eq!(result.tracks[&Period::SevenDay][0].play_count, 2);
+
+        assert_eq!(result.artists[&Period::SixMonth].len(), 3);
+        let overall = &result.artists[&Period::Overall];
+        assert_eq!((overall[0].name.as_str(), overall[0].play_count), ("Radiohead", 3));
+        let reckoner = result.tracks[&Period::Overall].iter().find(|t| t.name == "Reckoner").unwrap();
+        assert_eq!(reckoner.play_count, 2);
+    }
+}
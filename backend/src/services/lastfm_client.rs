@@ -0,0 +1,338 @@
+use crate::{config::Config, services::TokenBucket};
+use axum::http::StatusCode;
+use rand::Rng;
+use reqwest::{header::RETRY_AFTER, Client};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Longest we'll honour a Retry-After header for before retrying anyway
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// Failures talking to the Last.fm API, classified by Last.fm's JSON error codes
+/// (https://www.last.fm/api/errorcodes) and HTTP status
+#[derive(Error, Debug)]
+pub enum LastFmError {
+    /// Error 6 on a `user` request
+    #[error("Last.fm user not found")]
+    UserNotFound,
+
+    /// Error 6 on any other request (unknown artist, track, ...)
+    #[error("Not found on Last.fm: {0}")]
+    NotFound(String),
+
+    /// Error 17
+    #[error("Your Last.fm profile is private. Allow your listening data to be shown publicly in your Last.fm privacy settings and try again")]
+    PrivateProfile,
+
+    /// Error 29 or HTTP 429
+    #[error("Last.fm is rate limiting requests, please try again in a few minutes")]
+    RateLimited,
+
+    /// Errors 4 and 15
+    #[error("Last.fm authorization token is invalid or has expired")]
+    InvalidToken,
+
+    /// Error 14
+    #[error("Last.fm access has not been authorized yet")]
+    TokenNotAuthorized,
+
+    /// Errors 11 and 16, or an HTTP 5xx
+    #[error("Last.fm is temporarily unavailable, please try again later")]
+    Unavailable,
+
+    #[error("Last.fm took too long to respond, please try again later")]
+    Timeout,
+
+    #[error("Could not reach Last.fm: {0}")]
+    Request(String),
+
+    /// Any other Last.fm error code
+    #[error("Last.fm error {code}: {message}")]
+    Api { code: i32, message: String },
+
+    #[error("Unexpected response from Last.fm: {0}")]
+    InvalidResponse(String),
+}
+
+impl LastFmError {
+    /// Map a Last.fm JSON error code; `user_request` tells a missing user apart from other missing things
+    fn from_code(code: i32, message: String, user_request: bool) -> Self {
+        match code {
+            6 if user_request => LastFmError::UserNotFound,
+            6 => LastFmError::NotFound(message),
+            17 => LastFmError::PrivateProfile,
+            29 => LastFmError::RateLimited,
+            4 | 15 => LastFmError::InvalidToken,
+            14 => LastFmError::TokenNotAuthorized,
+            11 | 16 => LastFmError::Unavailable,
+            _ => LastFmError::Api { code, message },
+        }
+    }
+
+    /// Transient failures that are worth retrying after a backoff
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            LastFmError::RateLimited
+                | LastFmError::Unavailable
+                | LastFmError::Timeout
+                | LastFmError::Request(_)
+        )
+    }
+
+    /// Message safe to show to the user; hides raw Last.fm and transport details
+    pub fn user_message(&self) -> String {
+        match self {
+            LastFmError::Request(_) => "Could not reach Last.fm, please try again later".to_string(),
+            LastFmError::Api { .. } | LastFmError::InvalidResponse(_) => {
+                "Last.fm returned an unexpected error, please try again later".to_string()
+            }
+            _ => self.to_string(),
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            LastFmError::UserNotFound | LastFmError::NotFound(_) => StatusCode::NOT_FOUND,
+            LastFmError::PrivateProfile | LastFmError::InvalidToken | LastFmError::TokenNotAuthorized => {
+                StatusCode::BAD_REQUEST
+            }
+            LastFmError::RateLimited => StatusCode::SERVICE_UNAVAILABLE,
+            LastFmError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            LastFmError::Unavailable
+            | LastFmError::Request(_)
+            | LastFmError::Api { .. }
+            | LastFmError::InvalidResponse(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmErrorResponse {
+    error: i32,
+    message: String,
+}
+
+/// HTTP client for the Last.fm API
+/// Every request is rate limited by a shared token bucket, has a timeout, and is retried with
+/// exponential backoff (plus jitter) on 5xx, 429 and Last.fm's own rate-limit/unavailable codes.
+pub struct LastFmClient {
+    client: Client,
+    api_url: String,
+    api_key: String,
+    api_secret: String,
+    rate_limiter: TokenBucket,
+    max_retries: u32,
+    retry_backoff: Duration,
+}
+
+impl LastFmClient {
+    pub fn new(config: &Config) -> Self {
+        let timeout = Duration::from_secs(config.lastfm_timeout_secs);
+        let client = Client::builder()
+            .timeout(timeout)
+            .connect_timeout(timeout)
+            .build()
+            .expect("Failed to build Last.fm HTTP client");
+
+        Self {
+            client,
+            api_url: config.lastfm_api_url.clone(),
+            api_key: config.lastfm_api_key.clone(),
+            api_secret: config.lastfm_api_secret.clone(),
+            rate_limiter: TokenBucket::new(
+                config.lastfm_rate_limit_per_sec.ceil() as u32,
+                config.lastfm_rate_limit_per_sec,
+            ),
+            max_retries: config.lastfm_max_retries,
+            retry_backoff: Duration::from_millis(config.lastfm_retry_backoff_ms),
+        }
+    }
+
+    /// Perform an unsigned (read-only) Last.fm API call
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &[(&str, &str)],
+    ) -> Result<T, LastFmError> {
+        let query = self.base_query(method, params);
+        self.send(query).await
+    }
+
+    /// Perform a signed Last.fm API call
+    pub async fn signed_get<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &[(&str, &str)],
+    ) -> Result<T, LastFmError> {
+        let mut query = self.base_query(method, params);
+
+        let api_sig = api_signature(
+            query.iter().map(|(k, v)| (k.as_str(), v.as_str())),
+            &self.api_secret,
+        );
+        query.push(("api_sig".to_string(), api_sig));
+
+        self.send(query).await
+    }
+
+    fn base_query(&self, method: &str, params: &[(&str, &str)]) -> Vec<(String, String)> {
+        let mut query: Vec<(String, String)> = vec![
+            ("method".to_string(), method.to_string()),
+            ("api_key".to_string(), self.api_key.clone()),
+        ];
+        query.extend(params.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+        query
+    }
+
+    async fn send<T: DeserializeOwned>(&self, mut query: Vec<(String, String)>) -> Result<T, LastFmError> {
+        query.push(("format".to_string(), "json".to_string()));
+        let user_request = query.iter().any(|(k, _)| k == "user");
+
+        let mut attempt = 0;
+        loop {
+            let (result, retry_after) = self.send_once(&query, user_request).await;
+
+            match result {
+                Err(e) if e.is_retryable() && attempt < self.max_retries => {
+                    let delay = retry_after
+                        .map(|d| d.min(MAX_RETRY_AFTER))
+                        .unwrap_or_else(|| self.backoff(attempt));
+                    tracing::warn!(
+                        "Last.fm request failed ({}), retrying in {:?} (attempt {}/{})",
+                        e,
+                        delay,
+                        attempt + 1,
+                        self.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Exponential backoff with up to 50% random jitter so parallel retries spread out
+    fn backoff(&self, attempt: u32) -> Duration {
+        let base = self.retry_backoff.saturating_mul(2u32.saturating_pow(attempt));
+        let jitter_ms = rand::thread_rng().gen_range(0..=base.as_millis() as u64 / 2);
+        base + Duration::from_millis(jitter_ms)
+    }
+
+    /// One HTTP round trip; also returns the server's Retry-After hint, if any
+    async fn send_once<T: DeserializeOwned>(
+        &self,
+        query: &[(String, String)],
+        user_request: bool,
+    ) -> (Result<T, LastFmError>, Option<Duration>) {
+        self.rate_limiter.acquire().await;
+
+        let response = match self.client.get(&self.api_url).query(query).send().await {
+            Ok(response) => response,
+            Err(e) if e.is_timeout() => return (Err(LastFmError::Timeout), None),
+            Err(e) => return (Err(LastFmError::Request(e.to_string())), None),
+        };
+
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+
+        let body: serde_json::Value = match response.json().await {
+            Ok(body) => body,
+            Err(_) if status == reqwest::StatusCode::TOO_MANY_REQUESTS => return (Err(LastFmError::RateLimited), retry_after),
+            Err(_) if status.is_server_error() => return (Err(LastFmError::Unavailable), retry_after),
+            Err(e) if e.is_timeout() => return (Err(LastFmError::Timeout), None),
+            Err(e) => return (Err(LastFmError::InvalidResponse(e.to_string())), None),
+        };
+
+        // Last.fm reports errors as {"error": code, "message": "..."}, sometimes with a 200 status
+        if let Ok(error) = serde_json::from_value::<LastFmErrorResponse>(body.clone()) {
+            return (
+                Err(LastFmError::from_code(error.error, error.message, user_request)),
+                retry_after,
+            );
+        }
+
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return (Err(LastFmError::RateLimited), retry_after);
+        }
+        if status.is_server_error() {
+            return (Err(LastFmError::Unavailable), retry_after);
+        }
+        if !status.is_success() {
+            return (
+                Err(LastFmError::InvalidResponse(format!("HTTP status {}", status))),
+                None,
+            );
+        }
+
+        (
+            serde_json::from_value(body).map_err(|e| LastFmError::InvalidResponse(e.to_string())),
+            None,
+        )
+    }
+}
+
+/// Compute a Last.fm `api_sig`: the md5 of all parameter names and values sorted by name
+/// and concatenated, followed by the shared secret. `format` and `callback` are not signed.
+pub fn api_signature<'a>(params: impl IntoIterator<Item = (&'a str, &'a str)>, secret: &str) -> String {
+    let mut params: Vec<(&str, &str)> = params
+        .into_iter()
+        .filter(|(k, _)| *k != "format" && *k != "callback")
+        .collect();
+    params.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut payload = String::new();
+    for (key, value) in params {
+        payload.push_str(key);
+        payload.push_str(value);
+    }
+    payload.push_str(secret);
+
+    format!("{:x}", md5::compute(payload.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_signature_sorts_params_and_skips_format() {
+        let sig = api_signature(
+            [
+                ("token", "tok456"),
+                ("method", "auth.getSession"),
+                ("format", "json"),
+                ("api_key", "abc123"),
+            ],
+            "shh",
+        );
+        assert_eq!(sig, "4f7a464034087f5e4c6f0cc486fad8b0");
+    }
+
+    #[test]
+    fn test_error_codes_are_classified() {
+        assert!(matches!(LastFmError::from_code(6, "User not found".into(), true), LastFmError::UserNotFound));
+        assert!(matches!(
+            LastFmError::from_code(6, "The artist you supplied could not be found".into(), false),
+            LastFmError::NotFound(_)
+        ));
+        assert!(matches!(LastFmError::from_code(17, String::new(), true), LastFmError::PrivateProfile));
+        assert!(matches!(LastFmError::from_code(29, String::new(), true), LastFmError::RateLimited));
+        assert!(matches!(LastFmError::from_code(26, "Suspended API key".into(), false), LastFmError::Api { code: 26, .. }));
+    }
+
+    #[test]
+    fn test_only_transient_errors_are_retried() {
+        assert!(LastFmError::RateLimited.is_retryable());
+        assert!(LastFmError::Unavailable.is_retryable());
+        assert!(!LastFmError::UserNotFound.is_retryable());
+        assert!(!LastFmError::PrivateProfile.is_retryable());
+    }
+}
@@ -2,10 +2,26 @@ use crate::{
     config::Config,
     db::DbPool,
     errors::AppError,
-    models::{Artist, Scrobble},
+    models::{Artist, NowPlayingTrack, Period, Scrobble, Track},
+    services::{
+        cache_service::keys,
+        listening_provider::{ListeningProvider, LASTFM_PROVIDER},
+        ArtistKeyService, CacheService, LastFmClient, SessionKeyCipher, TasteHistoryService,
+    },
 };
-use reqwest::Client;
-use serde::Deserialize;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Raw Last.fm chart responses are reused for a few minutes, so repeated syncs don't refetch them
+const LASTFM_RESPONSE_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+/// Top artists and tracks fetched per period on sync
+const SYNC_CHART_LIMIT: u32 = 50;
+/// Top artists/tracks only change on sync, which invalidates them, so they can be kept a while
+const TASTE_CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
 
 #[derive(Debug, Deserialize)]
 struct LastFmTopArtistsResponse {
@@ -22,95 +38,482 @@ struct LastFmArtist {
     name: String,
     mbid: Option<String>,
     playcount: String,
+    // Not returned by user.gettopartists, only by artist-level methods
+    #[serde(default)]
+    listeners: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmTopTracksResponse {
+    toptracks: TopTracks,
+}
+
+#[derive(Debug, Deserialize)]
+struct TopTracks {
+    track: Vec<LastFmTrack>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmTrack {
+    name: String,
+    playcount: String,
+    artist: LastFmTrackArtist,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmTrackArtist {
+    name: String,
+    mbid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmTopTagsResponse {
+    toptags: TopTags,
+}
+
+#[derive(Debug, Deserialize)]
+struct TopTags {
+    #[serde(default)]
+    tag: Vec<LastFmTag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmTag {
+    name: String,
+    count: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmArtistInfoResponse {
+    artist: LastFmArtistInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmArtistInfo {
+    stats: LastFmArtistStats,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmArtistStats {
     listeners: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct LastFmSimilarArtistsResponse {
+    similarartists: SimilarArtists,
+}
+
+#[derive(Debug, Deserialize)]
+struct SimilarArtists {
+    #[serde(default)]
+    artist: Vec<LastFmSimilarArtist>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmSimilarArtist {
+    name: String,
+    mbid: Option<String>,
+    #[serde(rename = "match")]
+    match_score: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmRecentTracksResponse {
+    recenttracks: RecentTracks,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecentTracks {
+    // An array, or a bare object when there's only one track
+    #[serde(default)]
+    track: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmRecentTrack {
+    name: String,
+    artist: LastFmTextWithMbid,
+    album: Option<LastFmTextWithMbid>,
+    #[serde(rename = "@attr")]
+    attr: Option<LastFmRecentTrackAttr>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmTextWithMbid {
+    #[serde(rename = "#text")]
+    text: String,
+    mbid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmRecentTrackAttr {
+    nowplaying: Option<String>,
+}
+
+/// An artist.getsimilar result; `similarity` is Last.fm's match score (0-1)
+#[derive(Debug, Clone)]
+pub struct SimilarArtist {
+    pub name: String,
+    pub mbid: Option<String>,
+    pub similarity: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmTokenResponse {
+    token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmSessionResponse {
+    session: LastFmSession,
+}
+
+/// Authenticated Last.fm session returned by auth.getSession
+#[derive(Debug, Clone, Deserialize)]
+pub struct LastFmSession {
+    pub name: String,
+    pub key: String,
+}
+
+/// Pending web-auth request handed back to the client
+#[derive(Debug, Serialize)]
+pub struct LastFmAuthRequest {
+    pub token: String,
+    pub auth_url: String,
+}
+
+/// Everything written to `scrobbles_cache` by a sync
+#[derive(Debug, Default)]
+pub struct SyncResult {
+    pub artists: HashMap<Period, Vec<Artist>>,
+    pub tracks: HashMap<Period, Vec<Track>>,
+}
+
 pub struct LastFmService {
     config: Config,
-    client: Client,
+    client: LastFmClient,
+    session_cipher: SessionKeyCipher,
+    cache: Option<Arc<CacheService>>,
 }
 
 impl LastFmService {
     pub fn new(config: Config) -> Self {
+        let session_cipher = SessionKeyCipher::new(&config.lastfm_session_encryption_key);
+
         Self {
+            client: LastFmClient::new(&config),
             config,
-            client: Client::new(),
+            session_cipher,
+            cache: None,
         }
     }
 
-    pub async fn sync_user_scrobbles(
-        &self,
-        pool: &DbPool,
-        user_id: &str,
-        lastfm_username: &str,
-    ) -> Result<Vec<Artist>, AppError> {
-        // Fetch top artists from Last.fm
-        let artists = self.fetch_top_artists(lastfm_username, "6month", 50).await?;
+    /// Cache Last.fm responses and top artists/tracks (read-through, invalidated on sync)
+    pub fn with_cache(mut self, cache: Arc<CacheService>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    async fn cached<T, F, Fut>(&self, key: String, ttl: Duration, load: F) -> Result<T, AppError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, AppError>>,
+    {
+        match &self.cache {
+            Some(cache) => cache.get_or_load(&key, ttl, load).await,
+            None => load().await,
+        }
+    }
+
+    /// Forget cached taste and compatibility for a user whose listening data just changed
+    pub async fn invalidate_cached_taste(&self, user_id: &str) {
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.invalidate_user_taste(user_id).await {
+                tracing::warn!("Failed to invalidate cached taste for user {}: {}", user_id, e);
+            }
+        }
+    }
+
+    /// Forget the cached Last.fm charts a sync reads for `username`
+    /// For syncs the user asked for: new scrobbles should show up now, not once the cache expires.
+    pub async fn invalidate_cached_charts(&self, username: &str) {
+        let Some(cache) = &self.cache else { return };
+        for period in Period::ALL {
+            let params = format!("{}:{}:{}", username.to_lowercase(), period, SYNC_CHART_LIMIT);
+            for endpoint in ["user.gettopartists", "user.gettoptracks"] {
+                if let Err(e) = cache.delete(&keys::lastfm_api(endpoint, &params)).await {
+                    tracing::warn!("Failed to invalidate cached Last.fm charts for {}: {}", username, e);
+                }
+            }
+        }
+    }
+
+    /// Start the Last.fm web-auth handshake for a user
+    /// The user must open `auth_url` and grant access before calling `complete_auth`
+    pub async fn begin_auth(&self, pool: &DbPool, user_id: &str) -> Result<LastFmAuthRequest, AppError> {
+        let token = self.get_auth_token().await?;
 
-        // Clear old cached data for this user and period
-        sqlx::query("DELETE FROM scrobbles_cache WHERE user_id = ? AND period = ?")
+        // Only one pending token per user; it is bound to this user so it can't be redeemed by anyone else
+        sqlx::query("DELETE FROM lastfm_auth_tokens WHERE user_id = ?")
             .bind(user_id)
-            .bind("6month")
             .execute(pool)
             .await?;
 
-        // Insert new data
-        for artist in &artists {
-            let scrobble = Scrobble::new(
-                user_id.to_string(),
-                artist.name.clone(),
-                artist.mbid.clone(),
-                artist.play_count,
-                artist.listeners,
-                "6month".to_string(),
-            );
-
-            sqlx::query(
-                "INSERT INTO scrobbles_cache (id, user_id, artist_name, artist_mbid, play_count, listeners, period) VALUES (?, ?, ?, ?, ?, ?, ?)"
-            )
-            .bind(&scrobble.id)
-            .bind(&scrobble.user_id)
-            .bind(&scrobble.artist_name)
-            .bind(&scrobble.artist_mbid)
-            .bind(scrobble.play_count)
-            .bind(scrobble.listeners)
-            .bind(&scrobble.period)
+        sqlx::query("INSERT INTO lastfm_auth_tokens (token, user_id) VALUES (?, ?)")
+            .bind(&token)
+            .bind(user_id)
             .execute(pool)
             .await?;
+
+        Ok(LastFmAuthRequest {
+            auth_url: self.auth_url(&token),
+            token,
+        })
+    }
+
+    /// Complete the handshake once the user has authorized the token on Last.fm
+    /// The connected username is taken from the Last.fm session, never from the client
+    pub async fn complete_auth(&self, pool: &DbPool, user_id: &str, token: &str) -> Result<String, AppError> {
+        // Last.fm tokens are valid for 60 minutes
+        let pending: Option<(String,)> = sqlx::query_as(
+            "SELECT token FROM lastfm_auth_tokens WHERE token = ? AND user_id = ? AND created_at > DATE_SUB(NOW(), INTERVAL 60 MINUTE)"
+        )
+        .bind(token)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+        if pending.is_none() {
+            return Err(AppError::Validation("Unknown or expired Last.fm authorization token".to_string()));
+        }
+
+        let session = self.get_session(token).await?;
+
+        let other_owner: Option<(String,)> = sqlx::query_as(
+            "SELECT user_id FROM lastfm_sessions WHERE lastfm_username = ? AND user_id != ?"
+        )
+        .bind(&session.name)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+        let already_connected =
+            || AppError::Validation("This Last.fm account is already connected to another profile".to_string());
+        if other_owner.is_some() {
+            return Err(already_connected());
+        }
+
+        let previous_username: Option<String> = sqlx::query_scalar("SELECT lastfm_username FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
+
+        let encrypted_key = self.session_cipher.encrypt(&session.key)?;
+
+        let mut transaction = pool.begin().await?;
+
+        sqlx::query("DELETE FROM lastfm_sessions WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        sqlx::query(
+            "INSERT INTO lastfm_sessions (user_id, lastfm_username, session_key_encrypted) VALUES (?, ?, ?)"
+        )
+        .bind(user_id)
+        .bind(&session.name)
+        .bind(&encrypted_key)
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| match e {
+            // Another profile connected the same account since the check above
+            sqlx::Error::Database(ref db_error) if db_error.is_unique_violation() => already_connected(),
+            e => e.into(),
+        })?;
+
+        // Profiles that only claim the account by username (no session) lose it to whoever proves it
+        let unverified_holders: Vec<String> =
+            sqlx::query_scalar("SELECT id FROM users WHERE lastfm_username = ? AND id != ? FOR UPDATE")
+                .bind(&session.name)
+                .bind(user_id)
+                .fetch_all(&mut *transaction)
+                .await?;
+        for holder_id in &unverified_holders {
+            sqlx::query("UPDATE users SET lastfm_username = NULL, lastfm_connected_at = NULL WHERE id = ?")
+                .bind(holder_id)
+                .execute(&mut *transaction)
+                .await?;
+            sqlx::query("DELETE FROM scrobbles_cache WHERE user_id = ? AND source = ?")
+                .bind(holder_id)
+                .bind(LASTFM_PROVIDER)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        sqlx::query("UPDATE users SET lastfm_username = ?, lastfm_connected_at = NOW() WHERE id = ?")
+            .bind(&session.name)
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        // Listening data from a previously connected account no longer describes this user;
+        // imported history stays until the first successful sync replaces it
+        if previous_username.as_deref() != Some(session.name.as_str()) {
+            sqlx::query("DELETE FROM scrobbles_cache WHERE user_id = ? AND source = ?")
+                .bind(user_id)
+                .bind(LASTFM_PROVIDER)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        sqlx::query("DELETE FROM lastfm_auth_tokens WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+        self.invalidate_cached_taste(user_id).await;
+        for holder_id in &unverified_holders {
+            self.invalidate_cached_taste(holder_id).await;
+        }
+
+        Ok(session.name)
+    }
+
+    /// Fetch an unauthorized request token (auth.getToken)
+    pub async fn get_auth_token(&self) -> Result<String, AppError> {
+        let data: LastFmTokenResponse = self.client.signed_get("auth.getToken", &[]).await?;
+        Ok(data.token)
+    }
+
+    /// Exchange a user-authorized token for a session (auth.getSession)
+    pub async fn get_session(&self, token: &str) -> Result<LastFmSession, AppError> {
+        let data: LastFmSessionResponse = self
+            .client
+            .signed_get("auth.getSession", &[("token", token)])
+            .await?;
+        Ok(data.session)
+    }
+
+    /// URL the user must visit to authorize a request token
+    pub fn auth_url(&self, token: &str) -> String {
+        format!(
+            "{}?api_key={}&token={}",
+            self.config.lastfm_auth_url, self.config.lastfm_api_key, token
+        )
+    }
+
+    /// Sync top artists and tracks for every Last.fm period into `scrobbles_cache`, and snapshot the
+    /// one-month chart for taste history
+    /// All periods are fetched before anything is written, so a failed sync leaves the old data intact
+    pub async fn sync_user_scrobbles(
+        &self,
+        pool: &DbPool,
+        user_id: &str,
+        lastfm_username: &str,
+    ) -> Result<SyncResult, AppError> {
+        let synced = self.fetch_listening_data(lastfm_username).await?;
+        self.store_listening_data(pool, user_id, &synced, LASTFM_PROVIDER).await?;
+
+        Ok(synced)
+    }
+
+    /// Replace a user's top artists and tracks in `scrobbles_cache` with `data`, marking the rows
+    /// with where they came from, and snapshot the one-month chart for taste history
+    pub async fn store_listening_data(
+        &self,
+        pool: &DbPool,
+        user_id: &str,
+        data: &SyncResult,
+        source: &str,
+    ) -> Result<(), AppError> {
+        let artists = data.artists.values().flatten().map(|a| (a.name.as_str(), a.mbid.as_deref()));
+        let track_artists = data.tracks.values().flatten().map(|t| (t.artist_name.as_str(), t.artist_mbid.as_deref()));
+
+        let mut transaction = pool.begin().await?;
+        let known_mbids = ArtistKeyService::resolve(&mut transaction, artists.chain(track_artists)).await?;
+
+        for period in Period::ALL {
+            // Clear old cached data for this user and period
+            sqlx::query("DELETE FROM scrobbles_cache WHERE user_id = ? AND period = ?")
+                .bind(user_id)
+                .bind(period.as_str())
+                .execute(&mut *transaction)
+                .await?;
+
+            let artist_rows = data.artists[&period].iter().map(|artist| {
+                Scrobble::new(
+                    user_id.to_string(),
+                    artist.name.clone(),
+                    artist.mbid.clone(),
+                    artist.play_count,
+                    artist.listeners,
+                    period.to_string(),
+                )
+            });
+            let track_rows = data.tracks[&period]
+                .iter()
+                .map(|track| Scrobble::new_track(user_id.to_string(), track, period.to_string()));
+
+            for mut scrobble in artist_rows.chain(track_rows) {
+                if let Some(mbid_key) = known_mbids.get(&scrobble.artist_key) {
+                    scrobble.artist_key = mbid_key.clone();
+                }
+                sqlx::query(
+                    "INSERT INTO scrobbles_cache (id, user_id, artist_name, artist_mbid, artist_key, track_name, play_count, listeners, period, source) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+                )
+                .bind(&scrobble.id)
+                .bind(&scrobble.user_id)
+                .bind(&scrobble.artist_name)
+                .bind(&scrobble.artist_mbid)
+                .bind(&scrobble.artist_key)
+                .bind(&scrobble.track_name)
+                .bind(scrobble.play_count)
+                .bind(scrobble.listeners)
+                .bind(&scrobble.period)
+                .bind(source)
+                .execute(&mut *transaction)
+                .await?;
+            }
         }
 
-        Ok(artists)
+        TasteHistoryService::record_snapshot(&mut transaction, user_id, &data.artists[&Period::OneMonth]).await?;
+
+        transaction.commit().await?;
+        self.invalidate_cached_taste(user_id).await;
+
+        Ok(())
     }
 
     async fn fetch_top_artists(
         &self,
         username: &str,
-        period: &str,
+        period: Period,
         limit: u32,
     ) -> Result<Vec<Artist>, AppError> {
-        let url = format!(
-            "https://ws.audioscrobbler.com/2.0/?method=user.gettopartists&user={}&period={}&limit={}&api_key={}&format=json",
-            username, period, limit, self.config.lastfm_api_key
+        let key = keys::lastfm_api(
+            "user.gettopartists",
+            &format!("{}:{}:{}", username.to_lowercase(), period, limit),
         );
-
-        let response = self
-            .client
-            .get(&url)
-            .send()
+        self.cached(key, LASTFM_RESPONSE_CACHE_TTL, || self.request_top_artists(username, period, limit))
             .await
-            .map_err(|e| AppError::ExternalApi(format!("Failed to fetch Last.fm data: {}", e)))?;
-
-        if !response.status().is_success() {
-            return Err(AppError::ExternalApi(format!(
-                "Last.fm API returned status: {}",
-                response.status()
-            )));
-        }
+    }
 
-        let data: LastFmTopArtistsResponse = response
-            .json()
-            .await
-            .map_err(|e| AppError::ExternalApi(format!("Failed to parse Last.fm response: {}", e)))?;
+    async fn request_top_artists(
+        &self,
+        username: &str,
+        period: Period,
+        limit: u32,
+    ) -> Result<Vec<Artist>, AppError> {
+        let limit = limit.to_string();
+        let data: LastFmTopArtistsResponse = self
+            .client
+            .get(
+                "user.gettopartists",
+                &[("user", username), ("period", period.as_str()), ("limit", &limit)],
+            )
+            .await?;
 
         Ok(data
             .topartists
@@ -124,33 +527,332 @@ impl LastFmService {
                     a.mbid
                 },
                 play_count: a.playcount.parse().unwrap_or(0),
-                listeners: a.listeners.parse().unwrap_or(0),
+                listeners: a.listeners.and_then(|l| l.parse().ok()).unwrap_or(0),
+            })
+            .collect())
+    }
+
+    async fn fetch_top_tracks(
+        &self,
+        username: &str,
+        period: Period,
+        limit: u32,
+    ) -> Result<Vec<Track>, AppError> {
+        let key = keys::lastfm_api(
+            "user.gettoptracks",
+            &format!("{}:{}:{}", username.to_lowercase(), period, limit),
+        );
+        self.cached(key, LASTFM_RESPONSE_CACHE_TTL, || self.request_top_tracks(username, period, limit))
+            .await
+    }
+
+    async fn request_top_tracks(
+        &self,
+        username: &str,
+        period: Period,
+        limit: u32,
+    ) -> Result<Vec<Track>, AppError> {
+        let limit = limit.to_string();
+        let data: LastFmTopTracksResponse = self
+            .client
+            .get(
+                "user.gettoptracks",
+                &[("user", username), ("period", period.as_str()), ("limit", &limit)],
+            )
+            .await?;
+
+        Ok(data
+            .toptracks
+            .track
+            .into_iter()
+            .map(|t| Track {
+                name: t.name,
+                artist_name: t.artist.name,
+                artist_mbid: t.artist.mbid.filter(|m| !m.is_empty()),
+                play_count: t.playcount.parse().unwrap_or(0),
+            })
+            .collect())
+    }
+
+    /// Fetch an artist's top tags (artist.gettoptags) as (tag, weight 0-100) pairs
+    /// Last.fm uses the MBID when given, otherwise the (autocorrected) name
+    pub async fn fetch_artist_top_tags(
+        &self,
+        artist_name: &str,
+        artist_mbid: Option<&str>,
+    ) -> Result<Vec<(String, i32)>, AppError> {
+        let mut params = vec![("artist", artist_name), ("autocorrect", "1")];
+        if let Some(mbid) = artist_mbid {
+            params.push(("mbid", mbid));
+        }
+
+        let data: LastFmTopTagsResponse = self.client.get("artist.gettoptags", &params).await?;
+
+        Ok(data
+            .toptags
+            .tag
+            .into_iter()
+            .map(|t| {
+                // Counts come back as numbers or numeric strings depending on the endpoint version
+                let weight = match &t.count {
+                    serde_json::Value::Number(n) => n.as_i64().unwrap_or(0) as i32,
+                    serde_json::Value::String(s) => s.parse().unwrap_or(0),
+                    _ => 0,
+                };
+                (t.name.trim().to_lowercase(), weight)
             })
             .collect())
     }
 
+    /// Fetch an artist's Last.fm-wide listener count (artist.getinfo)
+    pub async fn fetch_artist_listeners(&self, artist_name: &str, artist_mbid: Option<&str>) -> Result<i32, AppError> {
+        let mut params = vec![("artist", artist_name), ("autocorrect", "1")];
+        if let Some(mbid) = artist_mbid {
+            params.push(("mbid", mbid));
+        }
+
+        let data: LastFmArtistInfoResponse = self.client.get("artist.getinfo", &params).await?;
+
+        Ok(data.artist.stats.listeners.parse().unwrap_or(0))
+    }
+
+    /// Fetch the artists Last.fm considers most similar to one (artist.getsimilar)
+    pub async fn fetch_similar_artists(
+        &self,
+        artist_name: &str,
+        artist_mbid: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<SimilarArtist>, AppError> {
+        let limit = limit.to_string();
+        let mut params = vec![("artist", artist_name), ("autocorrect", "1"), ("limit", limit.as_str())];
+        if let Some(mbid) = artist_mbid {
+            params.push(("mbid", mbid));
+        }
+
+        let data: LastFmSimilarArtistsResponse = self.client.get("artist.getsimilar", &params).await?;
+
+        Ok(data
+            .similarartists
+            .artist
+            .into_iter()
+            .map(|a| SimilarArtist {
+                // Match scores come back as numbers or numeric strings depending on the endpoint version
+                similarity: match &a.match_score {
+                    serde_json::Value::Number(n) => n.as_f64().unwrap_or(0.0),
+                    serde_json::Value::String(s) => s.parse().unwrap_or(0.0),
+                    _ => 0.0,
+                },
+                name: a.name,
+                mbid: a.mbid.filter(|m| !m.is_empty()),
+            })
+            .collect())
+    }
+
+    /// The track a user is scrobbling right now, if any (user.getrecenttracks)
+    /// Never cached: it's polled precisely because it changes every few minutes.
+    pub async fn fetch_now_playing(&self, username: &str) -> Result<Option<NowPlayingTrack>, AppError> {
+        let data: LastFmRecentTracksResponse = self
+            .client
+            .get("user.getrecenttracks", &[("user", username), ("limit", "1")])
+            .await?;
+
+        let tracks = match data.recenttracks.track {
+            serde_json::Value::Array(tracks) => tracks,
+            serde_json::Value::Null => Vec::new(),
+            track => vec![track],
+        };
+
+        // Only the first track can be playing; the rest are past scrobbles
+        let Some(track) = tracks.into_iter().next() else {
+            return Ok(None);
+        };
+        let track: LastFmRecentTrack = serde_json::from_value(track)
+            .map_err(|e| AppError::Internal(format!("Unexpected user.getrecenttracks response: {}", e)))?;
+        if track.attr.and_then(|a| a.nowplaying).as_deref() != Some("true") {
+            return Ok(None);
+        }
+
+        Ok(Some(NowPlayingTrack {
+            artist_name: track.artist.text,
+            artist_mbid: track.artist.mbid.filter(|m| !m.is_empty()),
+            track_name: track.name,
+            album_name: track.album.map(|a| a.text).filter(|a| !a.is_empty()),
+        }))
+    }
+
     pub async fn get_user_top_artists(
         &self,
         pool: &DbPool,
         user_id: &str,
+        period: Period,
         limit: i32,
     ) -> Result<Vec<Artist>, AppError> {
+        let key = keys::user_top_artists(user_id, period.as_str(), limit as usize);
+        self.cached(key, TASTE_CACHE_TTL, || Self::load_user_top_artists(pool, user_id, period, limit))
+            .await
+    }
+
+    async fn load_user_top_artists(
+        pool: &DbPool,
+        user_id: &str,
+        period: Period,
+        limit: i32,
+    ) -> Result<Vec<Artist>, AppError> {
+        // Listener counts come from artist.getinfo lookups, not the chart the row was synced from
         let scrobbles = sqlx::query_as::<_, Scrobble>(
-            "SELECT * FROM scrobbles_cache WHERE user_id = ? AND period = '6month' ORDER BY play_count DESC LIMIT ?"
+            "SELECT s.id, s.user_id, s.artist_name, s.artist_mbid, s.artist_key, s.track_name, s.play_count,
+                COALESCE(a.listeners, s.listeners) AS listeners, s.period, s.source, s.last_synced_at
+             FROM scrobbles_cache s
+             LEFT JOIN artists a ON a.artist_key = s.artist_key
+             WHERE s.user_id = ? AND s.period = ? AND s.track_name IS NULL
+             ORDER BY s.play_count DESC
+             LIMIT ?"
         )
         .bind(user_id)
+        .bind(period.as_str())
         .bind(limit)
         .fetch_all(pool)
         .await?;
 
-        Ok(scrobbles
-            .into_iter()
-            .map(|s| Artist {
-                name: s.artist_name,
-                mbid: s.artist_mbid,
-                play_count: s.play_count,
-                listeners: s.listeners,
-            })
-            .collect())
+        Ok(scrobbles.into_iter().map(Artist::from).collect())
+    }
+
+    pub async fn get_user_top_tracks(
+        &self,
+        pool: &DbPool,
+        user_id: &str,
+        period: Period,
+        limit: i32,
+    ) -> Result<Vec<Track>, AppError> {
+        let key = keys::user_top_tracks(user_id, period.as_str(), limit as usize);
+        self.cached(key, TASTE_CACHE_TTL, || Self::load_user_top_tracks(pool, user_id, period, limit))
+            .await
+    }
+
+    async fn load_user_top_tracks(
+        pool: &DbPool,
+        user_id: &str,
+        period: Period,
+        limit: i32,
+    ) -> Result<Vec<Track>, AppError> {
+        let scrobbles = sqlx::query_as::<_, Scrobble>(
+            "SELECT * FROM scrobbles_cache WHERE user_id = ? AND period = ? AND track_name IS NOT NULL ORDER BY play_count DESC LIMIT ?"
+        )
+        .bind(user_id)
+        .bind(period.as_str())
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(scrobbles.into_iter().filter_map(Scrobble::into_track).collect())
+    }
+
+    /// Top artists for every synced period in a single query, at most `limit` per period
+    pub async fn get_user_taste(
+        &self,
+        pool: &DbPool,
+        user_id: &str,
+        limit: i32,
+    ) -> Result<HashMap<Period, Vec<Artist>>, AppError> {
+        let key = keys::user_taste(user_id, limit as usize);
+        self.cached(key, TASTE_CACHE_TTL, || Self::load_user_taste(pool, user_id, limit))
+            .await
+    }
+
+    async fn load_user_taste(
+        pool: &DbPool,
+        user_id: &str,
+        limit: i32,
+    ) -> Result<HashMap<Period, Vec<Artist>>, AppError> {
+        let scrobbles = sqlx::query_as::<_, Scrobble>(
+            "SELECT r.id, r.user_id, r.artist_name, r.artist_mbid, r.artist_key, r.track_name, r.play_count,
+                COALESCE(a.listeners, r.listeners) AS listeners, r.period, r.source, r.last_synced_at
+             FROM (
+                SELECT s.*, ROW_NUMBER() OVER (PARTITION BY period ORDER BY play_count DESC) AS period_rank
+                FROM scrobbles_cache s
+                WHERE user_id = ? AND track_name IS NULL
+             ) r
+             LEFT JOIN artists a ON a.artist_key = r.artist_key
+             WHERE r.period_rank <= ?
+             ORDER BY r.period, r.play_count DESC"
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+        let mut taste: HashMap<Period, Vec<Artist>> = HashMap::new();
+        for scrobble in scrobbles {
+            // Skip rows written with a period we no longer recognise
+            if let Ok(period) = scrobble.period.parse::<Period>() {
+                taste.entry(period).or_default().push(Artist::from(scrobble));
+            }
+        }
+
+        Ok(taste)
+    }
+
+    /// Top tracks for every synced period in a single query, at most `limit` per period
+    pub async fn get_user_track_taste(
+        &self,
+        pool: &DbPool,
+        user_id: &str,
+        limit: i32,
+    ) -> Result<HashMap<Period, Vec<Track>>, AppError> {
+        let key = keys::user_track_taste(user_id, limit as usize);
+        self.cached(key, TASTE_CACHE_TTL, || Self::load_user_track_taste(pool, user_id, limit))
+            .await
+    }
+
+    async fn load_user_track_taste(
+        pool: &DbPool,
+        user_id: &str,
+        limit: i32,
+    ) -> Result<HashMap<Period, Vec<Track>>, AppError> {
+        let scrobbles = sqlx::query_as::<_, Scrobble>(
+            "SELECT id, user_id, artist_name, artist_mbid, artist_key, track_name, play_count, listeners, period, source, last_synced_at
+             FROM (
+                SELECT s.*, ROW_NUMBER() OVER (PARTITION BY period ORDER BY play_count DESC) AS period_rank
+                FROM scrobbles_cache s
+                WHERE user_id = ? AND track_name IS NOT NULL
+             ) ranked
+             WHERE period_rank <= ?
+             ORDER BY period, play_count DESC"
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+        let mut taste: HashMap<Period, Vec<Track>> = HashMap::new();
+        for scrobble in scrobbles {
+            let Ok(period) = scrobble.period.parse::<Period>() else {
+                continue;
+            };
+            if let Some(track) = scrobble.into_track() {
+                taste.entry(period).or_default().push(track);
+            }
+        }
+
+        Ok(taste)
+    }
+}
+
+#[async_trait]
+impl ListeningProvider for LastFmService {
+    fn name(&self) -> &'static str {
+        LASTFM_PROVIDER
+    }
+
+    async fn fetch_listening_data(&self, username: &str) -> Result<SyncResult, AppError> {
+        let mut data = SyncResult::default();
+        for period in Period::ALL {
+            let artists = self.fetch_top_artists(username, period, SYNC_CHART_LIMIT).await?;
+            let tracks = self.fetch_top_tracks(username, period, SYNC_CHART_LIMIT).await?;
+            data.artists.insert(period, artists);
+            data.tracks.insert(period, tracks);
+        }
+
+        Ok(data)
     }
 }
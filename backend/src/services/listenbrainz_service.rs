@@ -0,0 +1,233 @@
+use crate::{
+    config::Config,
+    errors::AppError,
+    models::{Artist, Period, Track},
+    services::{
+        lastfm_service::SyncResult,
+        listening_provider::{ListeningProvider, LISTENBRAINZ_PROVIDER},
+        TokenBucket,
+    },
+};
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode, Url};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::time::Duration;
+
+/// Artists and recordings fetched per period, as many as a Last.fm sync fetches
+const LISTENBRAINZ_CHART_SIZE: &str = "50";
+const LISTENBRAINZ_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Deserialize)]
+struct ListenCountResponse {
+    payload: ListenCount,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListenCount {
+    count: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArtistStatsResponse {
+    payload: ArtistStats,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArtistStats {
+    #[serde(default)]
+    artists: Vec<ListenBrainzArtist>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListenBrainzArtist {
+    artist_name: String,
+    artist_mbid: Option<String>,
+    #[serde(default)]
+    artist_mbids: Vec<String>,
+    listen_count: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordingStatsResponse {
+    payload: RecordingStats,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordingStats {
+    #[serde(default)]
+    recordings: Vec<ListenBrainzRecording>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListenBrainzRecording {
+    track_name: String,
+    artist_name: String,
+    #[serde(default)]
+    artist_mbids: Vec<String>,
+    listen_count: i32,
+}
+
+/// The MBID of a single-artist credit; collaborations list several and match none
+fn single_artist_mbid(artist_mbid: Option<String>, artist_mbids: Vec<String>) -> Option<String> {
+    artist_mbid.or_else(|| match <[String; 1]>::try_from(artist_mbids) {
+        Ok([mbid]) => Some(mbid),
+        Err(_) => None,
+    })
+    .filter(|m| !m.is_empty())
+}
+
+/// ListenBrainz statistics range closest to a Last.fm chart period
+fn stats_range(period: Period) -> &'static str {
+    match period {
+        Period::SevenDay => "week",
+        Period::OneMonth => "month",
+        Period::ThreeMonth => "quarter",
+        Period::SixMonth => "half_yearly",
+        Period::TwelveMonth => "year",
+        Period::Overall => "all_time",
+    }
+}
+
+/// Listening data from ListenBrainz's public user statistics (no authentication needed)
+pub struct ListenBrainzService {
+    client: Client,
+    api_url: String,
+    rate_limiter: TokenBucket,
+}
+
+impl ListenBrainzService {
+    pub fn new(config: &Config) -> Self {
+        let client = Client::builder()
+            .timeout(LISTENBRAINZ_TIMEOUT)
+            .connect_timeout(LISTENBRAINZ_TIMEOUT)
+            .build()
+            .expect("Failed to build ListenBrainz HTTP client");
+
+        Self {
+            client,
+            api_url: config.listenbrainz_api_url.clone(),
+            rate_limiter: TokenBucket::new(
+                config.listenbrainz_rate_limit_per_sec.ceil() as u32,
+                config.listenbrainz_rate_limit_per_sec,
+            ),
+        }
+    }
+
+    /// Check that a ListenBrainz user exists, returning their total listen count
+    pub async fn listen_count(&self, username: &str) -> Result<i64, AppError> {
+        let response: ListenCountResponse = self
+            .get(&["1", "user", username, "listen-count"], &[])
+            .await?
+            .ok_or_else(|| AppError::ExternalApi("ListenBrainz returned no listen count".to_string()))?;
+
+        Ok(response.payload.count)
+    }
+
+    async fn fetch_top_artists(&self, username: &str, period: Period) -> Result<Vec<Artist>, AppError> {
+        let stats: Option<ArtistStatsResponse> = self
+            .get(
+                &["1", "stats", "user", username, "artists"],
+                &[("range", stats_range(period)), ("count", LISTENBRAINZ_CHART_SIZE)],
+            )
+            .await?;
+
+        Ok(stats
+            .map(|s| s.payload.artists)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|a| Artist {
+                mbid: single_artist_mbid(a.artist_mbid, a.artist_mbids),
+                name: a.artist_name,
+                play_count: a.listen_count,
+                // ListenBrainz stats don't include global popularity
+                listeners: 0,
+            })
+            .collect())
+    }
+
+    async fn fetch_top_tracks(&self, username: &str, period: Period) -> Result<Vec<Track>, AppError> {
+        let stats: Option<RecordingStatsResponse> = self
+            .get(
+                &["1", "stats", "user", username, "recordings"],
+                &[("range", stats_range(period)), ("count", LISTENBRAINZ_CHART_SIZE)],
+            )
+            .await?;
+
+        Ok(stats
+            .map(|s| s.payload.recordings)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|r| Track {
+                artist_mbid: single_artist_mbid(None, r.artist_mbids),
+                name: r.track_name,
+                artist_name: r.artist_name,
+                play_count: r.listen_count,
+            })
+            .collect())
+    }
+
+    /// GET an API path; `None` when ListenBrainz has no data yet (204, e.g. stats not computed)
+    async fn get<T: DeserializeOwned>(&self, path: &[&str], query: &[(&str, &str)]) -> Result<Option<T>, AppError> {
+        let mut url = Url::parse(&self.api_url)
+            .map_err(|e| AppError::Internal(format!("Invalid ListenBrainz API URL: {}", e)))?;
+        url.path_segments_mut()
+            .map_err(|_| AppError::Internal("Invalid ListenBrainz API URL".to_string()))?
+            .pop_if_empty()
+            .extend(path);
+
+        self.rate_limiter.acquire().await;
+        let response = self
+            .client
+            .get(url)
+            .query(query)
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalApi(format!("ListenBrainz request failed: {}", e)))?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(None),
+            StatusCode::NOT_FOUND => Err(AppError::NotFound("ListenBrainz user not found".to_string())),
+            StatusCode::TOO_MANY_REQUESTS => Err(AppError::ExternalApi(
+                "ListenBrainz is rate limiting requests, please try again in a few minutes".to_string(),
+            )),
+            status if status.is_success() => response
+                .json()
+                .await
+                .map(Some)
+                .map_err(|e| AppError::ExternalApi(format!("Unexpected ListenBrainz response: {}", e))),
+            status => Err(AppError::ExternalApi(format!("ListenBrainz returned {}", status))),
+        }
+    }
+}
+
+#[async_trait]
+impl ListeningProvider for ListenBrainzService {
+    fn name(&self) -> &'static str {
+        LISTENBRAINZ_PROVIDER
+    }
+
+    async fn fetch_listening_data(&self, username: &str) -> Result<SyncResult, AppError> {
+        let mut data = SyncResult::default();
+        for period in Period::ALL {
+            data.artists.insert(period, self.fetch_top_artists(username, period).await?);
+            data.tracks.insert(period, self.fetch_top_tracks(username, period).await?);
+        }
+
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_artist_mbid() {
+        let mbid = "6a2a3db1-97a0-4d5a-92b9-7a5b8f7a62e5".to_string();
+
+        assert_eq!(single_artist_mbid(Some(mbid.clone()), vec![]), Some(mbid.clone()));
+        assert_eq!(single_artist_mbid(None, vec![mbid.clone()]), Some(mbid.clone()));
+        assert_eq!(single_artist_mbid(None, vec![mbid.clone(), "other".to_string()]), None);
+        assert_eq!(single_artist_mbid(Some(String::new()), vec![]), None);
+    }
+}
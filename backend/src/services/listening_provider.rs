@@ -0,0 +1,73 @@
+use crate::{db::DbPool, errors::AppError, services::lastfm_service::SyncResult};
+use async_trait::async_trait;
+
+/// Provider names, as stored in `users.listening_provider` and `scrobbles_cache.source`
+pub const LASTFM_PROVIDER: &str = "lastfm";
+pub const LISTENBRAINZ_PROVIDER: &str = "listenbrainz";
+pub const LISTENING_PROVIDERS: [&str; 2] = [LASTFM_PROVIDER, LISTENBRAINZ_PROVIDER];
+
+/// A service users can sync their listening data from
+/// Providers return the same per-period charts, keyed by MBID where they have one, so users on
+/// different providers are scored against each other like any two users.
+#[async_trait]
+pub trait ListeningProvider: Send + Sync {
+    /// One of `LISTENING_PROVIDERS`
+    fn name(&self) -> &'static str;
+
+    /// Top artists and tracks for every period
+    async fn fetch_listening_data(&self, username: &str) -> Result<SyncResult, AppError>;
+}
+
+/// The account a user syncs listening data from: their chosen provider and their username there
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct ListeningAccount {
+    pub provider: String,
+    pub username: String,
+}
+
+impl ListeningAccount {
+    pub fn lastfm(username: &str) -> Self {
+        Self {
+            provider: LASTFM_PROVIDER.to_string(),
+            username: username.to_string(),
+        }
+    }
+
+    pub fn listenbrainz(username: &str) -> Self {
+        Self {
+            provider: LISTENBRAINZ_PROVIDER.to_string(),
+            username: username.to_string(),
+        }
+    }
+
+    /// The user's account on their chosen provider, if they've connected one
+    pub async fn for_user(pool: &DbPool, user_id: &str) -> Result<Option<Self>, AppError> {
+        let account = sqlx::query_as::<_, ListeningAccount>(
+            "SELECT listening_provider AS provider,
+                IF(listening_provider = 'listenbrainz', listenbrainz_username, lastfm_username) AS username
+             FROM users
+             WHERE id = ?
+             AND IF(listening_provider = 'listenbrainz', listenbrainz_username, lastfm_username) IS NOT NULL",
+        )
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(account)
+    }
+}
+
+/// Check a provider name from a request
+pub fn validate_listening_provider(provider: &str) -> Result<&'static str, AppError> {
+    let provider = provider.trim().to_lowercase();
+    LISTENING_PROVIDERS
+        .into_iter()
+        .find(|p| *p == provider)
+        .ok_or_else(|| {
+            AppError::Validation(format!(
+                "Unknown listening provider \"{}\", expected one of: {}",
+                provider,
+                LISTENING_PROVIDERS.join(", ")
+            ))
+        })
+}
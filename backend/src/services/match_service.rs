@@ -1,10 +1,12 @@
 use crate::{
     db::DbPool,
     errors::AppError,
-    models::{Like, Match},
-    services::compatibility_service::CompatibilityService,
+    models::{Like, Match, Swipe},
+    services::{compatibility_service::CompatibilityService, DiscoverService},
 };
+use sqlx::MySqlConnection;
 use std::sync::Arc;
+use uuid::Uuid;
 
 pub struct MatchService {
     compatibility_service: Arc<CompatibilityService>,
@@ -17,71 +19,226 @@ impl MatchService {
         }
     }
 
+    /// Like a user, creating the match if they already liked back
+    /// Liking someone again is a no-op that returns the existing match, if any.
     pub async fn create_like(
         &self,
         pool: &DbPool,
         from_user_id: &str,
         to_user_id: &str,
     ) -> Result<Option<Match>, AppError> {
-        // Check if like already exists
-        let existing = sqlx::query_as::<_, Like>(
-            "SELECT * FROM likes WHERE from_user_id = ? AND to_user_id = ?"
-        )
-        .bind(from_user_id)
-        .bind(to_user_id)
-        .fetch_optional(pool)
-        .await?;
-
-        if existing.is_some() {
-            return Ok(None); // Already liked
+        if from_user_id == to_user_id {
+            return Err(AppError::Validation("You cannot like yourself".to_string()));
         }
 
-        // Create the like
+        let mut transaction = pool.begin().await?;
+        Self::lock_users(&mut transaction, from_user_id, to_user_id).await?;
+
         let like = Like::new(from_user_id.to_string(), to_user_id.to_string());
 
-        sqlx::query("INSERT INTO likes (id, from_user_id, to_user_id) VALUES (?, ?, ?)")
+        sqlx::query("INSERT IGNORE INTO likes (id, from_user_id, to_user_id) VALUES (?, ?, ?)")
             .bind(&like.id)
             .bind(&like.from_user_id)
             .bind(&like.to_user_id)
-            .execute(pool)
+            .execute(&mut *transaction)
+            .await?;
+
+        let mutual_like: Option<String> =
+            sqlx::query_scalar("SELECT id FROM likes WHERE from_user_id = ? AND to_user_id = ?")
+                .bind(to_user_id)
+                .bind(from_user_id)
+                .fetch_optional(&mut *transaction)
+                .await?;
+
+        let (match_record, created) = match mutual_like {
+            Some(_) => match Self::find_match(&mut transaction, from_user_id, to_user_id).await? {
+                Some(existing) => (Some(existing), false),
+                None => (Some(Self::insert_match(&mut transaction, from_user_id, to_user_id).await?), true),
+            },
+            None => (None, false),
+        };
+
+        transaction.commit().await?;
+
+        DiscoverService::remove_prospect(pool, from_user_id, to_user_id).await?;
+
+        // Scoring takes many queries, so it happens after the locks are released. The match stands
+        // even if it fails; it just has no score.
+        match match_record {
+            Some(mut match_record) if created => {
+                if let Err(e) = self.score_match(pool, &mut match_record).await {
+                    tracing::warn!("Failed to score match {}: {}", match_record.id, e);
+                }
+                Ok(Some(match_record))
+            }
+            match_record => Ok(match_record),
+        }
+    }
+
+    /// Lock two users' rows, in id order so concurrent swipes between the same pair take turns
+    /// Without it, two people liking each other at once both write their like before seeing the
+    /// other's and neither gets a match.
+    async fn lock_users(
+        transaction: &mut sqlx::Transaction<'_, sqlx::MySql>,
+        user_id: &str,
+        other_user_id: &str,
+    ) -> Result<(), AppError> {
+        let locked: Vec<String> = sqlx::query_scalar("SELECT id FROM users WHERE id IN (?, ?) ORDER BY id FOR UPDATE")
+            .bind(user_id)
+            .bind(other_user_id)
+            .fetch_all(&mut **transaction)
             .await?;
 
-        // Check if there's a mutual like (match)
-        let mutual_like = sqlx::query_as::<_, Like>(
-            "SELECT * FROM likes WHERE from_user_id = ? AND to_user_id = ?"
+        if locked.len() < 2 {
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+
+        Ok(())
+    }
+
+    async fn find_match(
+        transaction: &mut sqlx::Transaction<'_, sqlx::MySql>,
+        user_id: &str,
+        other_user_id: &str,
+    ) -> Result<Option<Match>, AppError> {
+        let existing = sqlx::query_as::<_, Match>(
+            "SELECT * FROM matches
+             WHERE (user1_id = ? AND user2_id = ?) OR (user1_id = ? AND user2_id = ?)",
         )
-        .bind(to_user_id)
-        .bind(from_user_id)
-        .fetch_optional(pool)
+        .bind(user_id)
+        .bind(other_user_id)
+        .bind(other_user_id)
+        .bind(user_id)
+        .fetch_optional(&mut **transaction)
         .await?;
 
-        if let Some(_) = mutual_like {
-            // Create match
-            let compatibility_score = self
-                .compatibility_service
-                .calculate_compatibility(pool, from_user_id, to_user_id)
-                .await?;
+        Ok(existing)
+    }
 
-            let match_record = Match::new(
-                from_user_id.to_string(),
-                to_user_id.to_string(),
-                Some(compatibility_score),
-            );
+    /// Create a match without a score; `score_match` adds it once the transaction is done
+    async fn insert_match(
+        transaction: &mut sqlx::Transaction<'_, sqlx::MySql>,
+        user_id: &str,
+        other_user_id: &str,
+    ) -> Result<Match, AppError> {
+        let match_record = Match::new(user_id.to_string(), other_user_id.to_string(), None, None);
 
-            sqlx::query(
-                "INSERT INTO matches (id, user1_id, user2_id, compatibility_score) VALUES (?, ?, ?, ?)"
-            )
+        sqlx::query("INSERT INTO matches (id, user1_id, user2_id) VALUES (?, ?, ?)")
             .bind(&match_record.id)
             .bind(&match_record.user1_id)
             .bind(&match_record.user2_id)
-            .bind(match_record.compatibility_score)
+            .execute(&mut **transaction)
+            .await?;
+
+        Ok(match_record)
+    }
+
+    /// Score a new match and store the score
+    async fn score_match(&self, pool: &DbPool, match_record: &mut Match) -> Result<(), AppError> {
+        let score = self
+            .compatibility_service
+            .calculate_compatibility(pool, &match_record.user1_id, &match_record.user2_id)
+            .await?;
+        let algorithm_version = self.compatibility_service.algorithm_version();
+
+        sqlx::query("UPDATE matches SET compatibility_score = ?, algorithm_version = ? WHERE id = ?")
+            .bind(score)
+            .bind(algorithm_version)
+            .bind(&match_record.id)
             .execute(pool)
             .await?;
 
-            return Ok(Some(match_record));
+        match_record.compatibility_score = Some(score);
+        match_record.algorithm_version = Some(algorithm_version.to_string());
+
+        Ok(())
+    }
+
+    /// Record a "no thanks"; passing on someone again restarts their cooldown
+    pub async fn create_pass(&self, pool: &DbPool, from_user_id: &str, to_user_id: &str) -> Result<(), AppError> {
+        if from_user_id == to_user_id {
+            return Err(AppError::Validation("You cannot pass on yourself".to_string()));
         }
 
-        Ok(None)
+        sqlx::query(
+            "INSERT INTO passes (id, from_user_id, to_user_id) VALUES (?, ?, ?)
+             ON DUPLICATE KEY UPDATE created_at = NOW(6)",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(from_user_id)
+        .bind(to_user_id)
+        .execute(pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(ref db_error) if db_error.is_foreign_key_violation() => {
+                AppError::NotFound("User not found".to_string())
+            }
+            e => e.into(),
+        })?;
+
+        Ok(())
+    }
+
+    /// Revert the user's most recent like or pass
+    /// Returns `None` when there is nothing to undo. A like that already created a match can't be undone.
+    pub async fn undo_last_swipe(&self, pool: &DbPool, user_id: &str) -> Result<Option<Swipe>, AppError> {
+        loop {
+            let Some(swipe) = Self::last_swipe(&mut *pool.acquire().await?, user_id).await? else {
+                return Ok(None);
+            };
+
+            // Take the same locks as `create_like`, so a like can't turn into a match between the
+            // check below and the delete
+            let mut transaction = pool.begin().await?;
+            Self::lock_users(&mut transaction, user_id, &swipe.to_user_id).await?;
+
+            // The user swiped again while we waited for the locks; undo that swipe instead
+            let latest = Self::last_swipe(&mut transaction, user_id).await?;
+            if latest.as_ref().map(|s| &s.id) != Some(&swipe.id) {
+                continue;
+            }
+
+            if swipe.kind == "like" {
+                if Self::find_match(&mut transaction, user_id, &swipe.to_user_id).await?.is_some() {
+                    return Err(AppError::Validation(
+                        "Your last like already created a match and can't be undone".to_string(),
+                    ));
+                }
+
+                sqlx::query("DELETE FROM likes WHERE id = ?")
+                    .bind(&swipe.id)
+                    .execute(&mut *transaction)
+                    .await?;
+            } else {
+                sqlx::query("DELETE FROM passes WHERE id = ?")
+                    .bind(&swipe.id)
+                    .execute(&mut *transaction)
+                    .await?;
+            }
+
+            transaction.commit().await?;
+
+            return Ok(Some(swipe));
+        }
+    }
+
+    /// The user's most recent like or pass
+    async fn last_swipe(conn: &mut MySqlConnection, user_id: &str) -> Result<Option<Swipe>, AppError> {
+        let swipe = sqlx::query_as::<_, Swipe>(
+            "SELECT kind, id, to_user_id, created_at FROM (
+                SELECT 'like' AS kind, id, to_user_id, created_at FROM likes WHERE from_user_id = ?
+                UNION ALL
+                SELECT 'pass' AS kind, id, to_user_id, created_at FROM passes WHERE from_user_id = ?
+             ) swipes
+             ORDER BY created_at DESC, id DESC
+             LIMIT 1",
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_optional(conn)
+        .await?;
+
+        Ok(swipe)
     }
 
     pub async fn get_user_matches(&self, pool: &DbPool, user_id: &str) -> Result<Vec<Match>, AppError> {
@@ -0,0 +1,272 @@
+use crate::{errors::AppError, services::cache_service::Cache};
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// In-process cache backend for local development, tests and single-node deployments
+/// Entries expire like Redis keys, and once `max_entries` is reached the least recently used
+/// entry is evicted to make room.
+pub struct MemoryCache {
+    max_entries: usize,
+    state: Mutex<MemoryState>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    entries: HashMap<String, Entry>,
+    /// Recency order: access tick -> key, oldest first
+    recency: BTreeMap<u64, String>,
+    next_tick: u64,
+}
+
+struct Entry {
+    value: String,
+    expires_at: Option<Instant>,
+    tick: u64,
+}
+
+impl Entry {
+    fn is_live(&self, now: Instant) -> bool {
+        self.expires_at.is_none_or(|at| at > now)
+    }
+}
+
+impl MemoryState {
+    /// Look up a live entry and mark it as most recently used; drops it if it has expired
+    fn touch(&mut self, key: &str) -> Option<&mut Entry> {
+        let now = Instant::now();
+        if !self.entries.get(key)?.is_live(now) {
+            self.remove(key);
+            return None;
+        }
+
+        let tick = self.next_tick;
+        self.next_tick += 1;
+
+        let entry = self.entries.get_mut(key)?;
+        self.recency.remove(&entry.tick);
+        self.recency.insert(tick, key.to_string());
+        entry.tick = tick;
+        Some(entry)
+    }
+
+    fn insert(&mut self, key: &str, value: String, expires_at: Option<Instant>, max_entries: usize) {
+        self.remove(key);
+
+        while self.entries.len() >= max_entries {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        self.recency.insert(tick, key.to_string());
+        self.entries.insert(
+            key.to_string(),
+            Entry {
+                value,
+                expires_at,
+                tick,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.tick);
+        }
+    }
+}
+
+impl MemoryCache {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries: max_entries.max(1),
+            state: Mutex::default(),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MemoryState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl Cache for MemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<String>, AppError> {
+        Ok(self.state().touch(key).map(|entry| entry.value.clone()))
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Duration) -> Result<(), AppError> {
+        self.state()
+            .insert(key, value, Some(Instant::now() + ttl), self.max_entries);
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        self.state().remove(key);
+        Ok(())
+    }
+
+    async fn delete_pattern(&self, pattern: &str) -> Result<(), AppError> {
+        let mut state = self.state();
+        let keys: Vec<String> = state
+            .entries
+            .keys()
+            .filter(|key| glob_match(pattern, key))
+            .cloned()
+            .collect();
+
+        for key in keys {
+            state.remove(&key);
+        }
+
+        Ok(())
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, AppError> {
+        Ok(self.state().touch(key).is_some())
+    }
+
+    async fn increment(&self, key: &str, ttl: Duration) -> Result<i64, AppError> {
+        let mut state = self.state();
+
+        // Like Redis INCR + EXPIRE on first increment: the window starts with the first hit
+        if let Some(entry) = state.touch(key) {
+            let value = entry
+                .value
+                .parse::<i64>()
+                .map_err(|_| AppError::Internal(format!("Cache value at {} is not an integer", key)))?
+                + 1;
+            entry.value = value.to_string();
+            return Ok(value);
+        }
+
+        state.insert(key, "1".to_string(), Some(Instant::now() + ttl), self.max_entries);
+        Ok(1)
+    }
+
+    /// Same conventions as Redis TTL: -2 for a missing key, -1 for no expiry
+    async fn ttl(&self, key: &str) -> Result<i64, AppError> {
+        Ok(match self.state().touch(key) {
+            None => -2,
+            Some(Entry { expires_at: None, .. }) => -1,
+            Some(Entry { expires_at: Some(at), .. }) => {
+                at.saturating_duration_since(Instant::now()).as_secs() as i64
+            }
+        })
+    }
+}
+
+/// Redis-style glob matching, supporting `*` (any run of characters) and `?` (one character)
+fn glob_match(pattern: &str, key: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let key: Vec<char> = key.chars().collect();
+    let (mut p, mut k) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while k < key.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, k));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == key[k] => {
+                p += 1;
+                k += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    k = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("user:abc:*", "user:abc:top_artists:6month:50"));
+        assert!(glob_match("compatibility:*:abc", "compatibility:000:abc"));
+        assert!(!glob_match("compatibility:*:abc", "compatibility:abc:zzz"));
+        assert!(glob_match("rate_limit:?:login", "rate_limit:1:login"));
+        assert!(!glob_match("user:abc:*", "user:abcd:taste:50"));
+    }
+
+    #[tokio::test]
+    async fn test_entries_expire() {
+        let cache = MemoryCache::new(10);
+        cache.set("short", "1".to_string(), Duration::from_millis(20)).await.unwrap();
+        cache.set("long", "2".to_string(), TTL).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+
+        assert_eq!(cache.get("short").await.unwrap(), None);
+        assert_eq!(cache.get("long").await.unwrap(), Some("2".to_string()));
+        assert_eq!(cache.ttl("short").await.unwrap(), -2);
+    }
+
+    #[tokio::test]
+    async fn test_evicts_least_recently_used() {
+        let cache = MemoryCache::new(2);
+        cache.set("a", "1".to_string(), TTL).await.unwrap();
+        cache.set("b", "2".to_string(), TTL).await.unwrap();
+
+        // Reading "a" makes "b" the least recently used
+        cache.get("a").await.unwrap();
+        cache.set("c", "3".to_string(), TTL).await.unwrap();
+
+        assert!(cache.exists("a").await.unwrap());
+        assert!(!cache.exists("b").await.unwrap());
+        assert!(cache.exists("c").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_overwrite_does_not_evict() {
+        let cache = MemoryCache::new(2);
+        cache.set("a", "1".to_string(), TTL).await.unwrap();
+        cache.set("b", "2".to_string(), TTL).await.unwrap();
+        cache.set("a", "3".to_string(), TTL).await.unwrap();
+
+        assert_eq!(cache.get("a").await.unwrap(), Some("3".to_string()));
+        assert!(cache.exists("b").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_increment_starts_window_on_first_hit() {
+        let cache = MemoryCache::new(10);
+        assert_eq!(cache.increment("hits", TTL).await.unwrap(), 1);
+        assert_eq!(cache.increment("hits", TTL).await.unwrap(), 2);
+        assert!(cache.ttl("hits").await.unwrap() > 0);
+
+        cache.increment("burst", Duration::from_millis(20)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(cache.increment("burst", TTL).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_delete_pattern() {
+        let cache = MemoryCache::new(10);
+        cache.set("user:a:taste:50", "1".to_string(), TTL).await.unwrap();
+        cache.set("user:b:taste:50", "1".to_string(), TTL).await.unwrap();
+
+        cache.delete_pattern("user:a:*").await.unwrap();
+
+        assert!(!cache.exists("user:a:taste:50").await.unwrap());
+        assert!(cache.exists("user:b:taste:50").await.unwrap());
+    }
+}
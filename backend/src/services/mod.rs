@@ -1,25 +1,65 @@
 pub mod auth_service;
+pub mod lastfm_client;
 pub mod lastfm_service;
 pub mod compatibility_service;
+pub mod compatibility_algorithm;
+pub mod compatibility_calibration;
+pub mod compatibility_replay;
 pub mod photo_service;
 pub mod match_service;
 pub mod email_normalization;
+pub mod gender_normalization;
+pub mod artist_normalization;
 pub mod captcha_service;
 pub mod cache_service;
+pub mod redis_cache;
+pub mod memory_cache;
 pub mod websocket_service;
 pub mod notification_service;
 pub mod achievement_service;
 pub mod event_service;
+pub mod session_crypto;
+pub mod artist_tag_service;
+pub mod artist_key_service;
+pub mod artist_similarity_service;
+pub mod token_bucket;
+pub mod sync_service;
+pub mod discover_service;
+pub mod music_dna_service;
+pub mod taste_history_service;
+pub mod history_import;
+pub mod listening_provider;
+pub mod listenbrainz_service;
+pub mod now_playing_service;
 
 pub use auth_service::AuthService;
+pub use lastfm_client::{LastFmClient, LastFmError};
 pub use lastfm_service::LastFmService;
 pub use compatibility_service::CompatibilityService;
+pub use compatibility_algorithm::{AlgorithmWeights, CompatibilityAlgorithm};
+pub use compatibility_calibration::Calibration;
 pub use photo_service::PhotoService;
 pub use match_service::MatchService;
 pub use email_normalization::normalize_email;
+pub use gender_normalization::{normalize_gender, normalize_gender_list};
+pub use artist_normalization::{normalize_artist_name, ArtistIdentities};
 pub use captcha_service::CaptchaService;
-pub use cache_service::CacheService;
+pub use cache_service::{Cache, CacheService};
+pub use redis_cache::RedisCache;
+pub use memory_cache::MemoryCache;
 pub use websocket_service::WebSocketService;
 pub use notification_service::NotificationService;
 pub use achievement_service::AchievementService;
 pub use event_service::EventService;
+pub use session_crypto::SessionKeyCipher;
+pub use artist_tag_service::ArtistTagService;
+pub use artist_key_service::ArtistKeyService;
+pub use artist_similarity_service::ArtistSimilarityService;
+pub use token_bucket::TokenBucket;
+pub use sync_service::SyncService;
+pub use discover_service::DiscoverService;
+pub use music_dna_service::MusicDnaService;
+pub use taste_history_service::TasteHistoryService;
+pub use now_playing_service::NowPlayingService;
+pub use listening_provider::{ListeningAccount, ListeningProvider};
+pub use listenbrainz_service::ListenBrainzService;
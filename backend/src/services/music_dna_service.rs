@@ -0,0 +1,395 @@
+//! Music DNA: a taste fingerprint computed from a user's all-time top artists
+//! Genres come from artist tags, how mainstream someone is from artists' listener counts, and
+//! listening eras from decade tags ("80s", "1990s"), since Last.fm has no release years for artists.
+
+use crate::{
+    db::DbPool,
+    errors::AppError,
+    models::Period,
+    services::{artist_tag_service::GenreWeight, cache_service::keys, CacheService},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Genres listed in a profile
+const TOP_GENRES: usize = 10;
+/// Genres listed in the public profile
+const PUBLIC_TOP_GENRES: usize = 5;
+/// Listener counts at (or below) which an artist counts as fully niche, and at (or above) which
+/// it counts as fully mainstream; in between is log scale
+const NICHE_LISTENERS: f64 = 10_000.0;
+const MAINSTREAM_LISTENERS: f64 = 5_000_000.0;
+/// Spreading listening evenly over this many genres is maximum diversity
+const DIVERSITY_GENRES: f64 = 20.0;
+/// Share of listening an era needs to make it into the label
+const LABEL_ERA_SHARE: f64 = 0.4;
+
+/// Profiles are recomputed after each sync (which invalidates them); this only bounds staleness
+const MUSIC_DNA_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Share of a user's listening attributed to a decade, e.g. "1990s"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EraWeight {
+    pub era: String,
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MusicDna {
+    /// Short generated description, e.g. "Eclectic underground shoegaze listener"; `None` until
+    /// the user has synced artists
+    pub label: Option<String>,
+    /// Strongest genres first; weights are shares of all genre listening
+    pub genres: Vec<GenreWeight>,
+    /// 0.0 (only niche artists) to 1.0 (only household names); 0.5 until listener counts are known
+    pub mainstream_index: f64,
+    /// Normalized genre entropy, 0.0 (one genre) to 1.0 (spread across many)
+    pub diversity: f64,
+    /// Oldest decade first; weights sum to 1 over artists with a decade tag
+    pub eras: Vec<EraWeight>,
+    pub artist_count: usize,
+}
+
+/// What other users see of a profile: the label and broad strokes, without exact weights
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicMusicDna {
+    pub label: Option<String>,
+    pub top_genres: Vec<String>,
+    pub mainstream_index: f64,
+    pub diversity: f64,
+    pub eras: Vec<String>,
+}
+
+impl From<MusicDna> for PublicMusicDna {
+    fn from(dna: MusicDna) -> Self {
+        // Rounded so the public view can't be used to fingerprint exact listening
+        let round = |value: f64| (value * 10.0).round() / 10.0;
+        Self {
+            label: dna.label,
+            top_genres: dna.genres.into_iter().take(PUBLIC_TOP_GENRES).map(|g| g.genre).collect(),
+            mainstream_index: round(dna.mainstream_index),
+            diversity: round(dna.diversity),
+            eras: dna.eras.into_iter().filter(|e| e.weight >= 0.1).map(|e| e.era).collect(),
+        }
+    }
+}
+
+/// One of the user's top artists, with its tags
+#[derive(Debug, Clone)]
+pub struct ListenedArtist {
+    pub play_count: i32,
+    /// Last.fm listener count; 0 when not looked up yet
+    pub listeners: i32,
+    pub tags: Vec<(String, i32)>,
+}
+
+pub struct MusicDnaService {
+    cache: Option<Arc<CacheService>>,
+}
+
+impl Default for MusicDnaService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MusicDnaService {
+    pub fn new() -> Self {
+        Self { cache: None }
+    }
+
+    /// Cache profiles (read-through; `LastFmService` invalidates them on sync)
+    pub fn with_cache(mut self, cache: Arc<CacheService>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub async fn get_music_dna(&self, pool: &DbPool, user_id: &str) -> Result<MusicDna, AppError> {
+        match &self.cache {
+            Some(cache) => {
+                cache
+                    .get_or_load(&keys::music_dna(user_id), MUSIC_DNA_CACHE_TTL, || {
+                        Self::compute_music_dna(pool, user_id)
+                    })
+                    .await
+            }
+            None => Self::compute_music_dna(pool, user_id).await,
+        }
+    }
+
+    /// Recompute and cache a user's profile, after a sync has refreshed their artists and tags
+    pub async fn refresh(&self, pool: &DbPool, user_id: &str) -> Result<MusicDna, AppError> {
+        let dna = Self::compute_music_dna(pool, user_id).await?;
+        if let Some(cache) = &self.cache {
+            cache.set(&keys::music_dna(user_id), &dna, MUSIC_DNA_CACHE_TTL).await?;
+        }
+        Ok(dna)
+    }
+
+    async fn compute_music_dna(pool: &DbPool, user_id: &str) -> Result<MusicDna, AppError> {
+        let artists: Vec<(String, i32, Option<i32>)> = sqlx::query_as(
+            "SELECT sc.artist_key, sc.play_count, a.listeners
+             FROM scrobbles_cache sc
+             LEFT JOIN artists a ON a.artist_key = sc.artist_key
+             WHERE sc.user_id = ? AND sc.period = ? AND sc.track_name IS NULL",
+        )
+        .bind(user_id)
+        .bind(Period::Overall.as_str())
+        .fetch_all(pool)
+        .await?;
+
+        let tags: Vec<(String, String, i32)> = sqlx::query_as(
+            "SELECT at.artist_key, at.tag, at.weight
+             FROM scrobbles_cache sc
+             INNER JOIN artist_tags at ON at.artist_key = sc.artist_key
+             WHERE sc.user_id = ? AND sc.period = ? AND sc.track_name IS NULL",
+        )
+        .bind(user_id)
+        .bind(Period::Overall.as_str())
+        .fetch_all(pool)
+        .await?;
+
+        let mut tags_by_artist: HashMap<String, Vec<(String, i32)>> = HashMap::new();
+        for (artist_key, tag, weight) in tags {
+            tags_by_artist.entry(artist_key).or_default().push((tag, weight));
+        }
+
+        let artists: Vec<ListenedArtist> = artists
+            .into_iter()
+            .map(|(artist_key, play_count, listeners)| ListenedArtist {
+                play_count,
+                listeners: listeners.unwrap_or(0),
+                tags: tags_by_artist.remove(&artist_key).unwrap_or_default(),
+            })
+            .collect();
+
+        Ok(music_dna(&artists))
+    }
+}
+
+/// Build a profile from a user's top artists
+/// Artists count by log-scaled play count, like in `ArtistTagService::get_user_genres`
+pub fn music_dna(artists: &[ListenedArtist]) -> MusicDna {
+    let mut genre_scores: HashMap<&str, f64> = HashMap::new();
+    let mut era_scores: HashMap<u16, f64> = HashMap::new();
+    let mut popularity_sum = 0.0;
+    let mut popularity_plays = 0.0;
+
+    for artist in artists {
+        let plays = (artist.play_count.max(0) as f64 + 2.0).ln();
+        // Artists whose listener count isn't known yet say nothing about how mainstream someone is
+        if artist.listeners > 0 {
+            popularity_sum += plays * mainstream_score(artist.listeners);
+            popularity_plays += plays;
+        }
+
+        for (tag, weight) in &artist.tags {
+            let score = plays * *weight as f64;
+            match decade_tag(tag) {
+                Some(decade) => *era_scores.entry(decade).or_insert(0.0) += score,
+                None => *genre_scores.entry(tag.as_str()).or_insert(0.0) += score,
+            }
+        }
+    }
+
+    let genre_total: f64 = genre_scores.values().sum();
+    let mut genres: Vec<GenreWeight> = genre_scores
+        .into_iter()
+        .map(|(genre, score)| GenreWeight {
+            genre: genre.to_string(),
+            weight: score / genre_total,
+        })
+        .collect();
+    genres.sort_by(|a, b| b.weight.total_cmp(&a.weight).then_with(|| a.genre.cmp(&b.genre)));
+    let diversity = diversity(&genres);
+    genres.truncate(TOP_GENRES);
+
+    let era_total: f64 = era_scores.values().sum();
+    let mut eras: Vec<(u16, f64)> = era_scores.into_iter().map(|(d, s)| (d, s / era_total)).collect();
+    eras.sort_by_key(|(decade, _)| *decade);
+    let eras: Vec<EraWeight> = eras
+        .into_iter()
+        .map(|(decade, weight)| EraWeight {
+            era: format!("{}s", decade),
+            weight,
+        })
+        .collect();
+
+    let mainstream_index = if popularity_plays > 0.0 { popularity_sum / popularity_plays } else { 0.5 };
+
+    MusicDna {
+        label: label(artists.len(), &genres, mainstream_index, diversity, &eras),
+        genres,
+        mainstream_index,
+        diversity,
+        eras,
+        artist_count: artists.len(),
+    }
+}
+
+/// Where an artist's listener count falls between niche (0.0) and mainstream (1.0)
+fn mainstream_score(listeners: i32) -> f64 {
+    let listeners = (listeners as f64).clamp(NICHE_LISTENERS, MAINSTREAM_LISTENERS);
+    (listeners / NICHE_LISTENERS).ln() / (MAINSTREAM_LISTENERS / NICHE_LISTENERS).ln()
+}
+
+/// Shannon entropy of the genre distribution, relative to an even spread over `DIVERSITY_GENRES`
+fn diversity(genres: &[GenreWeight]) -> f64 {
+    let entropy: f64 = genres
+        .iter()
+        .filter(|g| g.weight > 0.0)
+        .map(|g| -g.weight * g.weight.ln())
+        .sum();
+    (entropy / DIVERSITY_GENRES.ln()).clamp(0.0, 1.0)
+}
+
+/// The decade a tag like "80s", "'90s", "1970s" or "2010s" names
+fn decade_tag(tag: &str) -> Option<u16> {
+    let digits = tag
+        .trim()
+        .trim_start_matches('\'')
+        .strip_suffix('s')?
+        .trim_end_matches('\'');
+    if !digits.bytes().all(|b| b.is_ascii_digit()) || !digits.ends_with('0') {
+        return None;
+    }
+
+    let year: u16 = digits.parse().ok()?;
+    match digits.len() {
+        // Two-digit decades: "00s" and "10s" are this century, the rest the last one
+        2 if year <= 20 => Some(2000 + year),
+        2 => Some(1900 + year),
+        4 if (1900..2100).contains(&year) => Some(year),
+        _ => None,
+    }
+}
+
+fn label(
+    artist_count: usize,
+    genres: &[GenreWeight],
+    mainstream_index: f64,
+    diversity: f64,
+    eras: &[EraWeight],
+) -> Option<String> {
+    if artist_count == 0 {
+        return None;
+    }
+
+    let mut words = Vec::new();
+    if diversity >= 0.7 {
+        words.push("eclectic".to_string());
+    } else if !genres.is_empty() && diversity < 0.35 {
+        words.push("devoted".to_string());
+    }
+    if mainstream_index < 0.35 {
+        words.push("underground".to_string());
+    } else if mainstream_index >= 0.7 {
+        words.push("mainstream".to_string());
+    }
+    if let Some(era) = eras.iter().find(|e| e.weight >= LABEL_ERA_SHARE) {
+        // "1990s" reads as "90s"
+        words.push(era.era[2..].to_string());
+    }
+    if let Some(genre) = genres.first() {
+        words.push(genre.genre.clone());
+    }
+    words.push("listener".to_string());
+
+    let label = words.join(" ");
+    let mut chars = label.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artist(play_count: i32, listeners: i32, tags: &[(&str, i32)]) -> ListenedArtist {
+        ListenedArtist {
+            play_count,
+            listeners,
+            tags: tags.iter().map(|(t, w)| (t.to_string(), *w)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_decade_tag() {
+        assert_eq!(decade_tag("80s"), Some(1980));
+        assert_eq!(decade_tag("'90s"), Some(1990));
+        assert_eq!(decade_tag("90's"), Some(1990));
+        assert_eq!(decade_tag("00s"), Some(2000));
+        assert_eq!(decade_tag("2010s"), Some(2010));
+        assert_eq!(decade_tag("1970s"), Some(1970));
+        assert_eq!(decade_tag("shoegaze"), None);
+        assert_eq!(decade_tag("85s"), None);
+        assert_eq!(decade_tag("s"), None);
+    }
+
+    #[test]
+    fn test_niche_devoted_listener() {
+        let artists = vec![
+            artist(500, 200_000, &[("shoegaze", 100), ("90s", 80)]),
+            artist(300, 50_000, &[("shoegaze", 100), ("dream pop", 40), ("90s", 60)]),
+        ];
+        let dna = music_dna(&artists);
+
+        assert_eq!(dna.genres[0].genre, "shoegaze");
+        let total: f64 = dna.genres.iter().map(|g| g.weight).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert!(dna.mainstream_index < 0.5);
+        assert!(dna.diversity < 0.35);
+        assert_eq!(dna.eras.len(), 1);
+        assert_eq!(dna.eras[0].era, "1990s");
+        assert_eq!(dna.label.as_deref(), Some("Devoted 90s shoegaze listener"));
+    }
+
+    #[test]
+    fn test_mainstream_index_follows_listeners() {
+        let niche = music_dna(&[artist(100, 5_000, &[])]);
+        let mainstream = music_dna(&[artist(100, 8_000_000, &[])]);
+        assert_eq!(niche.mainstream_index, 0.0);
+        assert_eq!(mainstream.mainstream_index, 1.0);
+        assert_eq!(mainstream.label.as_deref(), Some("Mainstream listener"));
+    }
+
+    #[test]
+    fn test_unknown_listeners_are_left_out_of_mainstream_index() {
+        let unknown = music_dna(&[artist(100, 0, &[])]);
+        assert_eq!(unknown.mainstream_index, 0.5);
+        assert_eq!(unknown.label.as_deref(), Some("Listener"));
+
+        let mixed = music_dna(&[artist(100, 8_000_000, &[]), artist(100, 0, &[])]);
+        assert_eq!(mixed.mainstream_index, 1.0);
+    }
+
+    #[test]
+    fn test_many_even_genres_are_diverse() {
+        let genres = ["rock", "jazz", "techno", "folk", "hip-hop", "metal", "ambient", "soul", "punk", "house",
+            "blues", "country", "reggae", "classical", "disco", "grime", "emo", "funk", "drone", "trance"];
+        let artists: Vec<_> = genres.iter().map(|g| artist(100, 500_000, &[(g, 100)])).collect();
+        let dna = music_dna(&artists);
+        assert!((dna.diversity - 1.0).abs() < 1e-9);
+        assert_eq!(dna.genres.len(), TOP_GENRES);
+        assert!(dna.label.unwrap().starts_with("Eclectic"));
+    }
+
+    #[test]
+    fn test_no_artists() {
+        let dna = music_dna(&[]);
+        assert!(dna.label.is_none());
+        assert!(dna.genres.is_empty() && dna.eras.is_empty());
+        assert_eq!(dna.diversity, 0.0);
+    }
+
+    #[test]
+    fn test_public_profile_hides_details() {
+        let dna = music_dna(&[artist(500, 200_000, &[("shoegaze", 100), ("dream pop", 50), ("90s", 80)])]);
+        let public = PublicMusicDna::from(dna.clone());
+        assert_eq!(public.label, dna.label);
+        assert_eq!(public.top_genres, vec!["shoegaze", "dream pop"]);
+        assert_eq!(public.eras, vec!["1990s"]);
+        assert_eq!(public.mainstream_index, (dna.mainstream_index * 10.0).round() / 10.0);
+    }
+}
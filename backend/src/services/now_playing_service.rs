@@ -0,0 +1,212 @@
+use crate::{
+    config::Config,
+    db::DbPool,
+    errors::AppError,
+    models::{MatchNowPlaying, NowPlayingTrack},
+    services::{websocket_service::WsMessageType, LastFmService, WebSocketService},
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Users polled per worker tick, least recently polled first. Each poll is one Last.fm request
+/// through the shared rate limiter, so this bounds how much of it the poller can take.
+const NOW_PLAYING_BATCH_SIZE: i64 = 50;
+/// Polls older than this are treated as nothing playing, since users stop being polled once
+/// none of their matches are online
+const NOW_PLAYING_MAX_AGE_SECS: i64 = 10 * 60;
+
+#[derive(sqlx::FromRow)]
+struct PollUser {
+    id: String,
+    lastfm_username: String,
+}
+
+/// Polls what connected users are scrobbling and pushes changes to their matches over the WebSocket
+pub struct NowPlayingService {
+    lastfm_service: Arc<LastFmService>,
+    websocket_service: Arc<WebSocketService>,
+    poll_interval: Duration,
+}
+
+impl NowPlayingService {
+    pub fn new(
+        config: &Config,
+        lastfm_service: Arc<LastFmService>,
+        websocket_service: Arc<WebSocketService>,
+    ) -> Self {
+        Self {
+            lastfm_service,
+            websocket_service,
+            poll_interval: Duration::from_secs(config.now_playing_poll_interval_secs),
+        }
+    }
+
+    /// Poll one user's now-playing track, store it and tell their matches if it changed
+    /// Returns whether it changed.
+    pub async fn poll_user(&self, pool: &DbPool, user_id: &str, lastfm_username: &str) -> Result<bool, AppError> {
+        let track = self.lastfm_service.fetch_now_playing(lastfm_username).await?;
+        let previous = Self::stored_track(pool, user_id).await?;
+
+        // Selecting from users skips the write for anyone who hid their now playing mid-poll
+        let stored = sqlx::query(
+            "INSERT INTO now_playing (user_id, artist_name, artist_mbid, track_name, album_name, polled_at)
+             SELECT id, ?, ?, ?, ?, NOW() FROM users WHERE id = ? AND hide_now_playing = FALSE
+             ON DUPLICATE KEY UPDATE artist_name = VALUES(artist_name), artist_mbid = VALUES(artist_mbid),
+                track_name = VALUES(track_name), album_name = VALUES(album_name), polled_at = NOW()",
+        )
+        .bind(track.as_ref().map(|t| &t.artist_name))
+        .bind(track.as_ref().and_then(|t| t.artist_mbid.as_ref()))
+        .bind(track.as_ref().map(|t| &t.track_name))
+        .bind(track.as_ref().and_then(|t| t.album_name.as_ref()))
+        .bind(user_id)
+        .execute(pool)
+        .await?
+        .rows_affected()
+            > 0;
+
+        if !stored || track == previous {
+            return Ok(false);
+        }
+
+        self.notify_matches(pool, user_id, track).await?;
+        Ok(true)
+    }
+
+    /// Forget a user's now-playing track, e.g. when they hide it, and tell their matches it's gone
+    pub async fn clear(&self, pool: &DbPool, user_id: &str) -> Result<(), AppError> {
+        let previous = Self::stored_track(pool, user_id).await?;
+
+        sqlx::query(
+            "UPDATE now_playing SET artist_name = NULL, artist_mbid = NULL, track_name = NULL, album_name = NULL
+             WHERE user_id = ?",
+        )
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+        if previous.is_some() {
+            self.notify_matches(pool, user_id, None).await?;
+        }
+
+        Ok(())
+    }
+
+    /// What the user's matches are playing right now, leaving out anyone who hides it
+    pub async fn get_for_matches(pool: &DbPool, user_id: &str) -> Result<Vec<MatchNowPlaying>, AppError> {
+        let playing = sqlx::query_as::<_, MatchNowPlaying>(
+            "SELECT np.user_id, np.artist_name, np.artist_mbid, np.track_name, np.album_name
+             FROM matches m
+             JOIN now_playing np ON np.user_id = IF(m.user1_id = ?, m.user2_id, m.user1_id)
+             JOIN users u ON u.id = np.user_id
+             WHERE (m.user1_id = ? OR m.user2_id = ?)
+             AND np.track_name IS NOT NULL
+             AND u.hide_now_playing = FALSE
+             AND np.polled_at > DATE_SUB(NOW(), INTERVAL ? SECOND)",
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .bind(NOW_PLAYING_MAX_AGE_SECS)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(playing)
+    }
+
+    /// Poll the next batch of users worth polling: connected to Last.fm, not hiding their now
+    /// playing, and with at least one match online to see it
+    /// Returns the number of users whose track changed.
+    pub async fn poll_users(&self, pool: &DbPool) -> Result<usize, AppError> {
+        let users = sqlx::query_as::<_, PollUser>(
+            "SELECT u.id, u.lastfm_username
+             FROM users u
+             LEFT JOIN now_playing np ON np.user_id = u.id
+             WHERE u.lastfm_username IS NOT NULL
+             AND u.hide_now_playing = FALSE
+             AND EXISTS (
+                 SELECT 1 FROM matches m
+                 JOIN user_presence p ON p.user_id = IF(m.user1_id = u.id, m.user2_id, m.user1_id)
+                 WHERE (m.user1_id = u.id OR m.user2_id = u.id) AND p.status = 'online'
+             )
+             ORDER BY np.polled_at IS NOT NULL, np.polled_at ASC
+             LIMIT ?",
+        )
+        .bind(NOW_PLAYING_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+
+        let mut changed = 0;
+        for user in users {
+            match self.poll_user(pool, &user.id, &user.lastfm_username).await {
+                Ok(true) => changed += 1,
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Failed to poll now playing for user {}: {}", user.id, e),
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// Spawn the polling loop. Returns `None` when disabled by configuration.
+    pub fn spawn_worker(self: Arc<Self>, pool: DbPool) -> Option<JoinHandle<()>> {
+        if self.poll_interval.is_zero() {
+            tracing::info!("Now playing polling disabled");
+            return None;
+        }
+
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.poll_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+
+                if let Err(e) = self.poll_users(&pool).await {
+                    tracing::error!("Now playing polling failed: {}", e);
+                }
+            }
+        }))
+    }
+
+    async fn stored_track(pool: &DbPool, user_id: &str) -> Result<Option<NowPlayingTrack>, AppError> {
+        let track = sqlx::query_as::<_, NowPlayingTrack>(
+            "SELECT artist_name, artist_mbid, track_name, album_name
+             FROM now_playing WHERE user_id = ? AND track_name IS NOT NULL",
+        )
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(track)
+    }
+
+    /// Push a user's track to every match that's connected
+    async fn notify_matches(
+        &self,
+        pool: &DbPool,
+        user_id: &str,
+        track: Option<NowPlayingTrack>,
+    ) -> Result<(), AppError> {
+        let match_user_ids: Vec<String> = sqlx::query_scalar(
+            "SELECT IF(user1_id = ?, user2_id, user1_id) FROM matches WHERE user1_id = ? OR user2_id = ?",
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        for match_user_id in match_user_ids {
+            let message = WsMessageType::NowPlaying {
+                user_id: user_id.to_string(),
+                track: track.clone(),
+            };
+            if let Err(e) = self.websocket_service.send_to_user(&match_user_id, message).await {
+                tracing::warn!("Failed to push now playing to user {}: {}", match_user_id, e);
+            }
+        }
+
+        Ok(())
+    }
+}
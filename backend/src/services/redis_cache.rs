@@ -0,0 +1,114 @@
+use crate::{errors::AppError, services::cache_service::Cache};
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use std::time::Duration;
+
+/// Keys Redis examines per SCAN call when deleting by pattern
+const SCAN_BATCH_SIZE: usize = 1000;
+
+/// Redis cache backend, shared by every app instance
+#[derive(Clone)]
+pub struct RedisCache {
+    client: ConnectionManager,
+}
+
+impl RedisCache {
+    pub async fn new(redis_url: &str) -> Result<Self, AppError> {
+        let client = redis::Client::open(redis_url)
+            .map_err(|e| AppError::Internal(format!("Failed to connect to Redis: {}", e)))?;
+        
+        let connection_manager = ConnectionManager::new(client)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to create Redis connection manager: {}", e)))?;
+
+        Ok(Self {
+            client: connection_manager,
+        })
+    }
+}
+
+#[async_trait]
+impl Cache for RedisCache {
+    async fn get(&self, key: &str) -> Result<Option<String>, AppError> {
+        let mut conn = self.client.clone();
+        conn.get(key)
+            .await
+            .map_err(|e| AppError::Internal(format!("Redis get error: {}", e)))
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Duration) -> Result<(), AppError> {
+        let mut conn = self.client.clone();
+        conn.set_ex::<_, _, ()>(key, value, ttl.as_secs())
+            .await
+            .map_err(|e| AppError::Internal(format!("Redis set error: {}", e)))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        let mut conn = self.client.clone();
+        conn.del::<_, ()>(key)
+            .await
+            .map_err(|e| AppError::Internal(format!("Redis delete error: {}", e)))
+    }
+
+    async fn delete_pattern(&self, pattern: &str) -> Result<(), AppError> {
+        let mut conn = self.client.clone();
+
+        // SCAN walks the keyspace a batch at a time instead of blocking Redis like KEYS does
+        let mut cursor: u64 = 0;
+        loop {
+            let (next_cursor, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(pattern)
+                .arg("COUNT")
+                .arg(SCAN_BATCH_SIZE)
+                .query_async(&mut conn)
+                .await
+                .map_err(|e| AppError::Internal(format!("Redis scan error: {}", e)))?;
+
+            if !keys.is_empty() {
+                conn.del::<_, ()>(keys)
+                    .await
+                    .map_err(|e| AppError::Internal(format!("Redis delete pattern error: {}", e)))?;
+            }
+
+            if next_cursor == 0 {
+                return Ok(());
+            }
+            cursor = next_cursor;
+        }
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, AppError> {
+        let mut conn = self.client.clone();
+        conn.exists(key)
+            .await
+            .map_err(|e| AppError::Internal(format!("Redis exists error: {}", e)))
+    }
+
+    async fn increment(&self, key: &str, ttl: Duration) -> Result<i64, AppError> {
+        let mut conn = self.client.clone();
+        
+        // Increment the counter
+        let value: i64 = conn
+            .incr(key, 1)
+            .await
+            .map_err(|e| AppError::Internal(format!("Redis incr error: {}", e)))?;
+
+        // Set TTL if this is the first increment
+        if value == 1 {
+            conn.expire::<_, ()>(key, ttl.as_secs() as i64)
+                .await
+                .map_err(|e| AppError::Internal(format!("Redis expire error: {}", e)))?;
+        }
+
+        Ok(value)
+    }
+
+    async fn ttl(&self, key: &str) -> Result<i64, AppError> {
+        let mut conn = self.client.clone();
+        conn.ttl(key)
+            .await
+            .map_err(|e| AppError::Internal(format!("Redis ttl error: {}", e)))
+    }
+}
@@ -0,0 +1,85 @@
+use crate::errors::AppError;
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256};
+
+const NONCE_LEN: usize = 12;
+
+/// Encrypts third-party session keys (e.g. Last.fm) before they are stored
+/// Uses AES-256-GCM with a random nonce per value; output is base64(nonce || ciphertext)
+#[derive(Clone)]
+pub struct SessionKeyCipher {
+    cipher: Aes256Gcm,
+}
+
+impl SessionKeyCipher {
+    /// Derive the AES key from an arbitrary-length configured secret
+    pub fn new(secret: &str) -> Self {
+        let key = Sha256::digest(secret.as_bytes());
+        Self {
+            cipher: Aes256Gcm::new(&key),
+        }
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, AppError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|e| AppError::Internal(format!("Failed to encrypt session key: {}", e)))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+        Ok(STANDARD.encode(payload))
+    }
+
+    pub fn decrypt(&self, encoded: &str) -> Result<String, AppError> {
+        let payload = STANDARD
+            .decode(encoded)
+            .map_err(|e| AppError::Internal(format!("Invalid encrypted session key: {}", e)))?;
+
+        if payload.len() <= NONCE_LEN {
+            return Err(AppError::Internal("Invalid encrypted session key".to_string()));
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|e| AppError::Internal(format!("Failed to decrypt session key: {}", e)))?;
+
+        String::from_utf8(plaintext)
+            .map_err(|e| AppError::Internal(format!("Invalid decrypted session key: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let cipher = SessionKeyCipher::new("test-secret");
+        let encrypted = cipher.encrypt("d580d57f32848f5dcf574d1ce18d78b2").unwrap();
+
+        assert_ne!(encrypted, "d580d57f32848f5dcf574d1ce18d78b2");
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), "d580d57f32848f5dcf574d1ce18d78b2");
+    }
+
+    #[test]
+    fn test_encrypt_uses_fresh_nonce() {
+        let cipher = SessionKeyCipher::new("test-secret");
+        let a = cipher.encrypt("same").unwrap();
+        let b = cipher.encrypt("same").unwrap();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_decrypt_with_wrong_secret_fails() {
+        let encrypted = SessionKeyCipher::new("secret-a").encrypt("key").unwrap();
+        assert!(SessionKeyCipher::new("secret-b").decrypt(&encrypted).is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{
+    config::Config,
+    db::DbPool,
+    errors::AppError,
+    models::SyncStatus,
+    services::{
+        lastfm_service::{LastFmService, SyncResult},
+        listening_provider::{ListeningAccount, ListeningProvider, LISTENBRAINZ_PROVIDER},
+        AchievementService, ArtistSimilarityService, ArtistTagService, DiscoverService, ListenBrainzService,
+        MusicDnaService,
+    },
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Users re-synced per worker tick; the rest are picked up on the next tick
+const RESYNC_BATCH_SIZE: i64 = 25;
+
+#[derive(sqlx::FromRow)]
+struct StaleUser {
+    id: String,
+    #[sqlx(flatten)]
+    account: ListeningAccount,
+}
+
+/// Listening data sync orchestration: syncs each user from their chosen provider, records per-user
+/// sync status and re-syncs stale users in the background
+pub struct SyncService {
+    lastfm_service: Arc<LastFmService>,
+    listenbrainz_service: Arc<ListenBrainzService>,
+    artist_tag_service: Arc<ArtistTagService>,
+    artist_similarity_service: Arc<ArtistSimilarityService>,
+    discover_service: Arc<DiscoverService>,
+    music_dna_service: Arc<MusicDnaService>,
+    resync_interval: Duration,
+    stale_after: Duration,
+}
+
+impl SyncService {
+    pub fn new(
+        config: &Config,
+        lastfm_service: Arc<LastFmService>,
+        listenbrainz_service: Arc<ListenBrainzService>,
+        artist_tag_service: Arc<ArtistTagService>,
+        artist_similarity_service: Arc<ArtistSimilarityService>,
+        discover_service: Arc<DiscoverService>,
+        music_dna_service: Arc<MusicDnaService>,
+    ) -> Self {
+        Self {
+            lastfm_service,
+            listenbrainz_service,
+            artist_tag_service,
+            artist_similarity_service,
+            discover_service,
+            music_dna_service,
+            resync_interval: Duration::from_secs(config.lastfm_resync_interval_secs),
+            stale_after: Duration::from_secs(config.lastfm_resync_stale_after_secs),
+        }
+    }
+
+    fn provider(&self, name: &str) -> &dyn ListeningProvider {
+        match name {
+            LISTENBRAINZ_PROVIDER => self.listenbrainz_service.as_ref(),
+            _ => self.lastfm_service.as_ref(),
+        }
+    }
+
+    /// Sync a user's listening data from their provider and record the outcome in `lastfm_sync_status`
+    /// All periods are fetched before anything is written, so a failed sync leaves the old data intact
+    pub async fn sync_user(
+        &self,
+        pool: &DbPool,
+        user_id: &str,
+        account: &ListeningAccount,
+    ) -> Result<SyncResult, AppError> {
+        sqlx::query(
+            "INSERT INTO lastfm_sync_status (user_id, status, last_attempt_at) VALUES (?, 'syncing', NOW())
+             ON DUPLICATE KEY UPDATE status = 'syncing', last_attempt_at = NOW()",
+        )
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+        let provider = self.provider(&account.provider);
+        let result = match provider.fetch_listening_data(&account.username).await {
+            Ok(data) => self
+                .lastfm_service
+                .store_listening_data(pool, user_id, &data, provider.name())
+                .await
+                .map(|_| data),
+            Err(e) => Err(e),
+        };
+
+        match &result {
+            Ok(_) => {
+                sqlx::query(
+                    "UPDATE lastfm_sync_status
+                     SET status = 'ok', last_synced_at = NOW(), last_error = NULL, consecutive_failures = 0
+                     WHERE user_id = ?",
+                )
+                .bind(user_id)
+                .execute(pool)
+                .await?;
+            }
+            Err(e) => {
+                // Shown in the UI, so keep Last.fm's raw error details out of it
+                let message = match e {
+                    AppError::LastFm(e) => e.user_message(),
+                    e => e.to_string(),
+                };
+                sqlx::query(
+                    "UPDATE lastfm_sync_status
+                     SET status = 'error', last_error = ?, consecutive_failures = consecutive_failures + 1
+                     WHERE user_id = ?",
+                )
+                .bind(message)
+                .bind(user_id)
+                .execute(pool)
+                .await?;
+            }
+        }
+
+        result
+    }
+
+    /// Work that depends on fresh scrobbles but isn't needed to answer the sync request:
+    /// artist listener count, tag and similar-artist lookups, the discover feed, music achievements
+    /// and the music DNA profile. Failures are logged, not returned. Similar artists feed into
+    /// scores from the next rebuild on.
+    pub async fn after_sync(&self, pool: &DbPool, user_id: &str) {
+        // Listener counts weight niche artists up in scoring, so they go before the feed rebuild
+        if let Err(e) = self.artist_tag_service.refresh_user_artist_listeners(pool, user_id).await {
+            tracing::warn!("Failed to refresh artist listeners for user {}: {}", user_id, e);
+        }
+        if let Err(e) = self.discover_service.rebuild_feed(pool, user_id).await {
+            tracing::warn!("Failed to rebuild discover feed for user {}: {}", user_id, e);
+        }
+        // Genre achievements need the tags
+        match self.artist_tag_service.refresh_user_artist_tags(pool, user_id).await {
+            Ok(_) => {
+                if let Err(e) = AchievementService::on_scrobbles_synced(pool, user_id).await {
+                    tracing::warn!("Failed to check achievements for user {}: {}", user_id, e);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to refresh artist tags for user {}: {}", user_id, e),
+        }
+        // Genres need the tags too, but a profile from the tags we have beats none
+        if let Err(e) = self.music_dna_service.refresh(pool, user_id).await {
+            tracing::warn!("Failed to refresh music DNA for user {}: {}", user_id, e);
+        }
+        if let Err(e) = self.artist_similarity_service.refresh_user_similar_artists(pool, user_id).await {
+            tracing::warn!("Failed to refresh similar artists for user {}: {}", user_id, e);
+        }
+    }
+
+    pub async fn get_status(pool: &DbPool, user_id: &str) -> Result<Option<SyncStatus>, AppError> {
+        let status = sqlx::query_as::<_, SyncStatus>(
+            "SELECT user_id, status, last_synced_at, last_attempt_at, last_error, consecutive_failures
+             FROM lastfm_sync_status WHERE user_id = ?",
+        )
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(status)
+    }
+
+    /// Re-sync connected users whose cached listening data are older than the stale threshold
+    /// Users that were attempted recently (including failed attempts) are skipped until the
+    /// threshold passes again, so a broken account isn't retried every tick.
+    /// Returns the number of users synced successfully.
+    pub async fn resync_stale_users(&self, pool: &DbPool) -> Result<usize, AppError> {
+        let stale_after_secs = self.stale_after.as_secs() as i64;
+
+        let users = sqlx::query_as::<_, StaleUser>(
+            "SELECT u.id, u.listening_provider AS provider,
+                IF(u.listening_provider = 'listenbrainz', u.listenbrainz_username, u.lastfm_username) AS username
+             FROM users u
+             LEFT JOIN (
+                 SELECT user_id, MAX(last_synced_at) AS synced_at FROM scrobbles_cache GROUP BY user_id
+             ) sc ON sc.user_id = u.id
+             LEFT JOIN lastfm_sync_status s ON s.user_id = u.id
+             WHERE IF(u.listening_provider = 'listenbrainz', u.listenbrainz_username, u.lastfm_username) IS NOT NULL
+             AND (sc.synced_at IS NULL OR sc.synced_at < DATE_SUB(NOW(), INTERVAL ? SECOND))
+             AND (s.last_attempt_at IS NULL OR s.last_attempt_at < DATE_SUB(NOW(), INTERVAL ? SECOND))
+             ORDER BY sc.synced_at IS NOT NULL, sc.synced_at ASC
+             LIMIT ?",
+        )
+        .bind(stale_after_secs)
+        .bind(stale_after_secs)
+        .bind(RESYNC_BATCH_SIZE)
+        .fetch_all(pool)
+        .await?;
+
+        let mut synced = 0;
+        for user in users {
+            match self.sync_user(pool, &user.id, &user.account).await {
+                Ok(_) => {
+                    self.after_sync(pool, &user.id).await;
+                    synced += 1;
+                }
+                Err(e) => {
+                    tracing::warn!("Background {} sync failed for user {}: {}", user.account.provider, user.id, e);
+                }
+            }
+        }
+
+        Ok(synced)
+    }
+
+    /// Spawn the background re-sync loop. Returns `None` when disabled by configuration.
+    pub fn spawn_worker(self: Arc<Self>, pool: DbPool) -> Option<JoinHandle<()>> {
+        if self.resync_interval.is_zero() {
+            tracing::info!("Background Last.fm re-sync disabled");
+            return None;
+        }
+
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.resync_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+
+                match self.resync_stale_users(&pool).await {
+                    Ok(0) => {}
+                    Ok(count) => tracing::info!("Background Last.fm re-sync refreshed {} users", count),
+                    Err(e) => tracing::error!("Background Last.fm re-sync failed: {}", e),
+                }
+            }
+        }))
+    }
+}
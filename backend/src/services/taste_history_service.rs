@@ -0,0 +1,404 @@
+//! Taste history: a snapshot of the user's one-month top artists at every sync
+//! `scrobbles_cache` only holds the latest charts, so snapshots are what show how taste shifts
+//! month over month and which artists someone is getting into or drifting away from.
+
+use crate::{
+    db::DbPool,
+    errors::AppError,
+    models::Artist,
+    services::artist_tag_service::GenreWeight,
+};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::MySqlConnection;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Artists and genres listed per month of the timeline
+const TIMELINE_TOP_ARTISTS: usize = 10;
+const TIMELINE_TOP_GENRES: usize = 5;
+/// Snapshots are compared with the latest one taken at least this long before
+const TREND_WINDOW_DAYS: i64 = 30;
+/// Only artists this high in either chart can be rising or fading
+const TREND_TOP_RANK: i32 = 20;
+/// How many places an artist has to move to count as rising or fading
+const TREND_RANK_SHIFT: i32 = 10;
+const MAX_TRENDS: usize = 5;
+
+#[derive(sqlx::FromRow)]
+struct Snapshot {
+    id: String,
+    taken_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct SnapshotArtist {
+    pub artist_key: String,
+    pub artist_name: String,
+    /// 1-based position in the one-month chart
+    pub artist_rank: i32,
+    pub play_count: i32,
+}
+
+/// A user's taste in one month, as of the last sync that month
+#[derive(Debug, Clone, Serialize)]
+pub struct TasteMonth {
+    /// e.g. "2026-09"
+    pub month: String,
+    pub taken_at: NaiveDateTime,
+    pub top_artists: Vec<String>,
+    pub top_genres: Vec<GenreWeight>,
+}
+
+/// An artist that moved in the user's chart; a missing rank means outside the top 50
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ArtistTrend {
+    pub name: String,
+    pub previous_rank: Option<i32>,
+    pub current_rank: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TasteTimeline {
+    /// Oldest month first
+    pub months: Vec<TasteMonth>,
+    /// Compared with about a month ago; empty until there is a snapshot that old
+    pub rising: Vec<ArtistTrend>,
+    pub fading: Vec<ArtistTrend>,
+}
+
+pub struct TasteHistoryService;
+
+impl TasteHistoryService {
+    /// Record the user's current one-month chart, replacing any snapshot already taken today
+    /// Runs inside the sync transaction, so a snapshot always matches `scrobbles_cache`.
+    pub async fn record_snapshot(conn: &mut MySqlConnection, user_id: &str, artists: &[Artist]) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM taste_snapshots WHERE user_id = ? AND taken_at >= CURDATE()")
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+
+        if artists.is_empty() {
+            return Ok(());
+        }
+
+        let snapshot_id = Uuid::new_v4().to_string();
+        sqlx::query("INSERT INTO taste_snapshots (id, user_id) VALUES (?, ?)")
+            .bind(&snapshot_id)
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+
+        for (i, artist) in artists.iter().enumerate() {
+            // Two spellings of one artist share a key; the higher-ranked one is kept
+            sqlx::query(
+                "INSERT IGNORE INTO taste_snapshot_artists (snapshot_id, artist_key, artist_name, artist_rank, play_count)
+                 VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(&snapshot_id)
+            .bind(artist.key())
+            .bind(&artist.name)
+            .bind(i as i32 + 1)
+            .bind(artist.play_count)
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Month-by-month top artists and genres for the last `months` months, plus rising and
+    /// fading artists
+    pub async fn get_timeline(pool: &DbPool, user_id: &str, months: u32) -> Result<TasteTimeline, AppError> {
+        let snapshots = sqlx::query_as::<_, Snapshot>(
+            "SELECT id, taken_at FROM taste_snapshots
+             WHERE user_id = ? AND taken_at >= DATE_SUB(NOW(), INTERVAL ? MONTH)
+             ORDER BY taken_at ASC",
+        )
+        .bind(user_id)
+        .bind(months)
+        .fetch_all(pool)
+        .await?;
+
+        // The last snapshot of each month stands for the month
+        let mut monthly: Vec<Snapshot> = Vec::new();
+        for snapshot in snapshots {
+            match monthly.last_mut() {
+                Some(last) if month_of(last.taken_at) == month_of(snapshot.taken_at) => *last = snapshot,
+                _ => monthly.push(snapshot),
+            }
+        }
+
+        let snapshot_ids: Vec<String> = monthly.iter().map(|s| s.id.clone()).collect();
+        let mut artists = Self::load_snapshot_artists(pool, &snapshot_ids).await?;
+        let artist_keys: Vec<String> = artists.values().flatten().map(|a| a.artist_key.clone()).collect();
+        let tags = Self::load_tags(pool, &artist_keys).await?;
+
+        let months = monthly
+            .into_iter()
+            .map(|snapshot| {
+                let artists = artists.remove(&snapshot.id).unwrap_or_default();
+                TasteMonth {
+                    month: month_of(snapshot.taken_at),
+                    taken_at: snapshot.taken_at,
+                    top_genres: genre_weights(&artists, &tags, TIMELINE_TOP_GENRES),
+                    top_artists: artists
+                        .into_iter()
+                        .take(TIMELINE_TOP_ARTISTS)
+                        .map(|a| a.artist_name)
+                        .collect(),
+                }
+            })
+            .collect();
+
+        let (rising, fading) = match Self::load_trend_snapshots(pool, user_id).await? {
+            Some((previous, current)) => artist_trends(&previous, &current),
+            None => (vec![], vec![]),
+        };
+
+        Ok(TasteTimeline { months, rising, fading })
+    }
+
+    /// The highest-ranked artist the user started listening to in the last month, for
+    /// "recently got into X" on discover cards
+    pub async fn recently_got_into(pool: &DbPool, user_id: &str) -> Result<Option<String>, AppError> {
+        let Some((previous, current)) = Self::load_trend_snapshots(pool, user_id).await? else {
+            return Ok(None);
+        };
+
+        let (rising, _) = artist_trends(&previous, &current);
+        Ok(rising
+            .into_iter()
+            .find(|trend| trend.previous_rank.is_none())
+            .map(|trend| trend.name))
+    }
+
+    /// Artists of the user's snapshot from about a month ago and of their latest one
+    async fn load_trend_snapshots(
+        pool: &DbPool,
+        user_id: &str,
+    ) -> Result<Option<(Vec<SnapshotArtist>, Vec<SnapshotArtist>)>, AppError> {
+        let latest = sqlx::query_as::<_, Snapshot>(
+            "SELECT id, taken_at FROM taste_snapshots
+             WHERE user_id = ?
+             ORDER BY taken_at DESC
+             LIMIT 1",
+        )
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+        let Some(latest) = latest else {
+            return Ok(None);
+        };
+
+        let previous = sqlx::query_as::<_, Snapshot>(
+            "SELECT id, taken_at FROM taste_snapshots
+             WHERE user_id = ? AND taken_at <= DATE_SUB(?, INTERVAL ? DAY)
+             ORDER BY taken_at DESC
+             LIMIT 1",
+        )
+        .bind(user_id)
+        .bind(latest.taken_at)
+        .bind(TREND_WINDOW_DAYS)
+        .fetch_optional(pool)
+        .await?;
+        let Some(previous) = previous else {
+            return Ok(None);
+        };
+
+        let mut artists = Self::load_snapshot_artists(pool, &[previous.id.clone(), latest.id.clone()]).await?;
+        Ok(Some((
+            artists.remove(&previous.id).unwrap_or_default(),
+            artists.remove(&latest.id).unwrap_or_default(),
+        )))
+    }
+
+    /// Artists per snapshot, in chart order
+    async fn load_snapshot_artists(
+        pool: &DbPool,
+        snapshot_ids: &[String],
+    ) -> Result<HashMap<String, Vec<SnapshotArtist>>, AppError> {
+        let mut artists: HashMap<String, Vec<SnapshotArtist>> = HashMap::new();
+        if snapshot_ids.is_empty() {
+            return Ok(artists);
+        }
+
+        let sql = format!(
+            "SELECT snapshot_id, artist_key, artist_name, artist_rank, play_count
+             FROM taste_snapshot_artists
+             WHERE snapshot_id IN ({})
+             ORDER BY artist_rank ASC",
+            vec!["?"; snapshot_ids.len()].join(", ")
+        );
+        let mut query = sqlx::query_as::<_, (String, String, String, i32, i32)>(&sql);
+        for id in snapshot_ids {
+            query = query.bind(id);
+        }
+
+        for (snapshot_id, artist_key, artist_name, artist_rank, play_count) in query.fetch_all(pool).await? {
+            artists.entry(snapshot_id).or_default().push(SnapshotArtist {
+                artist_key,
+                artist_name,
+                artist_rank,
+                play_count,
+            });
+        }
+
+        Ok(artists)
+    }
+
+    /// Current tags of the given artists, by artist key
+    async fn load_tags(pool: &DbPool, artist_keys: &[String]) -> Result<HashMap<String, Vec<(String, i32)>>, AppError> {
+        let mut tags: HashMap<String, Vec<(String, i32)>> = HashMap::new();
+        if artist_keys.is_empty() {
+            return Ok(tags);
+        }
+
+        let sql = format!(
+            "SELECT artist_key, tag, weight FROM artist_tags WHERE artist_key IN ({})",
+            vec!["?"; artist_keys.len()].join(", ")
+        );
+        let mut query = sqlx::query_as::<_, (String, String, i32)>(&sql);
+        for key in artist_keys {
+            query = query.bind(key);
+        }
+
+        for (artist_key, tag, weight) in query.fetch_all(pool).await? {
+            tags.entry(artist_key).or_default().push((tag, weight));
+        }
+
+        Ok(tags)
+    }
+}
+
+fn month_of(taken_at: NaiveDateTime) -> String {
+    taken_at.format("%Y-%m").to_string()
+}
+
+/// Genre shares of a snapshot, weighted like `ArtistTagService::get_user_genres`
+fn genre_weights(
+    artists: &[SnapshotArtist],
+    tags: &HashMap<String, Vec<(String, i32)>>,
+    limit: usize,
+) -> Vec<GenreWeight> {
+    let mut scores: HashMap<&str, f64> = HashMap::new();
+    for artist in artists {
+        let plays = (artist.play_count.max(0) as f64 + 2.0).ln();
+        for (tag, weight) in tags.get(&artist.artist_key).into_iter().flatten() {
+            *scores.entry(tag.as_str()).or_insert(0.0) += plays * *weight as f64;
+        }
+    }
+
+    let total: f64 = scores.values().sum();
+    let mut genres: Vec<GenreWeight> = scores
+        .into_iter()
+        .map(|(genre, score)| GenreWeight {
+            genre: genre.to_string(),
+            weight: score / total,
+        })
+        .collect();
+    genres.sort_by(|a, b| b.weight.total_cmp(&a.weight).then_with(|| a.genre.cmp(&b.genre)));
+    genres.truncate(limit);
+    genres
+}
+
+/// Rising artists (new in, or well up the top of the current chart) and fading ones (gone from,
+/// or well down from the top of the previous chart)
+pub fn artist_trends(previous: &[SnapshotArtist], current: &[SnapshotArtist]) -> (Vec<ArtistTrend>, Vec<ArtistTrend>) {
+    let previous_ranks: HashMap<&str, i32> = previous.iter().map(|a| (a.artist_key.as_str(), a.artist_rank)).collect();
+    let current_ranks: HashMap<&str, i32> = current.iter().map(|a| (a.artist_key.as_str(), a.artist_rank)).collect();
+
+    let mut rising: Vec<ArtistTrend> = current
+        .iter()
+        .filter(|a| a.artist_rank <= TREND_TOP_RANK)
+        .filter_map(|a| {
+            let previous_rank = previous_ranks.get(a.artist_key.as_str()).copied();
+            let moved_up = previous_rank.is_none_or(|rank| rank - a.artist_rank >= TREND_RANK_SHIFT);
+            moved_up.then(|| ArtistTrend {
+                name: a.artist_name.clone(),
+                previous_rank,
+                current_rank: Some(a.artist_rank),
+            })
+        })
+        .collect();
+    rising.sort_by_key(|t| t.current_rank);
+    rising.truncate(MAX_TRENDS);
+
+    let mut fading: Vec<ArtistTrend> = previous
+        .iter()
+        .filter(|a| a.artist_rank <= TREND_TOP_RANK)
+        .filter_map(|a| {
+            let current_rank = current_ranks.get(a.artist_key.as_str()).copied();
+            let moved_down = current_rank.is_none_or(|rank| rank - a.artist_rank >= TREND_RANK_SHIFT);
+            moved_down.then(|| ArtistTrend {
+                name: a.artist_name.clone(),
+                previous_rank: Some(a.artist_rank),
+                current_rank,
+            })
+        })
+        .collect();
+    fading.sort_by_key(|t| t.previous_rank);
+    fading.truncate(MAX_TRENDS);
+
+    (rising, fading)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chart(names: &[&str]) -> Vec<SnapshotArtist> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| SnapshotArtist {
+                artist_key: format!("name:{}", name.to_lowercase()),
+                artist_name: name.to_string(),
+                artist_rank: i as i32 + 1,
+                play_count: 100 - i as i32,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_new_and_climbing_artists_are_rising() {
+        let previous = chart(&[
+            "Slowdive", "Ride", "Lush", "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "Grouper",
+        ]);
+        let current = chart(&["Grouper", "Beach House", "Slowdive", "Ride"]);
+        let (rising, fading) = artist_trends(&previous, &current);
+
+        assert_eq!(
+            rising,
+            vec![
+                ArtistTrend { name: "Grouper".to_string(), previous_rank: Some(15), current_rank: Some(1) },
+                ArtistTrend { name: "Beach House".to_string(), previous_rank: None, current_rank: Some(2) },
+            ]
+        );
+        // Lush and everything below dropped out; Slowdive and Ride barely moved
+        assert_eq!(fading[0].name, "Lush");
+        assert_eq!(fading[0].current_rank, None);
+        assert!(fading.iter().all(|t| t.name != "Slowdive" && t.name != "Ride"));
+        assert_eq!(fading.len(), MAX_TRENDS);
+    }
+
+    #[test]
+    fn test_unchanged_charts_have_no_trends() {
+        let names = ["Slowdive", "Ride", "Lush"];
+        let (rising, fading) = artist_trends(&chart(&names), &chart(&names));
+        assert!(rising.is_empty() && fading.is_empty());
+    }
+
+    #[test]
+    fn test_genre_weights() {
+        let artists = chart(&["Slowdive", "Metallica"]);
+        let mut tags = HashMap::new();
+        tags.insert("name:slowdive".to_string(), vec![("shoegaze".to_string(), 100)]);
+        tags.insert("name:metallica".to_string(), vec![("metal".to_string(), 100), ("rock".to_string(), 10)]);
+
+        let genres = genre_weights(&artists, &tags, 2);
+        assert_eq!(genres.len(), 2);
+        assert_eq!(genres[0].genre, "shoegaze");
+        assert!(genres[0].weight > 0.45 && genres[0].weight < 0.5);
+    }
+}
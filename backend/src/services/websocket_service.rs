@@ -1,4 +1,4 @@
-use crate::{db::DbPool, errors::AppError, models::Message};
+use crate::{db::DbPool, errors::AppError, models::{Message, NowPlayingTrack}};
 use axum::extract::ws::{Message as WsMessage, WebSocket};
 use futures_util::{sink::SinkExt, stream::StreamExt};
 use serde::{Deserialize, Serialize};
@@ -30,6 +30,12 @@ pub enum WsMessageType {
         message_id: String,
         match_id: String,
     },
+    /// A match started a new track (`None` when they stopped playing or hid it)
+    #[serde(rename = "now_playing")]
+    NowPlaying {
+        user_id: String,
+        track: Option<NowPlayingTrack>,
+    },
     #[serde(rename = "error")]
     Error { message: String },
     #[serde(rename = "ping")]
@@ -2,8 +2,9 @@ use crate::{
     config::Config,
     db::DbPool,
     services::{
-        AuthService, CacheService, CaptchaService, CompatibilityService, LastFmService,
-        MatchService, NotificationService, PhotoService, WebSocketService,
+        ArtistTagService, AuthService, CacheService, CaptchaService, CompatibilityService,
+        DiscoverService, LastFmService, ListenBrainzService, MatchService, MusicDnaService, NotificationService, NowPlayingService, PhotoService, SyncService,
+        WebSocketService,
     },
 };
 use std::sync::Arc;
@@ -14,11 +15,17 @@ pub struct AppState {
     pub config: Arc<Config>,
     pub auth_service: Arc<AuthService>,
     pub lastfm_service: Arc<LastFmService>,
+    pub listenbrainz_service: Arc<ListenBrainzService>,
+    pub artist_tag_service: Arc<ArtistTagService>,
+    pub sync_service: Arc<SyncService>,
     pub compatibility_service: Arc<CompatibilityService>,
+    pub discover_service: Arc<DiscoverService>,
+    pub music_dna_service: Arc<MusicDnaService>,
     pub match_service: Arc<MatchService>,
     pub photo_service: Arc<PhotoService>,
     pub captcha_service: Arc<CaptchaService>,
     pub cache_service: Arc<CacheService>,
     pub websocket_service: Arc<WebSocketService>,
+    pub now_playing_service: Arc<NowPlayingService>,
     pub notification_service: Arc<NotificationService>,
 }
@@ -0,0 +1,84 @@
+mod common;
+
+use lastfm_dating_backend::{
+    db::DbPool,
+    models::{Artist, Period},
+    services::{lastfm_service::SyncResult, LastFmService},
+};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// One artist in every period, and no tracks
+fn listening_data(name: &str, mbid: Option<&str>) -> SyncResult {
+    let artist = Artist {
+        name: name.to_string(),
+        mbid: mbid.map(str::to_string),
+        play_count: 100,
+        listeners: 50_000,
+    };
+    SyncResult {
+        artists: Period::ALL.into_iter().map(|period| (period, vec![artist.clone()])).collect(),
+        tracks: Period::ALL.into_iter().map(|period| (period, Vec::new())).collect(),
+    }
+}
+
+async fn stored_keys(pool: &DbPool, user_id: &str) -> Vec<String> {
+    sqlx::query_scalar("SELECT DISTINCT artist_key FROM scrobbles_cache WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_name_only_artists_follow_their_mbid_until_it_is_ambiguous() {
+    let Some(pool) = common::test_pool().await else { return };
+    let service = LastFmService::new(common::test_config("http://127.0.0.1:9"));
+    // The name table is shared, so use a name no other test syncs
+    let suffix = Uuid::new_v4().simple().to_string();
+    let name = format!("Artist {}", suffix);
+    let name_key = format!("name:artist {}", suffix);
+    let (first_mbid, second_mbid) = (Uuid::new_v4().to_string(), Uuid::new_v4().to_string());
+
+    let mut users = HashMap::new();
+    for user in ["name_only", "first", "second", "late"] {
+        users.insert(user, common::create_user(&pool, None).await);
+    }
+
+    // Stored by name, then moved once the name turns up with an MBID
+    service
+        .store_listening_data(&pool, &users["name_only"], &listening_data(&name, None), "lastfm")
+        .await
+        .unwrap();
+    assert_eq!(stored_keys(&pool, &users["name_only"]).await, vec![name_key.clone()]);
+    service
+        .store_listening_data(&pool, &users["first"], &listening_data(&name, Some(&first_mbid)), "lastfm")
+        .await
+        .unwrap();
+    assert_eq!(stored_keys(&pool, &users["name_only"]).await, vec![first_mbid.clone()]);
+
+    // A second MBID for the name: rows moved by name go back, rows with an MBID keep theirs
+    service
+        .store_listening_data(&pool, &users["second"], &listening_data(&name, Some(&second_mbid)), "lastfm")
+        .await
+        .unwrap();
+    assert_eq!(stored_keys(&pool, &users["name_only"]).await, vec![name_key.clone()]);
+    assert_eq!(stored_keys(&pool, &users["first"]).await, vec![first_mbid.clone()]);
+    assert_eq!(stored_keys(&pool, &users["second"]).await, vec![second_mbid.clone()]);
+
+    // And later name-only rows stay keyed by name
+    service
+        .store_listening_data(&pool, &users["late"], &listening_data(&name, None), "lastfm")
+        .await
+        .unwrap();
+    assert_eq!(stored_keys(&pool, &users["late"]).await, vec![name_key.clone()]);
+
+    for user_id in users.values() {
+        common::delete_user(&pool, user_id).await;
+    }
+    sqlx::query("DELETE FROM artist_name_mbids WHERE name_key = ?")
+        .bind(&name_key)
+        .execute(&pool)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use common::fake_lastfm::FakeLastFm;
+use lastfm_dating_backend::{
+    models::artist_key,
+    services::{ArtistSimilarityService, LastFmService},
+};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_refresh_user_similar_artists_builds_graph() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let lastfm_service = Arc::new(LastFmService::new(common::test_config(&server.api_url)));
+    let similarity_service = ArtistSimilarityService::new(lastfm_service.clone());
+    let user_id = common::create_user(&pool, Some("rj")).await;
+
+    lastfm_service.sync_user_scrobbles(&pool, &user_id, "rj").await.unwrap();
+    // Artists are shared, so another test may have fetched some of them already
+    let refreshed = similarity_service.refresh_user_similar_artists(&pool, &user_id).await.unwrap();
+    assert_eq!(server.request_count("artist.getsimilar"), refreshed);
+
+    // The fixture lists My Bloody Valentine (0.87) as similar to every artist, Slowdive included
+    let slowdive = artist_key("Slowdive", Some("6a2a3db1-97a0-4d5a-92b9-7a5b8f7a62e5"));
+    let mbv = artist_key("My Bloody Valentine", Some("8f92558c-2baa-4758-8c38-615519e9deda"));
+    let graph = ArtistSimilarityService::load_graph(&pool, &[slowdive.clone(), mbv.clone()])
+        .await
+        .unwrap();
+    assert!((graph.similarity(&mbv, &slowdive) - 0.87).abs() < 1e-6);
+
+    // Similar artists are fresh, so a second refresh does not hit Last.fm again
+    assert_eq!(similarity_service.refresh_user_similar_artists(&pool, &user_id).await.unwrap(), 0);
+    assert_eq!(server.request_count("artist.getsimilar"), refreshed);
+
+    common::delete_user(&pool, &user_id).await;
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use common::fake_lastfm::FakeLastFm;
+use lastfm_dating_backend::{
+    models::Period,
+    services::{ArtistTagService, LastFmService},
+};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_refresh_user_artist_tags_builds_genre_distribution() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let lastfm_service = Arc::new(LastFmService::new(common::test_config(&server.api_url)));
+    let tag_service = ArtistTagService::new(lastfm_service.clone());
+    let user_id = common::create_user(&pool, Some("rj")).await;
+
+    lastfm_service.sync_user_scrobbles(&pool, &user_id, "rj").await.unwrap();
+    let refreshed = tag_service.refresh_user_artist_tags(&pool, &user_id).await.unwrap();
+    assert!(refreshed > 0);
+    assert_eq!(server.request_count("artist.gettoptags"), refreshed);
+
+    let genres = tag_service
+        .get_user_genres(&pool, &user_id, Period::SixMonth, 20)
+        .await
+        .unwrap();
+    let names: Vec<_> = genres.iter().map(|g| g.genre.as_str()).collect();
+    assert!(names.contains(&"shoegaze"));
+    assert!(names.contains(&"alternative"));
+    let total: f64 = genres.iter().map(|g| g.weight).sum();
+    assert!((total - 1.0).abs() < 1e-6);
+
+    assert!(ArtistTagService::count_user_genres(&pool, &user_id).await.unwrap() >= 2);
+
+    // Tags are fresh, so a second refresh does not hit Last.fm again
+    assert_eq!(tag_service.refresh_user_artist_tags(&pool, &user_id).await.unwrap(), 0);
+    assert_eq!(server.request_count("artist.gettoptags"), refreshed);
+
+    common::delete_user(&pool, &user_id).await;
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use common::fake_lastfm::FakeLastFm;
+use lastfm_dating_backend::{
+    models::Period,
+    services::{cache_service::keys, CacheService, CompatibilityService, LastFmService, ListeningProvider},
+};
+use std::sync::Arc;
+
+fn cached_services(api_url: &str) -> (Arc<CacheService>, Arc<LastFmService>, CompatibilityService) {
+    let cache = Arc::new(CacheService::in_memory(1000));
+    let lastfm_service =
+        Arc::new(LastFmService::new(common::test_config(api_url)).with_cache(cache.clone()));
+    let compatibility_service =
+        CompatibilityService::new(lastfm_service.clone()).with_cache(cache.clone());
+    (cache, lastfm_service, compatibility_service)
+}
+
+#[tokio::test]
+async fn test_lastfm_responses_are_cached_between_syncs() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let (_, service, _) = cached_services(&server.api_url);
+    let user_id = common::create_user(&pool, Some("rj")).await;
+
+    service.sync_user_scrobbles(&pool, &user_id, "rj").await.unwrap();
+    service.sync_user_scrobbles(&pool, &user_id, "rj").await.unwrap();
+
+    assert_eq!(server.request_count("user.gettopartists"), Period::ALL.len());
+    assert_eq!(server.request_count("user.gettoptracks"), Period::ALL.len());
+
+    common::delete_user(&pool, &user_id).await;
+}
+
+#[tokio::test]
+async fn test_invalidated_charts_are_fetched_again() {
+    let server = FakeLastFm::spawn().await;
+    let (_, service, _) = cached_services(&server.api_url);
+
+    service.fetch_listening_data("rj").await.unwrap();
+    service.invalidate_cached_charts("RJ").await;
+    service.fetch_listening_data("rj").await.unwrap();
+
+    assert_eq!(server.request_count("user.gettopartists"), 2 * Period::ALL.len());
+    assert_eq!(server.request_count("user.gettoptracks"), 2 * Period::ALL.len());
+}
+
+#[tokio::test]
+async fn test_top_artists_are_read_through_and_invalidated_on_sync() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let (cache, service, _) = cached_services(&server.api_url);
+    let user_id = common::create_user(&pool, Some("rj")).await;
+
+    service.sync_user_scrobbles(&pool, &user_id, "rj").await.unwrap();
+    let key = keys::user_top_artists(&user_id, Period::SixMonth.as_str(), 50);
+    assert!(!cache.exists(&key).await.unwrap());
+
+    let artists = service.get_user_top_artists(&pool, &user_id, Period::SixMonth, 50).await.unwrap();
+    assert_eq!(artists[0].name, "Radiohead");
+    assert!(cache.exists(&key).await.unwrap());
+
+    // Served from the cache even though the database no longer has the rows
+    sqlx::query("DELETE FROM scrobbles_cache WHERE user_id = ?")
+        .bind(&user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let cached = service.get_user_top_artists(&pool, &user_id, Period::SixMonth, 50).await.unwrap();
+    assert_eq!(cached.len(), artists.len());
+
+    service.sync_user_scrobbles(&pool, &user_id, "alice").await.unwrap();
+    assert!(!cache.exists(&key).await.unwrap());
+
+    let artists = service.get_user_top_artists(&pool, &user_id, Period::SixMonth, 50).await.unwrap();
+    assert_eq!(artists[0].name, "Beach House");
+
+    common::delete_user(&pool, &user_id).await;
+}
+
+#[tokio::test]
+async fn test_compatibility_is_cached_and_invalidated_on_sync() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let (cache, service, compatibility_service) = cached_services(&server.api_url);
+    let rj = common::create_user(&pool, Some("rj")).await;
+    let alice = common::create_user(&pool, Some("alice")).await;
+
+    service.sync_user_scrobbles(&pool, &rj, "rj").await.unwrap();
+    service.sync_user_scrobbles(&pool, &alice, "alice").await.unwrap();
+
+    let similarity = compatibility_service.calculate_similarity(&pool, &rj, &alice).await.unwrap();
+    let key = keys::compatibility(&rj, &alice, compatibility_service.algorithm_version());
+    assert_eq!(cache.get::<f64>(&key).await.unwrap(), Some(similarity));
+    let score = compatibility_service.calculate_compatibility(&pool, &rj, &alice).await.unwrap();
+
+    // Either side syncing drops the pair's score
+    service.sync_user_scrobbles(&pool, &alice, "bob").await.unwrap();
+    assert!(!cache.exists(&key).await.unwrap());
+
+    let rescored = compatibility_service.calculate_compatibility(&pool, &alice, &rj).await.unwrap();
+    assert!(rescored < score);
+
+    common::delete_user(&pool, &rj).await;
+    common::delete_user(&pool, &alice).await;
+}
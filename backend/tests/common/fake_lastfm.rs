@@ -0,0 +1,192 @@
+//! Minimal stand-in for ws.audioscrobbler.com used by integration tests
+//!
+//! Read-only methods are answered from `tests/fixtures/lastfm/<method>.json`. A fixture named
+//! `<method>.<user or artist>.json` takes precedence for that user or artist, and
+//! `<method>.<user>.<period>.json` for a single chart period, so tests can give different
+//! listeners different tastes.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use lastfm_dating_backend::services::lastfm_client::api_signature;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::{TEST_API_KEY, TEST_API_SECRET};
+
+/// Token that the fake server treats as authorized by the user
+pub const AUTHORIZED_TOKEN: &str = "authorized-token";
+/// Token that the fake server treats as not yet authorized
+pub const UNAUTHORIZED_TOKEN: &str = "unauthorized-token";
+pub const SESSION_USERNAME: &str = "rj";
+pub const SESSION_KEY: &str = "d580d57f32848f5dcf574d1ce18d78b2";
+
+/// Usernames that trigger Last.fm error responses
+pub const UNKNOWN_USER: &str = "no-such-user";
+pub const PRIVATE_USER: &str = "private-user";
+pub const RATE_LIMITED_USER: &str = "rate-limited-user";
+/// Fails with HTTP 503 twice, then succeeds, for every third request
+pub const FLAKY_USER: &str = "flaky-user";
+/// Answers only after `SLOW_RESPONSE_DELAY`
+pub const SLOW_USER: &str = "slow-user";
+pub const SLOW_RESPONSE_DELAY: Duration = Duration::from_secs(3);
+
+type RequestLog = Arc<Mutex<Vec<HashMap<String, String>>>>;
+
+pub struct FakeLastFm {
+    pub api_url: String,
+    requests: RequestLog,
+}
+
+impl FakeLastFm {
+    /// Start the server on an ephemeral local port
+    pub async fn spawn() -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind fake Last.fm server");
+        let addr = listener.local_addr().unwrap();
+        let requests = RequestLog::default();
+
+        let app = Router::new()
+            .route("/2.0/", get(handle).post(handle))
+            .with_state(requests.clone());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        Self {
+            api_url: format!("http://{}/2.0/", addr),
+            requests,
+        }
+    }
+
+    /// Number of requests received for a given API method
+    pub fn request_count(&self, method: &str) -> usize {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|params| params.get("method").map(String::as_str) == Some(method))
+            .count()
+    }
+}
+
+async fn handle(
+    State(requests): State<RequestLog>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let flaky_attempt = {
+        let mut requests = requests.lock().unwrap();
+        requests.push(params.clone());
+        requests
+            .iter()
+            .filter(|p| p.get("user").map(String::as_str) == Some(FLAKY_USER))
+            .count()
+    };
+
+    if params.get("api_key").map(String::as_str) != Some(TEST_API_KEY) {
+        return error(10, "Invalid API key - You must be granted a valid key by last.fm");
+    }
+
+    let method = params.get("method").cloned().unwrap_or_default();
+
+    if method.starts_with("auth.") && !signature_is_valid(&params) {
+        return error(13, "Invalid method signature supplied");
+    }
+
+    match params.get("user").map(String::as_str) {
+        Some(UNKNOWN_USER) => return error(6, "User not found"),
+        Some(PRIVATE_USER) => return error(17, "Login: User required to be logged in"),
+        Some(RATE_LIMITED_USER) => {
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(json!({ "error": 29, "message": "Rate Limit Exceeded" })),
+            )
+                .into_response()
+        }
+        Some(FLAKY_USER) if flaky_attempt % 3 != 0 => {
+            return (StatusCode::SERVICE_UNAVAILABLE, "<html>503 Service Unavailable</html>").into_response()
+        }
+        Some(SLOW_USER) => tokio::time::sleep(SLOW_RESPONSE_DELAY).await,
+        _ => {}
+    }
+
+    match method.as_str() {
+        "auth.getToken" => Json(json!({ "token": UNAUTHORIZED_TOKEN })).into_response(),
+        "auth.getSession" => match params.get("token").map(String::as_str) {
+            Some(AUTHORIZED_TOKEN) => Json(json!({
+                "session": {
+                    "name": SESSION_USERNAME,
+                    "key": SESSION_KEY,
+                    "subscriber": 0
+                }
+            }))
+            .into_response(),
+            Some(UNAUTHORIZED_TOKEN) => error(14, "Unauthorized Token - This token has not been authorized"),
+            _ => error(4, "Invalid authentication token supplied"),
+        },
+        _ => {
+            let subject = params.get("user").or_else(|| params.get("artist"));
+            fixture(
+                &method,
+                subject.map(String::as_str),
+                params.get("period").map(String::as_str),
+            )
+                .map(IntoResponse::into_response)
+                .unwrap_or_else(|| error(3, "Invalid Method - No method with that name in this package"))
+        }
+    }
+}
+
+fn fixture(method: &str, subject: Option<&str>, period: Option<&str>) -> Option<Json<Value>> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lastfm");
+
+    let mut candidates = Vec::new();
+    if let Some(subject) = subject {
+        let slug = fixture_slug(subject);
+        if let Some(period) = period {
+            candidates.push(dir.join(format!("{}.{}.{}.json", method, slug, period)));
+        }
+        candidates.push(dir.join(format!("{}.{}.json", method, slug)));
+    }
+    candidates.push(dir.join(format!("{}.json", method)));
+
+    let path = candidates.into_iter().find(|p| p.exists())?;
+    let contents = std::fs::read_to_string(path).ok()?;
+    Some(Json(serde_json::from_str(&contents).expect("Invalid fixture JSON")))
+}
+
+fn fixture_slug(subject: &str) -> String {
+    subject
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn signature_is_valid(params: &HashMap<String, String>) -> bool {
+    let Some(sig) = params.get("api_sig") else {
+        return false;
+    };
+
+    let expected = api_signature(
+        params
+            .iter()
+            .filter(|(k, _)| k.as_str() != "api_sig")
+            .map(|(k, v)| (k.as_str(), v.as_str())),
+        TEST_API_SECRET,
+    );
+
+    *sig == expected
+}
+
+fn error(code: i32, message: &str) -> Response {
+    Json(json!({ "error": code, "message": message })).into_response()
+}
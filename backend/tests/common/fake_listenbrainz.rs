@@ -0,0 +1,75 @@
+//! Minimal stand-in for api.listenbrainz.org used by integration tests
+//!
+//! User statistics are answered from `tests/fixtures/listenbrainz/<entity>.json`, or
+//! `<entity>.<user>.json` when there is one for that user.
+
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+/// Username that doesn't exist
+pub const UNKNOWN_USER: &str = "no-such-user";
+/// User whose statistics haven't been calculated yet (204 No Content)
+pub const NEW_USER: &str = "new-user";
+
+pub struct FakeListenBrainz {
+    pub api_url: String,
+}
+
+impl FakeListenBrainz {
+    /// Start the server on an ephemeral local port
+    pub async fn spawn() -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind fake ListenBrainz server");
+        let addr = listener.local_addr().unwrap();
+
+        let app = Router::new()
+            .route("/1/user/:user/listen-count", get(listen_count))
+            .route("/1/stats/user/:user/:entity", get(user_stats));
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        Self {
+            api_url: format!("http://{}/", addr),
+        }
+    }
+}
+
+async fn listen_count(Path(user): Path<String>) -> Response {
+    match user.as_str() {
+        UNKNOWN_USER => not_found(),
+        NEW_USER => Json(json!({ "payload": { "count": 0 } })).into_response(),
+        _ => Json(json!({ "payload": { "count": 1234 } })).into_response(),
+    }
+}
+
+async fn user_stats(Path((user, entity)): Path<(String, String)>) -> Response {
+    match user.as_str() {
+        UNKNOWN_USER => not_found(),
+        NEW_USER => StatusCode::NO_CONTENT.into_response(),
+        _ => fixture(&entity, &user)
+            .map(IntoResponse::into_response)
+            .unwrap_or_else(not_found),
+    }
+}
+
+fn fixture(entity: &str, user: &str) -> Option<Json<Value>> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/listenbrainz");
+    let path = [dir.join(format!("{}.{}.json", entity, user.to_lowercase())), dir.join(format!("{}.json", entity))]
+        .into_iter()
+        .find(|p| p.exists())?;
+    let contents = std::fs::read_to_string(path).ok()?;
+    Some(Json(serde_json::from_str(&contents).expect("Invalid fixture JSON")))
+}
+
+fn not_found() -> Response {
+    (StatusCode::NOT_FOUND, Json(json!({ "code": 404, "error": "Cannot find user" }))).into_response()
+}
@@ -0,0 +1,100 @@
+#![allow(dead_code)]
+
+pub mod fake_lastfm;
+pub mod fake_listenbrainz;
+
+use lastfm_dating_backend::{
+    config::CacheBackend,
+    db,
+    services::compatibility_algorithm::{AlgorithmWeights, DEFAULT_ALGORITHM},
+    Config, DbPool,
+};
+use uuid::Uuid;
+
+pub const TEST_API_KEY: &str = "test-api-key";
+pub const TEST_API_SECRET: &str = "test-api-secret";
+
+/// Configuration pointing the Last.fm client at a local stand-in
+pub fn test_config(lastfm_api_url: &str) -> Config {
+    Config {
+        database_url: std::env::var("TEST_DATABASE_URL").unwrap_or_default(),
+        jwt_secret: "test-jwt-secret".to_string(),
+        lastfm_api_key: TEST_API_KEY.to_string(),
+        lastfm_api_secret: TEST_API_SECRET.to_string(),
+        lastfm_api_url: lastfm_api_url.to_string(),
+        lastfm_auth_url: "http://localhost/api/auth/".to_string(),
+        lastfm_session_encryption_key: "test-session-encryption-key".to_string(),
+        listenbrainz_api_url: "http://localhost/".to_string(),
+        listenbrainz_rate_limit_per_sec: 1000.0,
+        lastfm_rate_limit_per_sec: 1000.0,
+        lastfm_timeout_secs: 5,
+        lastfm_max_retries: 2,
+        lastfm_retry_backoff_ms: 1,
+        lastfm_resync_interval_secs: 0,
+        lastfm_resync_stale_after_secs: 3600,
+        now_playing_poll_interval_secs: 0,
+        pass_cooldown_days: 30,
+        compatibility_algorithm: DEFAULT_ALGORITHM.to_string(),
+        compatibility_weights: AlgorithmWeights::default(),
+        s3_endpoint: "http://localhost:9000".to_string(),
+        s3_bucket: "test".to_string(),
+        s3_region: "us-east-1".to_string(),
+        s3_access_key: String::new(),
+        s3_secret_key: String::new(),
+        cache_backend: CacheBackend::Memory,
+        redis_url: "redis://localhost:6379".to_string(),
+        cache_max_entries: 1000,
+        vapid_private_key: None,
+        vapid_public_key: None,
+        vapid_subject: None,
+        host: "127.0.0.1".to_string(),
+        port: 0,
+        allowed_origins: vec![],
+    }
+}
+
+/// Connect to the MySQL database named by `TEST_DATABASE_URL`
+/// The database must already have the migrations applied. Returns `None` (and the calling
+/// test should return early) when the variable is not set, so the suite still runs offline.
+/// On CI (`CI` set) a missing database is an error rather than a silent skip.
+pub async fn test_pool() -> Option<DbPool> {
+    let url = match std::env::var("TEST_DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) if std::env::var_os("CI").is_some() => {
+            panic!("TEST_DATABASE_URL must be set on CI so database tests run");
+        }
+        Err(_) => {
+            eprintln!("TEST_DATABASE_URL not set, skipping database test");
+            return None;
+        }
+    };
+
+    Some(db::create_pool(&url).await.expect("Failed to connect to test database"))
+}
+
+/// Insert a throwaway user, optionally with a connected Last.fm username
+pub async fn create_user(pool: &DbPool, lastfm_username: Option<&str>) -> String {
+    let id = Uuid::new_v4().to_string();
+
+    sqlx::query(
+        "INSERT INTO users (id, email, password_hash, name, lastfm_username, lastfm_connected_at)
+         VALUES (?, ?, 'not-a-hash', 'Test User', ?, IF(? IS NULL, NULL, NOW()))"
+    )
+    .bind(&id)
+    .bind(format!("{}@example.test", id))
+    .bind(lastfm_username)
+    .bind(lastfm_username)
+    .execute(pool)
+    .await
+    .expect("Failed to create test user");
+
+    id
+}
+
+pub async fn delete_user(pool: &DbPool, user_id: &str) {
+    sqlx::query("DELETE FROM users WHERE id = ?")
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .expect("Failed to delete test user");
+}
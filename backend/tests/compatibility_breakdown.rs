@@ -0,0 +1,45 @@
+mod common;
+
+use common::fake_lastfm::FakeLastFm;
+use lastfm_dating_backend::{
+    models::Period,
+    services::{ArtistTagService, CompatibilityService, LastFmService},
+};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_breakdown_weights_shared_artists_by_synced_listener_counts() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let lastfm_service = Arc::new(LastFmService::new(common::test_config(&server.api_url)));
+    let artist_tag_service = ArtistTagService::new(lastfm_service.clone());
+    let compatibility_service = CompatibilityService::new(lastfm_service.clone());
+    let rj = common::create_user(&pool, Some("rj")).await;
+    let alice = common::create_user(&pool, Some("alice")).await;
+
+    for (user_id, username) in [(&rj, "rj"), (&alice, "alice")] {
+        lastfm_service.sync_user_scrobbles(&pool, user_id, username).await.unwrap();
+        artist_tag_service.refresh_user_artist_listeners(&pool, user_id).await.unwrap();
+    }
+
+    let breakdown = compatibility_service
+        .explain_compatibility(&pool, &rj, &alice, Period::SixMonth)
+        .await
+        .unwrap();
+    let niche_weight = |name: &str| {
+        breakdown
+            .shared_artists
+            .iter()
+            .find(|a| a.name == name)
+            .unwrap_or_else(|| panic!("{} is not shared", name))
+            .niche_weight
+    };
+
+    // Slowdive has about a fifth of Radiohead's listeners (see the artist.getinfo fixtures)
+    assert!(niche_weight("Slowdive") > niche_weight("Radiohead"));
+    assert!(niche_weight("Radiohead") < 1.0);
+    assert_ne!(breakdown.niche_bonus, 0.0);
+
+    common::delete_user(&pool, &rj).await;
+    common::delete_user(&pool, &alice).await;
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use common::fake_lastfm::FakeLastFm;
+use lastfm_dating_backend::{
+    db::DbPool,
+    services::{
+        discover_service::MIN_COMPATIBILITY_SCORE, CompatibilityService, DiscoverService,
+        LastFmService, MatchService,
+    },
+};
+use std::sync::Arc;
+
+struct Services {
+    lastfm: Arc<LastFmService>,
+    discover: Arc<DiscoverService>,
+    matches: MatchService,
+}
+
+fn services(api_url: &str) -> Services {
+    let lastfm = Arc::new(LastFmService::new(common::test_config(api_url)));
+    let compatibility = Arc::new(CompatibilityService::new(lastfm.clone()));
+    Services {
+        lastfm,
+        discover: Arc::new(DiscoverService::new(compatibility.clone())),
+        matches: MatchService::new(compatibility),
+    }
+}
+
+/// Score of `prospect_id` in `user_id`'s feed. Tests share the database, so other tests'
+/// users can show up in these feeds too; only look at the pairs under test.
+async fn feed_score(pool: &DbPool, user_id: &str, prospect_id: &str) -> Option<f64> {
+    sqlx::query_scalar(
+        "SELECT CAST(compatibility_score AS DOUBLE) FROM discover_cache WHERE user_id = ? AND prospect_id = ?",
+    )
+    .bind(user_id)
+    .bind(prospect_id)
+    .fetch_optional(pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_rebuild_feed_scores_both_directions() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let services = services(&server.api_url);
+    let rj = common::create_user(&pool, Some("rj")).await;
+    let alice = common::create_user(&pool, Some("alice")).await;
+    let bob = common::create_user(&pool, Some("bob")).await;
+
+    for (user_id, username) in [(&rj, "rj"), (&alice, "alice"), (&bob, "bob")] {
+        services.lastfm.sync_user_scrobbles(&pool, user_id, username).await.unwrap();
+    }
+
+    let feed_size = services.discover.rebuild_feed(&pool, &rj).await.unwrap();
+    assert!(feed_size >= 1);
+
+    // Bob shares no artists with rj, so he doesn't make it into the feed
+    let score = feed_score(&pool, &rj, &alice).await.unwrap();
+    assert!(score >= MIN_COMPATIBILITY_SCORE);
+    assert_eq!(feed_score(&pool, &rj, &bob).await, None);
+
+    // Alice gets rj with the same score without rebuilding her own feed
+    assert_eq!(feed_score(&pool, &alice, &rj).await, Some(score));
+    assert!(DiscoverService::has_built_feed(&pool, &rj).await.unwrap());
+    assert!(!DiscoverService::has_built_feed(&pool, &bob).await.unwrap());
+
+    common::delete_user(&pool, &rj).await;
+    common::delete_user(&pool, &alice).await;
+    common::delete_user(&pool, &bob).await;
+}
+
+#[tokio::test]
+async fn test_empty_feed_still_counts_as_built() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let services = services(&server.api_url);
+    // Nobody else has any listening data in common with a user who has none
+    let loner = common::create_user(&pool, None).await;
+
+    assert!(!DiscoverService::has_built_feed(&pool, &loner).await.unwrap());
+    assert_eq!(services.discover.rebuild_feed(&pool, &loner).await.unwrap(), 0);
+    assert!(DiscoverService::has_built_feed(&pool, &loner).await.unwrap());
+
+    common::delete_user(&pool, &loner).await;
+}
+
+#[tokio::test]
+async fn test_first_feed_is_built_in_the_background() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let services = services(&server.api_url);
+    let rj = common::create_user(&pool, Some("rj")).await;
+    let alice = common::create_user(&pool, Some("alice")).await;
+    services.lastfm.sync_user_scrobbles(&pool, &rj, "rj").await.unwrap();
+    services.lastfm.sync_user_scrobbles(&pool, &alice, "alice").await.unwrap();
+
+    let build = services.discover.spawn_first_build(&pool, &rj).unwrap();
+    // Requests arriving while it runs don't start another
+    assert!(services.discover.spawn_first_build(&pool, &rj).is_none());
+    build.await.unwrap();
+
+    assert!(DiscoverService::has_built_feed(&pool, &rj).await.unwrap());
+    assert!(feed_score(&pool, &rj, &alice).await.is_some());
+
+    common::delete_user(&pool, &rj).await;
+    common::delete_user(&pool, &alice).await;
+}
+
+#[tokio::test]
+async fn test_likes_and_blocks_leave_the_feed() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let services = services(&server.api_url);
+    let rj = common::create_user(&pool, Some("rj")).await;
+    let alice = common::create_user(&pool, Some("alice")).await;
+
+    services.lastfm.sync_user_scrobbles(&pool, &rj, "rj").await.unwrap();
+    services.lastfm.sync_user_scrobbles(&pool, &alice, "alice").await.unwrap();
+    services.discover.rebuild_feed(&pool, &rj).await.unwrap();
+
+    // Liking only removes alice from rj's feed; rj stays in hers
+    services.matches.create_like(&pool, &rj, &alice).await.unwrap();
+    assert_eq!(feed_score(&pool, &rj, &alice).await, None);
+    assert!(feed_score(&pool, &alice, &rj).await.is_some());
+
+    // A rebuild doesn't bring alice back for rj
+    services.discover.rebuild_feed(&pool, &alice).await.unwrap();
+    assert_eq!(feed_score(&pool, &rj, &alice).await, None);
+    assert!(feed_score(&pool, &alice, &rj).await.is_some());
+
+    // A block removes the pair from both feeds, and rebuilds keep it that way
+    sqlx::query("INSERT INTO blocks (id, blocker_id, blocked_id) VALUES (UUID(), ?, ?)")
+        .bind(&alice)
+        .bind(&rj)
+        .execute(&pool)
+        .await
+        .unwrap();
+    DiscoverService::remove_pair(&pool, &alice, &rj).await.unwrap();
+    assert_eq!(feed_score(&pool, &alice, &rj).await, None);
+
+    services.discover.rebuild_feed(&pool, &rj).await.unwrap();
+    assert_eq!(feed_score(&pool, &alice, &rj).await, None);
+
+    common::delete_user(&pool, &rj).await;
+    common::delete_user(&pool, &alice).await;
+}
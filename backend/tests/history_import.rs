@@ -0,0 +1,73 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use common::fake_lastfm::FakeLastFm;
+use lastfm_dating_backend::services::{
+    compatibility_calibration,
+    history_import::{aggregate_listens, parse_export, ExportFormat},
+    CompatibilityService, LastFmService,
+};
+use std::sync::Arc;
+
+/// A Last.fm CSV export of someone into the same shoegaze and trip-hop as the default fixture
+fn lastfm_csv() -> String {
+    let artists = [("Slowdive", "Alison"), ("Radiohead", "Reckoner"), ("Portishead", "Roads"), ("Grouper", "Heavy Water")];
+    let mut csv = String::new();
+    for day in 0..60 {
+        let (artist, track) = artists[day % artists.len()];
+        let date = (Utc::now() - Duration::days(day as i64)).format("%d %b %Y %H:%M");
+        csv.push_str(&format!("{},,{},{}\n", artist, track, date));
+    }
+    csv
+}
+
+#[tokio::test]
+async fn test_imported_history_scores_against_synced_users() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let lastfm_service = Arc::new(LastFmService::new(common::test_config(&server.api_url)));
+    let compatibility_service = CompatibilityService::new(lastfm_service.clone());
+    let importer = common::create_user(&pool, None).await;
+    let synced = common::create_user(&pool, Some("rj")).await;
+
+    let csv = lastfm_csv();
+    let format = ExportFormat::detect(&csv);
+    assert_eq!(format, ExportFormat::LastFmCsv);
+    let data = aggregate_listens(&parse_export(format, &csv).unwrap(), Utc::now());
+    lastfm_service
+        .store_listening_data(&pool, &importer, &data, format.source())
+        .await
+        .unwrap();
+    lastfm_service.sync_user_scrobbles(&pool, &synced, "rj").await.unwrap();
+
+    let sources: Vec<String> = sqlx::query_scalar("SELECT DISTINCT source FROM scrobbles_cache WHERE user_id = ?")
+        .bind(&importer)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(sources, vec!["lastfm_export".to_string()]);
+
+    // The export has no MBIDs; Slowdive is stored under the one rj's sync knows it by
+    let slowdive_keys: Vec<String> = sqlx::query_scalar(
+        "SELECT DISTINCT artist_key FROM scrobbles_cache WHERE user_id = ? AND artist_name = 'Slowdive'",
+    )
+    .bind(&importer)
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(slowdive_keys, vec!["6a2a3db1-97a0-4d5a-92b9-7a5b8f7a62e5".to_string()]);
+
+    // Scored from scrobbles_cache alone, whatever the source
+    let score = compatibility_service
+        .calculate_compatibility(&pool, &importer, &synced)
+        .await
+        .unwrap();
+    assert!(score > 0.0);
+
+    // Imported listeners have no Last.fm username but still count towards calibration
+    let sample = compatibility_calibration::load_sample_users(&pool, u32::MAX).await.unwrap();
+    assert!(sample.contains(&importer));
+
+    common::delete_user(&pool, &importer).await;
+    common::delete_user(&pool, &synced).await;
+}
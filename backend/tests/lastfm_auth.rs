@@ -0,0 +1,88 @@
+mod common;
+
+use common::fake_lastfm::{self, FakeLastFm};
+use lastfm_dating_backend::{
+    errors::AppError,
+    services::{LastFmError, LastFmService},
+};
+
+#[tokio::test]
+async fn test_get_auth_token() {
+    let server = FakeLastFm::spawn().await;
+    let service = LastFmService::new(common::test_config(&server.api_url));
+
+    let token = service.get_auth_token().await.unwrap();
+    assert_eq!(token, fake_lastfm::UNAUTHORIZED_TOKEN);
+
+    let url = service.auth_url(&token);
+    assert!(url.contains("api_key=test-api-key"));
+    assert!(url.contains(&format!("token={}", token)));
+}
+
+#[tokio::test]
+async fn test_get_session_with_authorized_token() {
+    let server = FakeLastFm::spawn().await;
+    let service = LastFmService::new(common::test_config(&server.api_url));
+
+    let session = service.get_session(fake_lastfm::AUTHORIZED_TOKEN).await.unwrap();
+    assert_eq!(session.name, fake_lastfm::SESSION_USERNAME);
+    assert_eq!(session.key, fake_lastfm::SESSION_KEY);
+}
+
+#[tokio::test]
+async fn test_get_session_with_unauthorized_token() {
+    let server = FakeLastFm::spawn().await;
+    let service = LastFmService::new(common::test_config(&server.api_url));
+
+    let result = service.get_session(fake_lastfm::UNAUTHORIZED_TOKEN).await;
+    assert!(matches!(result, Err(AppError::LastFm(LastFmError::TokenNotAuthorized))));
+}
+
+#[tokio::test]
+async fn test_get_session_with_wrong_secret_is_rejected() {
+    let server = FakeLastFm::spawn().await;
+    let mut config = common::test_config(&server.api_url);
+    config.lastfm_api_secret = "not-the-secret".to_string();
+    let service = LastFmService::new(config);
+
+    let result = service.get_session(fake_lastfm::AUTHORIZED_TOKEN).await;
+    assert!(matches!(result, Err(AppError::LastFm(LastFmError::Api { code: 13, .. }))));
+}
+
+#[tokio::test]
+async fn test_connecting_takes_the_account_from_unverified_profiles() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let service = LastFmService::new(common::test_config(&server.api_url));
+    // Claimed by username only, with listening data synced from it
+    let claimant = common::create_user(&pool, Some(fake_lastfm::SESSION_USERNAME)).await;
+    service
+        .sync_user_scrobbles(&pool, &claimant, fake_lastfm::SESSION_USERNAME)
+        .await
+        .unwrap();
+    let owner = common::create_user(&pool, None).await;
+
+    sqlx::query("INSERT INTO lastfm_auth_tokens (token, user_id) VALUES (?, ?)")
+        .bind(fake_lastfm::AUTHORIZED_TOKEN)
+        .bind(&owner)
+        .execute(&pool)
+        .await
+        .unwrap();
+    service.complete_auth(&pool, &owner, fake_lastfm::AUTHORIZED_TOKEN).await.unwrap();
+
+    let claimed: Option<String> = sqlx::query_scalar("SELECT lastfm_username FROM users WHERE id = ?")
+        .bind(&claimant)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(claimed, None);
+    let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM scrobbles_cache WHERE user_id = ?")
+        .bind(&claimant)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(rows, 0);
+
+    common::delete_user(&pool, &claimant).await;
+    common::delete_user(&pool, &owner).await;
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::fake_lastfm::{self, FakeLastFm};
+use lastfm_dating_backend::services::{LastFmClient, LastFmError};
+use serde_json::Value;
+
+async fn top_artists(client: &LastFmClient, user: &str) -> Result<Value, LastFmError> {
+    client
+        .get("user.gettopartists", &[("user", user), ("period", "6month")])
+        .await
+}
+
+#[tokio::test]
+async fn test_retries_server_errors_until_success() {
+    let server = FakeLastFm::spawn().await;
+    let client = LastFmClient::new(&common::test_config(&server.api_url));
+
+    let data = top_artists(&client, fake_lastfm::FLAKY_USER).await.unwrap();
+    assert!(data["topartists"]["artist"].is_array());
+    assert_eq!(server.request_count("user.gettopartists"), 3);
+}
+
+#[tokio::test]
+async fn test_gives_up_after_max_retries_when_rate_limited() {
+    let server = FakeLastFm::spawn().await;
+    let config = common::test_config(&server.api_url);
+    let client = LastFmClient::new(&config);
+
+    let result = top_artists(&client, fake_lastfm::RATE_LIMITED_USER).await;
+    assert!(matches!(result, Err(LastFmError::RateLimited)));
+    assert_eq!(
+        server.request_count("user.gettopartists"),
+        config.lastfm_max_retries as usize + 1
+    );
+}
+
+#[tokio::test]
+async fn test_user_not_found_is_not_retried() {
+    let server = FakeLastFm::spawn().await;
+    let client = LastFmClient::new(&common::test_config(&server.api_url));
+
+    let result = top_artists(&client, fake_lastfm::UNKNOWN_USER).await;
+    assert!(matches!(result, Err(LastFmError::UserNotFound)));
+    assert_eq!(server.request_count("user.gettopartists"), 1);
+}
+
+#[tokio::test]
+async fn test_private_profile_has_user_facing_message() {
+    let server = FakeLastFm::spawn().await;
+    let client = LastFmClient::new(&common::test_config(&server.api_url));
+
+    let error = top_artists(&client, fake_lastfm::PRIVATE_USER).await.unwrap_err();
+    assert!(matches!(error, LastFmError::PrivateProfile));
+    assert!(error.user_message().contains("profile is private"));
+}
+
+#[tokio::test]
+async fn test_slow_responses_time_out() {
+    let server = FakeLastFm::spawn().await;
+    let mut config = common::test_config(&server.api_url);
+    config.lastfm_timeout_secs = 1;
+    config.lastfm_max_retries = 0;
+    let client = LastFmClient::new(&config);
+
+    let result = top_artists(&client, fake_lastfm::SLOW_USER).await;
+    assert!(matches!(result, Err(LastFmError::Timeout)));
+}
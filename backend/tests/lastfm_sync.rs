@@ -0,0 +1,153 @@
+mod common;
+
+use common::fake_lastfm::{self, FakeLastFm};
+use lastfm_dating_backend::{
+    errors::AppError,
+    models::Period,
+    services::{CompatibilityService, LastFmError, LastFmService},
+};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_sync_user_scrobbles_stores_top_artists() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let service = LastFmService::new(common::test_config(&server.api_url));
+    let user_id = common::create_user(&pool, Some("rj")).await;
+
+    let synced = service.sync_user_scrobbles(&pool, &user_id, "rj").await.unwrap();
+    assert_eq!(synced.artists.len(), Period::ALL.len());
+
+    let artists = &synced.artists[&Period::SixMonth];
+    assert_eq!(artists.len(), 8);
+    assert_eq!(artists[0].name, "Radiohead");
+    assert_eq!(artists[0].play_count, 1843);
+    // Empty MBIDs from Last.fm are stored as NULL
+    assert_eq!(artists[7].mbid, None);
+
+    let stored = service.get_user_top_artists(&pool, &user_id, Period::SixMonth, 50).await.unwrap();
+    let names: Vec<_> = stored.iter().map(|a| a.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "Radiohead",
+            "Slowdive",
+            "My Bloody Valentine",
+            "Portishead",
+            "Beach House",
+            "Cocteau Twins",
+            "Mazzy Star",
+            "Grouper",
+        ]
+    );
+
+    common::delete_user(&pool, &user_id).await;
+}
+
+#[tokio::test]
+async fn test_resync_replaces_previous_data() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let service = LastFmService::new(common::test_config(&server.api_url));
+    let user_id = common::create_user(&pool, Some("rj")).await;
+
+    service.sync_user_scrobbles(&pool, &user_id, "rj").await.unwrap();
+    service.sync_user_scrobbles(&pool, &user_id, "alice").await.unwrap();
+
+    let stored = service.get_user_top_artists(&pool, &user_id, Period::SixMonth, 50).await.unwrap();
+    assert_eq!(stored.len(), 5);
+    assert_eq!(stored[0].name, "Beach House");
+    assert_eq!(server.request_count("user.gettopartists"), 2 * Period::ALL.len());
+
+    common::delete_user(&pool, &user_id).await;
+}
+
+#[tokio::test]
+async fn test_sync_unknown_user_fails_without_touching_cache() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let service = LastFmService::new(common::test_config(&server.api_url));
+    let user_id = common::create_user(&pool, Some("rj")).await;
+
+    service.sync_user_scrobbles(&pool, &user_id, "rj").await.unwrap();
+
+    let result = service
+        .sync_user_scrobbles(&pool, &user_id, fake_lastfm::UNKNOWN_USER)
+        .await;
+    assert!(matches!(result, Err(AppError::LastFm(LastFmError::UserNotFound))));
+
+    let stored = service.get_user_top_artists(&pool, &user_id, Period::SixMonth, 50).await.unwrap();
+    assert_eq!(stored.len(), 8);
+
+    common::delete_user(&pool, &user_id).await;
+}
+
+#[tokio::test]
+async fn test_sync_keeps_periods_separate() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let service = LastFmService::new(common::test_config(&server.api_url));
+    let user_id = common::create_user(&pool, Some("rj")).await;
+
+    service.sync_user_scrobbles(&pool, &user_id, "rj").await.unwrap();
+    assert_eq!(server.request_count("user.gettopartists"), Period::ALL.len());
+
+    let this_week = service.get_user_top_artists(&pool, &user_id, Period::SevenDay, 50).await.unwrap();
+    assert_eq!(this_week.len(), 2);
+    assert_eq!(this_week[0].name, "Grouper");
+
+    let all_time = service.get_user_top_artists(&pool, &user_id, Period::Overall, 50).await.unwrap();
+    assert_eq!(all_time[0].name, "Radiohead");
+
+    let taste = service.get_user_taste(&pool, &user_id, 3).await.unwrap();
+    assert_eq!(taste[&Period::SevenDay].len(), 2);
+    assert_eq!(taste[&Period::Overall].len(), 3);
+
+    common::delete_user(&pool, &user_id).await;
+}
+
+#[tokio::test]
+async fn test_sync_stores_top_tracks_separately_from_artists() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let service = LastFmService::new(common::test_config(&server.api_url));
+    let user_id = common::create_user(&pool, Some("rj")).await;
+
+    let synced = service.sync_user_scrobbles(&pool, &user_id, "rj").await.unwrap();
+    assert_eq!(synced.tracks[&Period::SixMonth].len(), 5);
+    assert_eq!(server.request_count("user.gettoptracks"), Period::ALL.len());
+
+    let tracks = service.get_user_top_tracks(&pool, &user_id, Period::SixMonth, 50).await.unwrap();
+    assert_eq!(tracks[0].name, "Weird Fishes/Arpeggi");
+    assert_eq!(tracks[0].artist_name, "Radiohead");
+    assert_eq!(tracks[0].play_count, 212);
+
+    // Track rows must not leak into artist queries
+    let artists = service.get_user_top_artists(&pool, &user_id, Period::SixMonth, 50).await.unwrap();
+    assert_eq!(artists.len(), 8);
+
+    common::delete_user(&pool, &user_id).await;
+}
+
+#[tokio::test]
+async fn test_synced_users_can_be_scored() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let lastfm_service = Arc::new(LastFmService::new(common::test_config(&server.api_url)));
+    let compatibility_service = CompatibilityService::new(lastfm_service.clone());
+    let rj = common::create_user(&pool, Some("rj")).await;
+    let alice = common::create_user(&pool, Some("alice")).await;
+
+    lastfm_service.sync_user_scrobbles(&pool, &rj, "rj").await.unwrap();
+    lastfm_service.sync_user_scrobbles(&pool, &alice, "alice").await.unwrap();
+
+    // Scoring reads every period's artists and tracks back out of the cache
+    let tracks = lastfm_service.get_user_track_taste(&pool, &rj, 50).await.unwrap();
+    assert_eq!(tracks[&Period::SixMonth].len(), 5);
+
+    let score = compatibility_service.calculate_compatibility(&pool, &rj, &alice).await.unwrap();
+    assert!(score > 0.0);
+
+    common::delete_user(&pool, &rj).await;
+    common::delete_user(&pool, &alice).await;
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::fake_lastfm::FakeLastFm;
+use common::fake_listenbrainz::{self, FakeListenBrainz};
+use lastfm_dating_backend::{
+    models::Period,
+    services::{
+        ArtistSimilarityService, ArtistTagService, CompatibilityService, DiscoverService, LastFmService,
+        ListenBrainzService, ListeningAccount, ListeningProvider, MusicDnaService, SyncService,
+    },
+    AppError, Config,
+};
+use std::sync::Arc;
+
+async fn test_config() -> (Config, FakeLastFm, FakeListenBrainz) {
+    let lastfm = FakeLastFm::spawn().await;
+    let listenbrainz = FakeListenBrainz::spawn().await;
+    let mut config = common::test_config(&lastfm.api_url);
+    config.listenbrainz_api_url = listenbrainz.api_url.clone();
+    (config, lastfm, listenbrainz)
+}
+
+#[tokio::test]
+async fn test_fetch_listening_data() {
+    let (config, _lastfm, _listenbrainz) = test_config().await;
+    let service = ListenBrainzService::new(&config);
+
+    let data = service.fetch_listening_data("lb-user").await.unwrap();
+    let overall = &data.artists[&Period::Overall];
+    assert_eq!(overall[0].name, "Slowdive");
+    assert_eq!(overall[0].play_count, 412);
+    assert_eq!(overall[0].mbid.as_deref(), Some("6a2a3db1-97a0-4d5a-92b9-7a5b8f7a62e5"));
+    assert_eq!(overall[1].mbid, None);
+    assert_eq!(data.tracks[&Period::Overall][0].name, "Alison");
+
+    // Statistics not calculated yet is no data rather than an error
+    let data = service.fetch_listening_data(fake_listenbrainz::NEW_USER).await.unwrap();
+    assert!(data.artists.values().all(Vec::is_empty));
+
+    assert!(matches!(
+        service.listen_count(fake_listenbrainz::UNKNOWN_USER).await,
+        Err(AppError::NotFound(_))
+    ));
+    assert_eq!(service.listen_count("lb-user").await.unwrap(), 1234);
+}
+
+#[tokio::test]
+async fn test_listenbrainz_users_score_against_lastfm_users() {
+    let Some(pool) = common::test_pool().await else { return };
+    let (config, _lastfm, _listenbrainz) = test_config().await;
+    let lastfm_service = Arc::new(LastFmService::new(config.clone()));
+    let compatibility_service = Arc::new(CompatibilityService::new(lastfm_service.clone()));
+    let sync_service = SyncService::new(
+        &config,
+        lastfm_service.clone(),
+        Arc::new(ListenBrainzService::new(&config)),
+        Arc::new(ArtistTagService::new(lastfm_service.clone())),
+        Arc::new(ArtistSimilarityService::new(lastfm_service.clone())),
+        Arc::new(DiscoverService::new(compatibility_service.clone())),
+        Arc::new(MusicDnaService::new()),
+    );
+    let lastfm_user = common::create_user(&pool, Some("rj")).await;
+    let listenbrainz_user = common::create_user(&pool, None).await;
+    sqlx::query("UPDATE users SET listening_provider = 'listenbrainz', listenbrainz_username = 'lb-user' WHERE id = ?")
+        .bind(&listenbrainz_user)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    for user_id in [&lastfm_user, &listenbrainz_user] {
+        let account = ListeningAccount::for_user(&pool, user_id).await.unwrap().unwrap();
+        sync_service.sync_user(&pool, user_id, &account).await.unwrap();
+    }
+
+    let sources: Vec<String> = sqlx::query_scalar("SELECT DISTINCT source FROM scrobbles_cache WHERE user_id = ?")
+        .bind(&listenbrainz_user)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(sources, vec!["listenbrainz".to_string()]);
+
+    let score = compatibility_service
+        .calculate_compatibility(&pool, &lastfm_user, &listenbrainz_user)
+        .await
+        .unwrap();
+    assert!(score > 0.0);
+
+    common::delete_user(&pool, &lastfm_user).await;
+    common::delete_user(&pool, &listenbrainz_user).await;
+}
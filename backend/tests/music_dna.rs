@@ -0,0 +1,60 @@
+mod common;
+
+use common::fake_lastfm::FakeLastFm;
+use lastfm_dating_backend::services::{cache_service::keys, ArtistTagService, CacheService, LastFmService, MusicDnaService};
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_music_dna_is_cached_and_invalidated_on_sync() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let cache = Arc::new(CacheService::in_memory(1000));
+    let lastfm_service =
+        Arc::new(LastFmService::new(common::test_config(&server.api_url)).with_cache(cache.clone()));
+    let artist_tag_service = ArtistTagService::new(lastfm_service.clone());
+    let music_dna_service = MusicDnaService::new().with_cache(cache.clone());
+    let user_id = common::create_user(&pool, Some("rj")).await;
+
+    lastfm_service.sync_user_scrobbles(&pool, &user_id, "rj").await.unwrap();
+    artist_tag_service.refresh_user_artist_tags(&pool, &user_id).await.unwrap();
+
+    let dna = music_dna_service.refresh(&pool, &user_id).await.unwrap();
+    assert!(dna.artist_count > 0);
+    assert!(dna.label.is_some());
+    assert!(!dna.genres.is_empty());
+    assert!(cache.exists(&keys::music_dna(&user_id)).await.unwrap());
+
+    let cached = music_dna_service.get_music_dna(&pool, &user_id).await.unwrap();
+    assert_eq!(cached.label, dna.label);
+
+    // A sync drops the profile until it is recomputed
+    lastfm_service.sync_user_scrobbles(&pool, &user_id, "rj").await.unwrap();
+    assert!(!cache.exists(&keys::music_dna(&user_id)).await.unwrap());
+
+    common::delete_user(&pool, &user_id).await;
+}
+
+#[tokio::test]
+async fn test_mainstream_index_uses_synced_listener_counts() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let lastfm_service = Arc::new(LastFmService::new(common::test_config(&server.api_url)));
+    let artist_tag_service = ArtistTagService::new(lastfm_service.clone());
+    let user_id = common::create_user(&pool, Some("rj")).await;
+
+    lastfm_service.sync_user_scrobbles(&pool, &user_id, "rj").await.unwrap();
+    // Before the lookup nothing is known, which is neither niche nor mainstream
+    let unknown = MusicDnaService::new().refresh(&pool, &user_id).await.unwrap();
+    assert_eq!(unknown.mainstream_index, 0.5);
+
+    let refreshed = artist_tag_service.refresh_user_artist_listeners(&pool, &user_id).await.unwrap();
+    assert!(refreshed > 0);
+    assert_eq!(server.request_count("artist.getinfo"), refreshed);
+    assert_eq!(artist_tag_service.refresh_user_artist_listeners(&pool, &user_id).await.unwrap(), 0);
+
+    // Mostly millions of listeners, with Slowdive and Grouper pulling it down a little
+    let dna = MusicDnaService::new().refresh(&pool, &user_id).await.unwrap();
+    assert!(dna.mainstream_index > 0.7 && dna.mainstream_index < 1.0, "{}", dna.mainstream_index);
+
+    common::delete_user(&pool, &user_id).await;
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use common::fake_lastfm::FakeLastFm;
+use lastfm_dating_backend::services::{
+    websocket_service::WsMessageType, LastFmService, NowPlayingService, WebSocketService,
+};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+#[tokio::test]
+async fn test_fetch_now_playing() {
+    let server = FakeLastFm::spawn().await;
+    let service = LastFmService::new(common::test_config(&server.api_url));
+
+    let track = service.fetch_now_playing("rj").await.unwrap().unwrap();
+    assert_eq!(track.artist_name, "Slowdive");
+    assert_eq!(track.track_name, "Alison");
+    assert_eq!(track.album_name.as_deref(), Some("Souvlaki"));
+
+    // A single past scrobble comes back as a bare object, and isn't playing
+    assert_eq!(service.fetch_now_playing("bob").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_now_playing_is_pushed_to_matches_unless_hidden() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let config = common::test_config(&server.api_url);
+    let websocket_service = Arc::new(WebSocketService::new());
+    let service = NowPlayingService::new(
+        &config,
+        Arc::new(LastFmService::new(config.clone())),
+        websocket_service.clone(),
+    );
+    let listener = common::create_user(&pool, Some("rj")).await;
+    let other = common::create_user(&pool, None).await;
+    sqlx::query("INSERT INTO matches (id, user1_id, user2_id) VALUES (UUID(), ?, ?)")
+        .bind(&listener)
+        .bind(&other)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    websocket_service.register_connection(other.clone(), tx).await;
+
+    assert!(service.poll_user(&pool, &listener, "rj").await.unwrap());
+    match rx.try_recv().unwrap() {
+        WsMessageType::NowPlaying { user_id, track } => {
+            assert_eq!(user_id, listener);
+            assert_eq!(track.unwrap().track_name, "Alison");
+        }
+        other => panic!("Expected now_playing, got {:?}", other),
+    }
+    let playing = NowPlayingService::get_for_matches(&pool, &other).await.unwrap();
+    assert_eq!(playing.len(), 1);
+    assert_eq!(playing[0].user_id, listener);
+
+    // Same track, nothing to push
+    assert!(!service.poll_user(&pool, &listener, "rj").await.unwrap());
+    assert!(rx.try_recv().is_err());
+
+    // Hiding clears the track for matches and stops it from being stored again
+    sqlx::query("UPDATE users SET hide_now_playing = TRUE WHERE id = ?")
+        .bind(&listener)
+        .execute(&pool)
+        .await
+        .unwrap();
+    service.clear(&pool, &listener).await.unwrap();
+    assert!(matches!(rx.try_recv().unwrap(), WsMessageType::NowPlaying { track: None, .. }));
+    assert!(!service.poll_user(&pool, &listener, "rj").await.unwrap());
+    assert!(NowPlayingService::get_for_matches(&pool, &other).await.unwrap().is_empty());
+
+    common::delete_user(&pool, &listener).await;
+    common::delete_user(&pool, &other).await;
+}
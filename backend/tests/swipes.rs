@@ -0,0 +1,133 @@
+mod common;
+
+use lastfm_dating_backend::{
+    errors::AppError,
+    services::{compatibility_algorithm::DEFAULT_ALGORITHM, CompatibilityService, LastFmService, MatchService},
+};
+use std::sync::Arc;
+
+fn match_service() -> MatchService {
+    let lastfm_service = Arc::new(LastFmService::new(common::test_config("http://127.0.0.1:9")));
+    MatchService::new(Arc::new(CompatibilityService::new(lastfm_service)))
+}
+
+#[tokio::test]
+async fn test_undo_reverts_the_most_recent_swipe() {
+    let Some(pool) = common::test_pool().await else { return };
+    let service = match_service();
+    let me = common::create_user(&pool, None).await;
+    let liked = common::create_user(&pool, None).await;
+    let passed = common::create_user(&pool, None).await;
+
+    // Swipes in the same second are still told apart
+    service.create_like(&pool, &me, &liked).await.unwrap();
+    service.create_pass(&pool, &me, &passed).await.unwrap();
+
+    let undone = service.undo_last_swipe(&pool, &me).await.unwrap().unwrap();
+    assert_eq!((undone.kind.as_str(), undone.to_user_id.as_str()), ("pass", passed.as_str()));
+
+    let undone = service.undo_last_swipe(&pool, &me).await.unwrap().unwrap();
+    assert_eq!((undone.kind.as_str(), undone.to_user_id.as_str()), ("like", liked.as_str()));
+
+    assert!(service.undo_last_swipe(&pool, &me).await.unwrap().is_none());
+
+    for user_id in [&me, &liked, &passed] {
+        common::delete_user(&pool, user_id).await;
+    }
+}
+
+#[tokio::test]
+async fn test_passing_twice_keeps_one_pass() {
+    let Some(pool) = common::test_pool().await else { return };
+    let service = match_service();
+    let me = common::create_user(&pool, None).await;
+    let other = common::create_user(&pool, None).await;
+
+    service.create_pass(&pool, &me, &other).await.unwrap();
+    service.create_pass(&pool, &me, &other).await.unwrap();
+
+    let passes: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM passes WHERE from_user_id = ?")
+        .bind(&me)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(passes, 1);
+    assert!(matches!(
+        service.create_pass(&pool, &me, &me).await,
+        Err(AppError::Validation(_))
+    ));
+    assert!(matches!(
+        service.create_pass(&pool, &me, "no-such-user").await,
+        Err(AppError::NotFound(_))
+    ));
+
+    common::delete_user(&pool, &me).await;
+    common::delete_user(&pool, &other).await;
+}
+
+#[tokio::test]
+async fn test_likes_that_matched_cannot_be_undone() {
+    let Some(pool) = common::test_pool().await else { return };
+    let service = match_service();
+    let me = common::create_user(&pool, None).await;
+    let other = common::create_user(&pool, None).await;
+
+    service.create_like(&pool, &other, &me).await.unwrap();
+    let matched = service.create_like(&pool, &me, &other).await.unwrap().unwrap();
+    let algorithm_version: Option<String> =
+        sqlx::query_scalar("SELECT algorithm_version FROM matches WHERE id = ?")
+            .bind(&matched.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(algorithm_version.as_deref(), Some(DEFAULT_ALGORITHM));
+
+    assert!(matches!(
+        service.undo_last_swipe(&pool, &me).await,
+        Err(AppError::Validation(_))
+    ));
+
+    common::delete_user(&pool, &me).await;
+    common::delete_user(&pool, &other).await;
+}
+
+#[tokio::test]
+async fn test_simultaneous_mutual_likes_create_one_match() {
+    let Some(pool) = common::test_pool().await else { return };
+    let service = Arc::new(match_service());
+
+    for _ in 0..10 {
+        let me = common::create_user(&pool, None).await;
+        let other = common::create_user(&pool, None).await;
+
+        let (mine, theirs) = tokio::join!(
+            service.create_like(&pool, &me, &other),
+            service.create_like(&pool, &other, &me),
+        );
+        let (mine, theirs) = (mine.unwrap(), theirs.unwrap());
+        // Whichever like lands second sees the first and creates the match
+        assert!(mine.is_some() != theirs.is_some());
+
+        // Liking again returns the same match instead of creating another
+        let again = service.create_like(&pool, &me, &other).await.unwrap().unwrap();
+        assert_eq!(Some(again.id), mine.or(theirs).map(|m| m.id));
+        // Scored after the like's transaction, but before it returned
+        assert!(again.compatibility_score.is_some());
+
+        let matches: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM matches
+             WHERE (user1_id = ? AND user2_id = ?) OR (user1_id = ? AND user2_id = ?)",
+        )
+        .bind(&me)
+        .bind(&other)
+        .bind(&other)
+        .bind(&me)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(matches, 1);
+
+        common::delete_user(&pool, &me).await;
+        common::delete_user(&pool, &other).await;
+    }
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::fake_lastfm::{self, FakeLastFm};
+use lastfm_dating_backend::services::{
+    ArtistSimilarityService, ArtistTagService, CompatibilityService, DiscoverService, LastFmService, ListenBrainzService,
+    ListeningAccount, MusicDnaService, SyncService,
+};
+use std::sync::Arc;
+
+fn sync_service(api_url: &str) -> SyncService {
+    let config = common::test_config(api_url);
+    let lastfm_service = Arc::new(LastFmService::new(config.clone()));
+    let artist_tag_service = Arc::new(ArtistTagService::new(lastfm_service.clone()));
+    let artist_similarity_service = Arc::new(ArtistSimilarityService::new(lastfm_service.clone()));
+    let compatibility_service = Arc::new(CompatibilityService::new(lastfm_service.clone()));
+    let discover_service = Arc::new(DiscoverService::new(compatibility_service));
+    SyncService::new(
+        &config,
+        lastfm_service,
+        Arc::new(ListenBrainzService::new(&config)),
+        artist_tag_service,
+        artist_similarity_service,
+        discover_service,
+        Arc::new(MusicDnaService::new()),
+    )
+}
+
+#[tokio::test]
+async fn test_sync_user_records_success() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let service = sync_service(&server.api_url);
+    let user_id = common::create_user(&pool, Some("rj")).await;
+
+    assert!(SyncService::get_status(&pool, &user_id).await.unwrap().is_none());
+
+    service.sync_user(&pool, &user_id, &ListeningAccount::lastfm("rj")).await.unwrap();
+
+    let status = SyncService::get_status(&pool, &user_id).await.unwrap().unwrap();
+    assert_eq!(status.status, "ok");
+    assert!(status.last_synced_at.is_some());
+    assert_eq!(status.last_error, None);
+    assert_eq!(status.consecutive_failures, 0);
+
+    common::delete_user(&pool, &user_id).await;
+}
+
+#[tokio::test]
+async fn test_sync_user_records_failure_and_keeps_last_success() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let service = sync_service(&server.api_url);
+    let user_id = common::create_user(&pool, Some("rj")).await;
+
+    service.sync_user(&pool, &user_id, &ListeningAccount::lastfm("rj")).await.unwrap();
+    let synced_at = SyncService::get_status(&pool, &user_id).await.unwrap().unwrap().last_synced_at;
+
+    assert!(service.sync_user(&pool, &user_id, &ListeningAccount::lastfm(fake_lastfm::UNKNOWN_USER)).await.is_err());
+    assert!(service.sync_user(&pool, &user_id, &ListeningAccount::lastfm(fake_lastfm::UNKNOWN_USER)).await.is_err());
+
+    let status = SyncService::get_status(&pool, &user_id).await.unwrap().unwrap();
+    assert_eq!(status.status, "error");
+    assert!(status.last_error.is_some());
+    assert_eq!(status.consecutive_failures, 2);
+    assert_eq!(status.last_synced_at, synced_at);
+
+    common::delete_user(&pool, &user_id).await;
+}
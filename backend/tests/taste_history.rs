@@ -0,0 +1,48 @@
+mod common;
+
+use common::fake_lastfm::FakeLastFm;
+use lastfm_dating_backend::services::{LastFmService, TasteHistoryService};
+
+async fn snapshot_count(pool: &lastfm_dating_backend::DbPool, user_id: &str) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM taste_snapshots WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_syncs_record_taste_snapshots() {
+    let Some(pool) = common::test_pool().await else { return };
+    let server = FakeLastFm::spawn().await;
+    let service = LastFmService::new(common::test_config(&server.api_url));
+    let user_id = common::create_user(&pool, Some("rj")).await;
+
+    service.sync_user_scrobbles(&pool, &user_id, "rj").await.unwrap();
+    // A second sync the same day replaces the day's snapshot
+    service.sync_user_scrobbles(&pool, &user_id, "rj").await.unwrap();
+    assert_eq!(snapshot_count(&pool, &user_id).await, 1);
+
+    let timeline = TasteHistoryService::get_timeline(&pool, &user_id, 6).await.unwrap();
+    assert_eq!(timeline.months.len(), 1);
+    assert!(!timeline.months[0].top_artists.is_empty());
+    // Nothing to compare with yet
+    assert!(timeline.rising.is_empty() && timeline.fading.is_empty());
+    assert_eq!(TasteHistoryService::recently_got_into(&pool, &user_id).await.unwrap(), None);
+
+    // Pretend that snapshot is from last month, then sync a different chart
+    sqlx::query("UPDATE taste_snapshots SET taken_at = DATE_SUB(NOW(), INTERVAL 40 DAY) WHERE user_id = ?")
+        .bind(&user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    service.sync_user_scrobbles(&pool, &user_id, "bob").await.unwrap();
+
+    let timeline = TasteHistoryService::get_timeline(&pool, &user_id, 6).await.unwrap();
+    assert_eq!(timeline.months.len(), 2);
+    assert!(!timeline.rising.is_empty());
+    assert!(!timeline.fading.is_empty());
+    assert!(TasteHistoryService::recently_got_into(&pool, &user_id).await.unwrap().is_some());
+
+    common::delete_user(&pool, &user_id).await;
+}
@@ -1,9 +1,19 @@
 import { apiClient } from './client';
-import type { ConnectLastFmRequest, TopArtistsResponse } from '@/types/lastfm';
+import type { LastFmAuthRequest, SyncStatus, TopArtistsResponse } from '@/types/lastfm';
 
 export const lastfmApi = {
-  connect: async (data: ConnectLastFmRequest) => {
-    const response = await apiClient.post('/lastfm/connect', data);
+  startAuth: async (): Promise<LastFmAuthRequest> => {
+    const response = await apiClient.post('/lastfm/auth/start');
+    return response.data;
+  },
+
+  completeAuth: async (token: string) => {
+    const response = await apiClient.post('/lastfm/auth/complete', { token });
+    return response.data;
+  },
+
+  connectListenBrainz: async (username: string) => {
+    const response = await apiClient.post('/listenbrainz/connect', { username });
     return response.data;
   },
 
@@ -11,4 +21,19 @@ export const lastfmApi = {
     const response = await apiClient.post('/lastfm/sync');
     return response.data;
   },
+
+  importHistory: async (file: File, format?: 'lastfm_csv' | 'listenbrainz_json') => {
+    const data = new FormData();
+    data.append('file', file);
+    if (format) {
+      data.append('format', format);
+    }
+    const response = await apiClient.post('/listening-history/import', data);
+    return response.data;
+  },
+
+  getSyncStatus: async (): Promise<SyncStatus> => {
+    const response = await apiClient.get('/lastfm/sync/status');
+    return response.data;
+  },
 };
@@ -1,5 +1,5 @@
 import { apiClient } from './client';
-import type { Match } from '@/types/match';
+import type { DiscoverPage, Match, MatchNowPlaying } from '@/types/match';
 
 export const matchesApi = {
   createLike: async (toUserId: string) => {
@@ -7,18 +7,33 @@ export const matchesApi = {
     return response.data;
   },
 
+  createPass: async (toUserId: string) => {
+    const response = await apiClient.post('/passes', { to_user_id: toUserId });
+    return response.data;
+  },
+
+  undoLastSwipe: async () => {
+    const response = await apiClient.post('/swipes/undo');
+    return response.data;
+  },
+
   getMatches: async (): Promise<Match[]> => {
     const response = await apiClient.get('/matches');
     return response.data;
   },
 
+  getMatchesNowPlaying: async (): Promise<MatchNowPlaying[]> => {
+    const response = await apiClient.get('/matches/now-playing');
+    return response.data;
+  },
+
   deleteMatch: async (matchId: string) => {
     const response = await apiClient.delete(`/matches/${matchId}`);
     return response.data;
   },
 
-  getDiscoverProfiles: async () => {
-    const response = await apiClient.get('/discover');
+  getDiscoverProfiles: async (cursor?: string): Promise<DiscoverPage> => {
+    const response = await apiClient.get('/discover', { params: { cursor } });
     return response.data;
   },
 };
@@ -34,6 +34,10 @@ export const SwipeCard: React.FC<SwipeCardProps> = ({ profile }) => {
           <p className="text-gray-300 mt-2">{profile.bio}</p>
         )}
 
+        {profile.recently_into && (
+          <p className="text-sm text-gray-400 mt-2">Recently got into {profile.recently_into}</p>
+        )}
+
         {profile.common_artists.length > 0 && (
           <div className="mt-4">
             <p className="text-sm text-gray-400 flex items-center gap-2">
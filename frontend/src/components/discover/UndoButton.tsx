@@ -0,0 +1,21 @@
+import React from 'react';
+import { Button } from '@/components/ui/Button';
+import { RotateCcw } from 'lucide-react';
+
+interface UndoButtonProps {
+  onClick: () => void;
+  disabled?: boolean;
+}
+
+export const UndoButton: React.FC<UndoButtonProps> = ({ onClick, disabled }) => {
+  return (
+    <Button
+      variant="ghost"
+      onClick={onClick}
+      disabled={disabled}
+      className="rounded-full w-12 h-12 flex items-center justify-center border border-gray-600 self-center"
+    >
+      <RotateCcw size={20} />
+    </Button>
+  );
+};
@@ -1,19 +1,35 @@
-import { useMutation } from '@tanstack/react-query';
+import { useMutation, useQuery, useQueryClient } from '@tanstack/react-query';
 import { lastfmApi } from '@/api/lastfm';
 
 export const useLastFm = () => {
-  const connectMutation = useMutation({
-    mutationFn: (username: string) => lastfmApi.connect({ username }),
+  const queryClient = useQueryClient();
+
+  const { data: syncStatus } = useQuery({
+    queryKey: ['lastfm-sync-status'],
+    queryFn: lastfmApi.getSyncStatus,
+  });
+
+  const startAuthMutation = useMutation({
+    mutationFn: () => lastfmApi.startAuth(),
+  });
+
+  const completeAuthMutation = useMutation({
+    mutationFn: (token: string) => lastfmApi.completeAuth(token),
   });
 
   const syncMutation = useMutation({
     mutationFn: () => lastfmApi.sync(),
+    onSettled: () => {
+      queryClient.invalidateQueries({ queryKey: ['lastfm-sync-status'] });
+    },
   });
 
   return {
-    connect: connectMutation.mutate,
+    startAuth: startAuthMutation.mutateAsync,
+    completeAuth: completeAuthMutation.mutateAsync,
     sync: syncMutation.mutate,
-    isConnecting: connectMutation.isPending,
+    isConnecting: startAuthMutation.isPending || completeAuthMutation.isPending,
     isSyncing: syncMutation.isPending,
+    syncStatus,
   };
 };
@@ -1,4 +1,4 @@
-import { useQuery, useMutation, useQueryClient } from '@tanstack/react-query';
+import { useQuery, useInfiniteQuery, useMutation, useQueryClient } from '@tanstack/react-query';
 import { matchesApi } from '@/api/matches';
 
 export const useMatches = () => {
@@ -9,10 +9,18 @@ export const useMatches = () => {
     queryFn: matchesApi.getMatches,
   });
 
-  const { data: discoverProfiles, isLoading: isLoadingProfiles } = useQuery({
+  const {
+    data: discoverPages,
+    isLoading: isLoadingProfiles,
+    hasNextPage: hasMoreProfiles,
+    fetchNextPage: fetchMoreProfiles,
+  } = useInfiniteQuery({
     queryKey: ['discover'],
-    queryFn: matchesApi.getDiscoverProfiles,
+    queryFn: ({ pageParam }) => matchesApi.getDiscoverProfiles(pageParam),
+    initialPageParam: undefined as string | undefined,
+    getNextPageParam: (lastPage) => lastPage.next_cursor ?? undefined,
   });
+  const discoverProfiles = discoverPages?.pages.flatMap((page) => page.profiles);
 
   const likeMutation = useMutation({
     mutationFn: (userId: string) => matchesApi.createLike(userId),
@@ -22,6 +30,14 @@ export const useMatches = () => {
     },
   });
 
+  const passMutation = useMutation({
+    mutationFn: (userId: string) => matchesApi.createPass(userId),
+  });
+
+  const undoMutation = useMutation({
+    mutationFn: matchesApi.undoLastSwipe,
+  });
+
   const deleteMutation = useMutation({
     mutationFn: (matchId: string) => matchesApi.deleteMatch(matchId),
     onSuccess: () => {
@@ -34,7 +50,12 @@ export const useMatches = () => {
     discoverProfiles,
     isLoading,
     isLoadingProfiles,
+    hasMoreProfiles,
+    fetchMoreProfiles,
     likeUser: likeMutation.mutate,
+    passUser: passMutation.mutate,
+    undoLastSwipe: undoMutation.mutateAsync,
+    isUndoing: undoMutation.isPending,
     deleteMatch: deleteMutation.mutate,
     isLiking: likeMutation.isPending,
   };
@@ -2,12 +2,23 @@ import React, { useState } from 'react';
 import { SwipeCard } from '@/components/discover/SwipeCard';
 import { LikeButton } from '@/components/discover/LikeButton';
 import { DislikeButton } from '@/components/discover/DislikeButton';
+import { UndoButton } from '@/components/discover/UndoButton';
 import { useMatches } from '@/hooks/useMatches';
 import { Card } from '@/components/ui/Card';
 import { Heart } from 'lucide-react';
 
 export const Discover: React.FC = () => {
-  const { discoverProfiles, likeUser, isLoadingProfiles, isLiking } = useMatches();
+  const {
+    discoverProfiles,
+    likeUser,
+    passUser,
+    undoLastSwipe,
+    isUndoing,
+    isLoadingProfiles,
+    isLiking,
+    hasMoreProfiles,
+    fetchMoreProfiles,
+  } = useMatches();
   const [currentIndex, setCurrentIndex] = useState(0);
 
   if (isLoadingProfiles) {
@@ -42,10 +53,23 @@ export const Discover: React.FC = () => {
   };
 
   const handleDislike = () => {
+    if (currentProfile) {
+      passUser(currentProfile.id);
+    }
     handleNext();
   };
 
+  const handleUndo = async () => {
+    // The undone profile is the one before the current card
+    await undoLastSwipe();
+    setCurrentIndex(Math.max(currentIndex - 1, 0));
+  };
+
   const handleNext = () => {
+    // Load the next page a few cards before running out
+    if (hasMoreProfiles && currentIndex >= discoverProfiles.length - 3) {
+      fetchMoreProfiles();
+    }
     if (currentIndex < discoverProfiles.length - 1) {
       setCurrentIndex(currentIndex + 1);
     }
@@ -60,6 +84,7 @@ export const Discover: React.FC = () => {
 
         <div className="flex justify-center gap-8 mt-8">
           <DislikeButton onClick={handleDislike} disabled={isLiking} />
+          <UndoButton onClick={handleUndo} disabled={isUndoing || currentIndex === 0} />
           <LikeButton onClick={handleLike} disabled={isLiking} />
         </div>
 
@@ -1,26 +1,46 @@
 import React, { useState } from 'react';
 import { Card } from '@/components/ui/Card';
 import { Button } from '@/components/ui/Button';
-import { Input } from '@/components/ui/Input';
 import { useAuth } from '@/hooks/useAuth';
 import { useLastFm } from '@/hooks/useLastFm';
-import { useQuery } from '@tanstack/react-query';
+import { useQuery, useQueryClient } from '@tanstack/react-query';
 import { usersApi } from '@/api/users';
 import { Music, LogOut } from 'lucide-react';
 
+// Backend timestamps are UTC without an offset
+const formatTimeAgo = (timestamp: string): string => {
+  const seconds = Math.floor((Date.now() - new Date(`${timestamp}Z`).getTime()) / 1000);
+  if (seconds < 60) return 'just now';
+  const minutes = Math.floor(seconds / 60);
+  if (minutes < 60) return `${minutes} minute${minutes === 1 ? '' : 's'} ago`;
+  const hours = Math.floor(minutes / 60);
+  if (hours < 24) return `${hours} hour${hours === 1 ? '' : 's'} ago`;
+  const days = Math.floor(hours / 24);
+  return `${days} day${days === 1 ? '' : 's'} ago`;
+};
+
 export const Profile: React.FC = () => {
   const { logout } = useAuth();
-  const { connect, sync, isConnecting, isSyncing } = useLastFm();
-  const [lastfmUsername, setLastfmUsername] = useState('');
+  const { startAuth, completeAuth, sync, isConnecting, isSyncing, syncStatus } = useLastFm();
+  const queryClient = useQueryClient();
+  const [pendingToken, setPendingToken] = useState<string | null>(null);
 
   const { data: user, isLoading } = useQuery({
     queryKey: ['me'],
     queryFn: usersApi.getMe,
   });
 
-  const handleConnectLastFm = () => {
-    if (lastfmUsername) {
-      connect(lastfmUsername);
+  const handleConnectLastFm = async () => {
+    const { token, auth_url } = await startAuth();
+    setPendingToken(token);
+    window.open(auth_url, '_blank', 'noopener');
+  };
+
+  const handleCompleteLastFm = async () => {
+    if (pendingToken) {
+      await completeAuth(pendingToken);
+      setPendingToken(null);
+      queryClient.invalidateQueries({ queryKey: ['me'] });
     }
   };
 
@@ -61,6 +81,14 @@ export const Profile: React.FC = () => {
               <p className="text-gray-300 mb-4">
                 Connected as: <span className="text-primary">{user.lastfm_username}</span>
               </p>
+              <p className="text-sm text-gray-400 mb-4">
+                {syncStatus?.last_synced_at
+                  ? `Last synced ${formatTimeAgo(syncStatus.last_synced_at)}`
+                  : 'Not synced yet'}
+                {syncStatus?.status === 'error' && syncStatus.last_error && (
+                  <span className="block text-red-400">Last sync failed: {syncStatus.last_error}</span>
+                )}
+              </p>
               <Button onClick={handleSync} disabled={isSyncing}>
                 {isSyncing ? 'Syncing...' : 'Sync Scrobbles'}
               </Button>
@@ -70,16 +98,15 @@ export const Profile: React.FC = () => {
               <p className="text-gray-400">
                 Connect your Last.fm account to find matches based on your music taste
               </p>
-              <div className="flex gap-2">
-                <Input
-                  placeholder="Last.fm username"
-                  value={lastfmUsername}
-                  onChange={(e) => setLastfmUsername(e.target.value)}
-                />
+              {pendingToken ? (
+                <Button onClick={handleCompleteLastFm} disabled={isConnecting}>
+                  {isConnecting ? 'Connecting...' : "I've authorized on Last.fm"}
+                </Button>
+              ) : (
                 <Button onClick={handleConnectLastFm} disabled={isConnecting}>
-                  {isConnecting ? 'Connecting...' : 'Connect'}
+                  {isConnecting ? 'Connecting...' : 'Connect with Last.fm'}
                 </Button>
-              </div>
+              )}
             </div>
           )}
         </Card>
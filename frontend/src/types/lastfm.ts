@@ -10,6 +10,36 @@ export interface TopArtistsResponse {
   message: string;
 }
 
-export interface ConnectLastFmRequest {
-  username: string;
+export interface LastFmAuthRequest {
+  token: string;
+  auth_url: string;
+}
+
+export interface SyncStatus {
+  status: 'syncing' | 'ok' | 'error' | null;
+  last_synced_at: string | null;
+  last_attempt_at: string | null;
+  last_error: string | null;
+}
+
+export interface GenreWeight {
+  genre: string;
+  weight: number;
+}
+
+export interface MusicDna {
+  label: string | null;
+  genres: GenreWeight[];
+  mainstream_index: number;
+  diversity: number;
+  eras: { era: string; weight: number }[];
+  artist_count: number;
+}
+
+export interface PublicMusicDna {
+  label: string | null;
+  top_genres: string[];
+  mainstream_index: number;
+  diversity: number;
+  eras: string[];
 }
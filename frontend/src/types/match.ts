@@ -1,8 +1,11 @@
+import type { UserProfile } from './user';
+
 export interface Match {
   id: string;
   user1_id: string;
   user2_id: string;
   compatibility_score?: number;
+  algorithm_version?: string;
   created_at: string;
 }
 
@@ -21,3 +24,20 @@ export interface Message {
   read_at?: string;
   created_at: string;
 }
+
+export interface NowPlayingTrack {
+  artist_name: string;
+  artist_mbid?: string | null;
+  track_name: string;
+  album_name?: string | null;
+}
+
+export interface MatchNowPlaying {
+  user_id: string;
+  track: NowPlayingTrack;
+}
+
+export interface DiscoverPage {
+  profiles: UserProfile[];
+  next_cursor?: string | null;
+}
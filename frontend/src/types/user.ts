@@ -8,8 +8,11 @@ export interface User {
   looking_for?: string;
   lastfm_username?: string;
   lastfm_connected_at?: string;
+  listening_provider: 'lastfm' | 'listenbrainz';
+  listenbrainz_username?: string;
   latitude?: number;
   longitude?: number;
+  hide_now_playing: boolean;
   created_at: string;
   updated_at: string;
 }
@@ -30,6 +33,8 @@ export interface UpdateUser {
   looking_for?: string;
   latitude?: number;
   longitude?: number;
+  hide_now_playing?: boolean;
+  listening_provider?: 'lastfm' | 'listenbrainz';
 }
 
 export interface UserProfile {
@@ -40,5 +45,6 @@ export interface UserProfile {
   photos: string[];
   top_artists: string[];
   common_artists: string[];
+  recently_into?: string | null;
   compatibility_score: number;
 }